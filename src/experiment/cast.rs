@@ -0,0 +1,418 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+use crate::experiment::data::{CValue, CValueType};
+use crate::experiment::trans::{cvalue_to_json, json_to_cvalue};
+
+/// Why a value could not be cast, see `cast`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastError {
+    pub from: CValueType,
+    pub to: CValueType,
+    pub message: String,
+}
+
+impl fmt::Display for CastError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not cast {:?} to {:?}: {}", self.from, self.to, self.message)
+    }
+}
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+fn error(value: &CValue, to: CValueType, message: &str) -> CastError {
+    CastError { from: value.get_dtype(), to, message: message.to_string() }
+}
+
+/// Parses timestamps as epoch milliseconds, how CrateDB stores them. Accepts RFC 3339,
+/// `YYYY-MM-DD HH:MM:SS[.fff]`, `YYYY-MM-DDTHH:MM:SS[.fff]` (UTC) and `YYYY-MM-DD`.
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.timestamp_millis());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+        if let Ok(timestamp) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(timestamp.and_utc().timestamp_millis());
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis())
+}
+
+/// Returns an integer value as i64, floats only if they have no fractional part and fit.
+fn to_i64(value: &CValue, to: CValueType) -> Result<i64, CastError> {
+    let from_f64 = |v: f64| {
+        if !v.is_finite() || v.fract() != 0.0 {
+            Err(error(value, to, "the number is not an integer"))
+        } else if v < i64::MIN as f64 || v >= i64::MAX as f64 {
+            Err(error(value, to, "the number overflows"))
+        } else {
+            Ok(v as i64)
+        }
+    };
+
+    match value {
+        CValue::Bool(v) => Ok(*v as i64),
+        CValue::I16(v) => Ok(*v as i64),
+        CValue::I32(v) => Ok(*v as i64),
+        CValue::I64(v) => Ok(*v),
+        CValue::Double32(v) => from_f64(*v as f64),
+        CValue::Double64(v) => from_f64(*v),
+        CValue::Timestamp(v) => Ok(*v),
+        CValue::String(s) | CValue::Decimal(s) => {
+            let s = s.trim();
+            if let Ok(v) = s.parse::<i64>() {
+                return Ok(v);
+            }
+            if let Ok(v) = s.parse::<f64>() {
+                return from_f64(v);
+            }
+            if to == CValueType::I64 {
+                if let Some(v) = parse_timestamp(s) {
+                    return Ok(v);
+                }
+            }
+            Err(error(value, to, &format!("'{}' is not a number", s)))
+        }
+        _ => Err(error(value, to, "not a scalar"))
+    }
+}
+
+/// Returns a number as f64, integers only if the f64 holds them exactly, i.e. up to 2^53.
+fn to_f64(value: &CValue, to: CValueType) -> Result<f64, CastError> {
+    let from_i64 = |v: i64| {
+        let float = v as f64;
+        // i64::MAX rounds up to 2^63, which saturates back to i64::MAX.
+        if float >= i64::MAX as f64 || float as i64 != v {
+            Err(error(value, to, "the integer can not be represented exactly as a float"))
+        } else {
+            Ok(float)
+        }
+    };
+
+    match value {
+        CValue::Bool(v) => Ok(*v as i64 as f64),
+        CValue::I16(v) => Ok(*v as f64),
+        CValue::I32(v) => Ok(*v as f64),
+        CValue::I64(v) => from_i64(*v),
+        CValue::Double32(v) => Ok(*v as f64),
+        CValue::Double64(v) => Ok(*v),
+        CValue::String(s) | CValue::Decimal(s) => match s.trim().parse::<i64>() {
+            Ok(v) => from_i64(v),
+            Err(_) => s.trim().parse::<f64>().map_err(|_| error(value, to, &format!("'{}' is not a number", s)))
+        },
+        _ => Err(error(value, to, "not a scalar"))
+    }
+}
+
+fn to_bool(value: &CValue) -> Result<bool, CastError> {
+    let to = CValueType::Bool;
+    match value {
+        CValue::Bool(v) => Ok(*v),
+        CValue::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" | "1" => Ok(true),
+            "false" | "f" | "no" | "n" | "0" => Ok(false),
+            _ => Err(error(value, to, &format!("'{}' is not a boolean", s)))
+        },
+        CValue::I16(_) | CValue::I32(_) | CValue::I64(_) | CValue::Double32(_) | CValue::Double64(_) => {
+            match to_f64(value, to)? {
+                0.0 => Ok(false),
+                1.0 => Ok(true),
+                _ => Err(error(value, to, "only 0 and 1 are booleans"))
+            }
+        }
+        _ => Err(error(value, to, "not a scalar"))
+    }
+}
+
+fn to_string(value: &CValue) -> String {
+    match value {
+        CValue::String(s) => s.clone(),
+        CValue::Bool(v) => v.to_string(),
+        CValue::I16(v) => v.to_string(),
+        CValue::I32(v) => v.to_string(),
+        CValue::I64(v) => v.to_string(),
+        CValue::Double32(v) => v.to_string(),
+        CValue::Double64(v) => v.to_string(),
+        CValue::Timestamp(_) | CValue::Date(_) | CValue::Decimal(_) | CValue::Bytes(_) | CValue::Ip(_) => value.to_string(),
+        other => cvalue_to_json(other).to_string()
+    }
+}
+
+/// Returns the elements of an array, a JSON array string or a scalar, which is a single element.
+fn to_elements(value: &CValue, to: CValueType) -> Result<Vec<CValue>, CastError> {
+    Ok(match value {
+        CValue::VecString(v) => v.iter().cloned().map(CValue::String).collect(),
+        CValue::VecI32(v) => v.iter().cloned().map(CValue::I32).collect(),
+        CValue::VecI64(v) => v.iter().cloned().map(CValue::I64).collect(),
+        CValue::VecF32(v) => v.iter().cloned().map(CValue::Double32).collect(),
+        CValue::VecF64(v) => v.iter().cloned().map(CValue::Double64).collect(),
+        CValue::VecDyn(v) => v.clone(),
+        CValue::String(s) if s.trim_start().starts_with('[') => {
+            match serde_json::from_str::<Value>(s) {
+                Ok(Value::Array(values)) => values.into_iter().map(json_to_cvalue).collect(),
+                _ => return Err(error(value, to, "invalid JSON array"))
+            }
+        }
+        CValue::Object(_) => return Err(error(value, to, "an object is not an array")),
+        scalar => vec![scalar.clone()]
+    })
+}
+
+fn cast_elements<T>(value: &CValue, to: CValueType, element_type: CValueType, unwrap: fn(CValue) -> Option<T>) -> Result<Vec<T>, CastError> {
+    to_elements(value, to)?
+        .iter()
+        .map(|element| {
+            let element = cast(element, element_type).map_err(|e| error(value, to, &e.message))?;
+            unwrap(element).ok_or(error(value, to, "null element"))
+        })
+        .collect()
+}
+
+fn to_timestamp(value: &CValue, to: CValueType) -> Result<i64, CastError> {
+    match value {
+        CValue::Date(days) => Ok(*days as i64 * MILLIS_PER_DAY),
+        CValue::String(s) => match s.trim().parse::<i64>() {
+            Ok(v) => Ok(v),
+            Err(_) => parse_timestamp(s).ok_or(error(value, to, &format!("'{}' is not a timestamp", s)))
+        },
+        _ => to_i64(value, to)
+    }
+}
+
+fn to_date(value: &CValue, to: CValueType) -> Result<i32, CastError> {
+    let days = match value {
+        CValue::Timestamp(ms) => ms.div_euclid(MILLIS_PER_DAY),
+        CValue::String(s) => parse_timestamp(s).ok_or(error(value, to, &format!("'{}' is not a date", s)))?.div_euclid(MILLIS_PER_DAY),
+        _ => return Err(error(value, to, "only timestamps and strings can be cast to dates"))
+    };
+    i32::try_from(days).map_err(|_| error(value, to, "the date overflows"))
+}
+
+fn to_decimal(value: &CValue, to: CValueType) -> Result<String, CastError> {
+    match value {
+        // Kept as written, parsing them would lose the precision of long decimals.
+        CValue::String(s) if s.trim().parse::<f64>().is_ok_and(|v| v.is_finite()) => Ok(s.trim().to_string()),
+        CValue::I16(_) | CValue::I32(_) | CValue::I64(_) => Ok(to_i64(value, to)?.to_string()),
+        // The shortest text that reads back as the same float, 0.1f32 is "0.1" and not the digits of its f64.
+        CValue::Double32(v) if v.is_finite() => Ok(v.to_string()),
+        CValue::Double64(v) if v.is_finite() => Ok(v.to_string()),
+        _ => Err(error(value, to, "not a finite number"))
+    }
+}
+
+fn to_bytes(value: &CValue, to: CValueType) -> Result<Vec<u8>, CastError> {
+    match value {
+        CValue::String(s) => match s.strip_prefix("\\x") {
+            Some(hex) if hex.len() % 2 == 0 => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| error(value, to, "invalid hex")))
+                .collect(),
+            Some(_) => Err(error(value, to, "invalid hex")),
+            None => Ok(s.as_bytes().to_vec())
+        },
+        _ => Err(error(value, to, "only strings can be cast to bytes"))
+    }
+}
+
+fn to_geo_point(value: &CValue, to: CValueType) -> Result<(f64, f64), CastError> {
+    let elements = match value {
+        CValue::String(s) if s.trim().to_uppercase().starts_with("POINT") => {
+            let coordinates = s.trim()[5..].trim().trim_start_matches('(').trim_end_matches(')');
+            coordinates.split_whitespace().map(|c| CValue::String(c.to_string())).collect()
+        }
+        CValue::VecString(_) | CValue::VecI32(_) | CValue::VecI64(_) | CValue::VecF32(_) | CValue::VecF64(_) | CValue::VecDyn(_) | CValue::String(_) => to_elements(value, to)?,
+        _ => return Err(error(value, to, "only [lon, lat] arrays and WKT points can be cast to geo points"))
+    };
+    let [lon, lat] = elements.as_slice() else {
+        return Err(error(value, to, "a geo point has two coordinates"));
+    };
+    let (lon, lat) = (to_f64(lon, to)?, to_f64(lat, to)?);
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(error(value, to, "the coordinates are out of range"));
+    }
+    Ok((lon, lat))
+}
+
+/// Casts `value` to `to`.
+///
+/// * Integers widen freely and narrow only if they fit, floats become integers only without a
+///   fractional part. Integers become floats only if the float holds them exactly, e.g. up to
+///   2^53 for `Double64`, and floats narrow to `Double32` only if it holds them exactly.
+/// * Strings are trimmed and parsed; `I64` also accepts timestamps, as epoch milliseconds.
+/// * Booleans are `true`/`false`, `t`/`f`, `yes`/`no`, `y`/`n` or `1`/`0`, numbers only 1 and 0.
+/// * Anything becomes a String, arrays and objects as JSON.
+/// * Arrays are cast element by element, a scalar becomes a single element array and a JSON array
+///   string is parsed.
+/// * JSON object strings become an Object.
+/// * Timestamps are epoch milliseconds, from integers, dates or strings (see `parse_timestamp`);
+///   dates are the day of a timestamp or string.
+/// * Decimals keep the text of numeric strings, bytes are a string's UTF-8 or its `\x` hex.
+/// * IPs are parsed from strings, geo points from `[lon, lat]` arrays or WKT `POINT (lon lat)`.
+///
+/// Nulls stay null. Returns an error if the value can not be converted without losing it.
+pub fn cast(value: &CValue, to: CValueType) -> Result<CValue, CastError> {
+    if matches!(value, CValue::None | CValue::Unknown) || value.is_dtype(to) {
+        return Ok(value.clone());
+    }
+
+    let overflow = |_| error(value, to, "the number overflows");
+    match to {
+        CValueType::Bool => Ok(CValue::Bool(to_bool(value)?)),
+        CValueType::I16 => Ok(CValue::I16(i16::try_from(to_i64(value, to)?).map_err(overflow)?)),
+        CValueType::I32 => Ok(CValue::I32(i32::try_from(to_i64(value, to)?).map_err(overflow)?)),
+        CValueType::I64 => Ok(CValue::I64(to_i64(value, to)?)),
+        CValueType::Double32 => {
+            let v = to_f64(value, to)?;
+            if v.is_finite() && v.abs() > f32::MAX as f64 {
+                return Err(error(value, to, "the number overflows"));
+            }
+            if !v.is_nan() && v as f32 as f64 != v {
+                return Err(error(value, to, "the number can not be represented exactly as a f32"));
+            }
+            Ok(CValue::Double32(v as f32))
+        }
+        CValueType::Double64 => Ok(CValue::Double64(to_f64(value, to)?)),
+        CValueType::String => Ok(CValue::String(to_string(value))),
+        CValueType::VecString => Ok(CValue::VecString(cast_elements(value, to, CValueType::String, |v| match v { CValue::String(v) => Some(v), _ => None })?)),
+        CValueType::VecI32 => Ok(CValue::VecI32(cast_elements(value, to, CValueType::I32, |v| match v { CValue::I32(v) => Some(v), _ => None })?)),
+        CValueType::VecI64 => Ok(CValue::VecI64(cast_elements(value, to, CValueType::I64, |v| match v { CValue::I64(v) => Some(v), _ => None })?)),
+        CValueType::VecF32 => Ok(CValue::VecF32(cast_elements(value, to, CValueType::Double32, |v| match v { CValue::Double32(v) => Some(v), _ => None })?)),
+        CValueType::VecF64 => Ok(CValue::VecF64(cast_elements(value, to, CValueType::Double64, |v| match v { CValue::Double64(v) => Some(v), _ => None })?)),
+        CValueType::VecDyn => Ok(CValue::VecDyn(to_elements(value, to)?)),
+        CValueType::Object => match value {
+            CValue::String(s) => match serde_json::from_str::<Value>(s) {
+                Ok(object @ Value::Object(_)) => Ok(json_to_cvalue(object)),
+                _ => Err(error(value, to, "invalid JSON object"))
+            },
+            _ => Err(error(value, to, "only JSON strings can be cast to objects"))
+        },
+        CValueType::Timestamp => Ok(CValue::Timestamp(to_timestamp(value, to)?)),
+        CValueType::Date => Ok(CValue::Date(to_date(value, to)?)),
+        CValueType::Decimal => Ok(CValue::Decimal(to_decimal(value, to)?)),
+        CValueType::Bytes => Ok(CValue::Bytes(to_bytes(value, to)?)),
+        CValueType::Ip => match value {
+            CValue::String(s) => s.trim().parse().map(CValue::Ip).map_err(|_| error(value, to, &format!("'{}' is not an IP", s))),
+            _ => Err(error(value, to, "only strings can be cast to IPs"))
+        },
+        CValueType::GeoPoint => to_geo_point(value, to).map(|(lon, lat)| CValue::GeoPoint(lon, lat)),
+        CValueType::None | CValueType::Unknown => Ok(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::experiment::cast::{cast, parse_timestamp};
+    use crate::experiment::data::{CValue, CValueType};
+
+    #[test]
+    fn test_cast_numbers() {
+        assert!(matches!(cast(&CValue::I16(7), CValueType::I64), Ok(CValue::I64(7))));
+        assert!(matches!(cast(&CValue::I64(70_000), CValueType::I32), Ok(CValue::I32(70_000))));
+        assert!(cast(&CValue::I64(70_000), CValueType::I16).is_err());
+        assert!(cast(&CValue::I64(i64::MAX), CValueType::I32).is_err());
+        assert!(matches!(cast(&CValue::Double64(3.0), CValueType::I32), Ok(CValue::I32(3))));
+        assert!(cast(&CValue::Double64(3.5), CValueType::I32).is_err());
+        assert!(cast(&CValue::Double64(f64::NAN), CValueType::I64).is_err());
+        assert!(cast(&CValue::Double64(1e300), CValueType::Double32).is_err());
+        assert!(matches!(cast(&CValue::I32(2), CValueType::Double32), Ok(CValue::Double32(v)) if v == 2.0));
+        assert!(matches!(cast(&CValue::Double64(0.5), CValueType::Double32), Ok(CValue::Double32(v)) if v == 0.5));
+        assert!(matches!(cast(&CValue::Double64(f64::NAN), CValueType::Double32), Ok(CValue::Double32(v)) if v.is_nan()));
+        assert!(matches!(cast(&CValue::Double32(0.1), CValueType::Double64), Ok(CValue::Double64(v)) if v == 0.1f32 as f64));
+        assert!(matches!(cast(&CValue::String(" 42 ".to_string()), CValueType::I16), Ok(CValue::I16(42))));
+        assert!(matches!(cast(&CValue::String("1e3".to_string()), CValueType::I32), Ok(CValue::I32(1000))));
+        assert!(matches!(cast(&CValue::String("2.5".to_string()), CValueType::Double64), Ok(CValue::Double64(v)) if v == 2.5));
+        assert!(cast(&CValue::String("abc".to_string()), CValueType::I32).is_err());
+        assert!(matches!(cast(&CValue::Bool(true), CValueType::I32), Ok(CValue::I32(1))));
+        assert!(cast(&CValue::Object(HashMap::new()), CValueType::I64).is_err());
+        assert!(matches!(cast(&CValue::None, CValueType::I32), Ok(CValue::None)));
+    }
+
+    #[test]
+    fn test_cast_bools_and_strings() {
+        assert!(matches!(cast(&CValue::String("Yes".to_string()), CValueType::Bool), Ok(CValue::Bool(true))));
+        assert!(matches!(cast(&CValue::String("0".to_string()), CValueType::Bool), Ok(CValue::Bool(false))));
+        assert!(matches!(cast(&CValue::I64(1), CValueType::Bool), Ok(CValue::Bool(true))));
+        assert!(cast(&CValue::I64(2), CValueType::Bool).is_err());
+        assert!(cast(&CValue::String("maybe".to_string()), CValueType::Bool).is_err());
+
+        assert!(matches!(cast(&CValue::I32(5), CValueType::String), Ok(CValue::String(s)) if s == "5"));
+        assert!(matches!(cast(&CValue::Bool(false), CValueType::String), Ok(CValue::String(s)) if s == "false"));
+        assert!(matches!(cast(&CValue::VecI32(vec![1, 2]), CValueType::String), Ok(CValue::String(s)) if s == "[1,2]"));
+    }
+
+    #[test]
+    fn test_cast_timestamps() {
+        assert_eq!(parse_timestamp("2024-01-02T03:04:05Z"), Some(1704164645000));
+        assert_eq!(parse_timestamp("2024-01-02T05:04:05+02:00"), Some(1704164645000));
+        assert_eq!(parse_timestamp("2024-01-02 03:04:05.250"), Some(1704164645250));
+        assert_eq!(parse_timestamp("2024-01-02"), Some(1704153600000));
+        assert_eq!(parse_timestamp("yesterday"), None);
+
+        assert!(matches!(cast(&CValue::String("2024-01-02".to_string()), CValueType::I64), Ok(CValue::I64(1704153600000))));
+        // Epoch milliseconds do not fit narrower integers.
+        assert!(cast(&CValue::String("2024-01-02".to_string()), CValueType::I32).is_err());
+    }
+
+    #[test]
+    fn test_cast_arrays_and_objects() {
+        assert!(matches!(cast(&CValue::I32(1), CValueType::VecI64), Ok(CValue::VecI64(v)) if v == vec![1]));
+        assert!(matches!(cast(&CValue::VecI32(vec![1, 2]), CValueType::VecF64), Ok(CValue::VecF64(v)) if v == vec![1.0, 2.0]));
+        assert!(cast(&CValue::VecI64(vec![1, i64::MAX]), CValueType::VecI32).is_err());
+        assert!(matches!(cast(&CValue::VecDyn(vec![CValue::I32(1), CValue::String("2".to_string())]), CValueType::VecString), Ok(CValue::VecString(v)) if v == vec!["1", "2"]));
+        assert!(cast(&CValue::VecDyn(vec![CValue::None]), CValueType::VecI32).is_err());
+        assert!(matches!(cast(&CValue::String("[1, 2]".to_string()), CValueType::VecI32), Ok(CValue::VecI32(v)) if v == vec![1, 2]));
+        assert!(matches!(cast(&CValue::String("a".to_string()), CValueType::VecString), Ok(CValue::VecString(v)) if v == vec!["a"]));
+        assert!(matches!(cast(&CValue::VecF32(vec![0.5]), CValueType::VecDyn), Ok(CValue::VecDyn(v)) if v.len() == 1));
+
+        let object = cast(&CValue::String(r#"{"a": 1, "b": {"c": "d"}}"#.to_string()), CValueType::Object).unwrap();
+        assert!(matches!(&object, CValue::Object(o) if matches!(o.get("b"), Some(CValue::Object(b)) if b.contains_key("c"))));
+        assert!(cast(&CValue::String("[1]".to_string()), CValueType::Object).is_err());
+        assert!(cast(&CValue::I32(1), CValueType::Object).is_err());
+        assert!(cast(&object, CValueType::VecDyn).is_err());
+    }
+
+    #[test]
+    fn test_cast_cratedb_types() {
+        assert!(matches!(cast(&CValue::String("2024-01-02T03:04:05Z".to_string()), CValueType::Timestamp), Ok(CValue::Timestamp(1704164645000))));
+        assert!(matches!(cast(&CValue::Date(1), CValueType::Timestamp), Ok(CValue::Timestamp(86_400_000))));
+        assert!(matches!(cast(&CValue::Timestamp(-1), CValueType::Date), Ok(CValue::Date(-1))));
+        assert!(matches!(cast(&CValue::String("1970-01-03".to_string()), CValueType::Date), Ok(CValue::Date(2))));
+        assert!(matches!(cast(&CValue::Timestamp(5), CValueType::I64), Ok(CValue::I64(5))));
+
+        assert!(matches!(cast(&CValue::String(" 0.10000000000000000001 ".to_string()), CValueType::Decimal), Ok(CValue::Decimal(d)) if d == "0.10000000000000000001"));
+        assert!(matches!(cast(&CValue::Decimal("2.5".to_string()), CValueType::Double64), Ok(CValue::Double64(v)) if v == 2.5));
+        assert!(cast(&CValue::String("NaN".to_string()), CValueType::Decimal).is_err());
+        assert!(matches!(cast(&CValue::Double32(0.1), CValueType::Decimal), Ok(CValue::Decimal(d)) if d == "0.1"));
+
+        assert!(matches!(cast(&CValue::String("\\x0aff".to_string()), CValueType::Bytes), Ok(CValue::Bytes(b)) if b == vec![10, 255]));
+        assert!(matches!(cast(&CValue::Bytes(vec![10, 255]), CValueType::String), Ok(CValue::String(s)) if s == "\\x0aff"));
+        assert!(matches!(cast(&CValue::String("::1".to_string()), CValueType::Ip), Ok(CValue::Ip(ip)) if ip.is_loopback()));
+        assert!(cast(&CValue::String("10.0.0.256".to_string()), CValueType::Ip).is_err());
+
+        assert!(matches!(cast(&CValue::VecF64(vec![9.74, 47.4]), CValueType::GeoPoint), Ok(CValue::GeoPoint(lon, lat)) if lon == 9.74 && lat == 47.4));
+        assert!(matches!(cast(&CValue::String("POINT (9.74 47.4)".to_string()), CValueType::GeoPoint), Ok(CValue::GeoPoint(lon, _)) if lon == 9.74));
+        assert!(cast(&CValue::VecF64(vec![47.4, 190.0]), CValueType::GeoPoint).is_err());
+    }
+
+    #[test]
+    fn test_cast_lossy_floats() {
+        let exact = 1i64 << 53;
+        assert!(matches!(cast(&CValue::I64(exact), CValueType::Double64), Ok(CValue::Double64(v)) if v == exact as f64));
+        assert!(cast(&CValue::I64(exact + 1), CValueType::Double64).is_err());
+        assert!(cast(&CValue::I64(i64::MAX), CValueType::Double64).is_err());
+        assert!(matches!(cast(&CValue::I64(i64::MIN), CValueType::Double64), Ok(CValue::Double64(v)) if v == i64::MIN as f64));
+        assert!(cast(&CValue::String("9007199254740993".to_string()), CValueType::Double64).is_err());
+        assert!(cast(&CValue::VecI64(vec![1, exact + 1]), CValueType::VecF64).is_err());
+
+        assert!(cast(&CValue::Double64(0.1), CValueType::Double32).is_err());
+        assert!(cast(&CValue::I32(16_777_217), CValueType::Double32).is_err());
+        assert!(matches!(cast(&CValue::I32(16_777_216), CValueType::Double32), Ok(CValue::Double32(v)) if v == 16_777_216.0));
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::HashSet;
+use std::mem::size_of;
+
+use memory_stats::memory_stats;
+
+use crate::experiment::data::{CValue, CValueType};
+
+/// Validity bitmap, one bit per row, unset for nulls.
+#[derive(Debug, Clone, Default)]
+pub struct Validity {
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl Validity {
+    pub fn push(&mut self, valid: bool) {
+        if self.len.is_multiple_of(64) {
+            self.bits.push(0);
+        }
+        if valid {
+            self.bits[self.len / 64] |= 1 << (self.len % 64);
+        }
+        self.len += 1;
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn null_count(&self) -> usize {
+        self.len - self.bits.iter().map(|word| word.count_ones() as usize).sum::<usize>()
+    }
+
+    /// Returns the bits of the rows for which `keep` is true.
+    fn retain(&self, keep: impl Fn(usize) -> bool) -> Self {
+        let mut kept = Self::default();
+        (0..self.len).filter(|&i| keep(i)).for_each(|i| kept.push(self.get(i)));
+        kept
+    }
+
+    fn heap_size(&self) -> usize {
+        self.bits.capacity() * size_of::<u64>()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.bits.shrink_to_fit();
+    }
+}
+
+/// Strings stored back to back in one buffer, the i-th string is `data[offsets[i]..offsets[i + 1]]`.
+#[derive(Debug, Clone)]
+pub struct Strings {
+    offsets: Vec<usize>,
+    data: String,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self { offsets: vec![0], data: String::new() }
+    }
+}
+
+impl Strings {
+    pub fn push(&mut self, value: &str) {
+        self.data.push_str(value);
+        self.offsets.push(self.data.len());
+    }
+
+    pub fn get(&self, i: usize) -> &str {
+        &self.data[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn retain(&self, keep: impl Fn(usize) -> bool) -> Self {
+        let mut kept = Self::default();
+        (0..self.len()).filter(|&i| keep(i)).for_each(|i| kept.push(self.get(i)));
+        kept
+    }
+
+    fn heap_size(&self) -> usize {
+        self.offsets.capacity() * size_of::<usize>() + self.data.capacity()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.offsets.shrink_to_fit();
+        self.data.shrink_to_fit();
+    }
+}
+
+/// Lists stored back to back in one buffer, the i-th list is `values[offsets[i]..offsets[i + 1]]`,
+/// e.g. embedding vectors.
+#[derive(Debug, Clone)]
+pub struct List<T> {
+    offsets: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self { offsets: vec![0], values: vec![] }
+    }
+}
+
+impl<T: Clone> List<T> {
+    pub fn push(&mut self, list: Vec<T>) {
+        self.values.extend(list);
+        self.offsets.push(self.values.len());
+    }
+
+    pub fn get(&self, i: usize) -> &[T] {
+        &self.values[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn retain(&self, keep: impl Fn(usize) -> bool) -> Self {
+        let mut kept = Self::default();
+        for i in (0..self.len()).filter(|&i| keep(i)) {
+            kept.values.extend_from_slice(self.get(i));
+            kept.offsets.push(kept.values.len());
+        }
+        kept
+    }
+
+    /// Converts every value, e.g. to widen the type of the list.
+    fn map<U>(self, func: impl Fn(T) -> U) -> List<U> {
+        List { offsets: self.offsets, values: self.values.into_iter().map(func).collect() }
+    }
+
+    fn heap_size(&self) -> usize {
+        self.offsets.capacity() * size_of::<usize>() + self.values.capacity() * size_of::<T>()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.offsets.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+}
+
+/// Values of a CDataFrame column in typed buffers. The buffer type is decided by the first value
+/// that is not null and numeric buffers are widened to hold wider values (see `CValueType::widen`);
+/// a column whose values have other types, or objects and mixed arrays, falls back to `Mixed`.
+/// Nulls of typed buffers are a default value with its validity bit unset.
+///
+/// Values are converted to and from `CValue` at the edges, e.g. `push`, `get` and `into_values`.
+#[derive(Debug, Clone)]
+pub enum ColumnData {
+    /// Only nulls so far.
+    Null(usize),
+    Bool(Vec<bool>, Validity),
+    I16(Vec<i16>, Validity),
+    I32(Vec<i32>, Validity),
+    I64(Vec<i64>, Validity),
+    F32(Vec<f32>, Validity),
+    F64(Vec<f64>, Validity),
+    String(Strings, Validity),
+    VecString(List<String>, Validity),
+    VecI32(List<i32>, Validity),
+    VecI64(List<i64>, Validity),
+    VecF32(List<f32>, Validity),
+    VecF64(List<f64>, Validity),
+    Mixed(Vec<CValue>),
+}
+
+impl Default for ColumnData {
+    fn default() -> Self {
+        ColumnData::Null(0)
+    }
+}
+
+/// Estimates the heap memory a CValue holds.
+pub fn cvalue_heap_size(value: &CValue) -> usize {
+    match value {
+        CValue::String(v) | CValue::Decimal(v) => v.capacity(),
+        CValue::Bytes(v) => v.capacity(),
+        CValue::VecString(v) => v.capacity() * size_of::<String>() + v.iter().map(|s| s.capacity()).sum::<usize>(),
+        CValue::VecI32(v) => v.capacity() * size_of::<i32>(),
+        CValue::VecI64(v) => v.capacity() * size_of::<i64>(),
+        CValue::VecF32(v) => v.capacity() * size_of::<f32>(),
+        CValue::VecF64(v) => v.capacity() * size_of::<f64>(),
+        CValue::VecDyn(v) => v.capacity() * size_of::<CValue>() + v.iter().map(cvalue_heap_size).sum::<usize>(),
+        CValue::Object(v) => v.iter().map(|(k, v)| k.capacity() + size_of::<(String, CValue)>() + cvalue_heap_size(v)).sum(),
+        _ => 0
+    }
+}
+
+/// Keeps the values whose index `keep` is true for.
+fn retain_indexed<T>(values: &mut Vec<T>, keep: impl Fn(usize) -> bool) {
+    let mut i = 0;
+    values.retain(|_| {
+        i += 1;
+        keep(i - 1)
+    });
+}
+
+macro_rules! typed_push {
+    ($values:expr, $validity:expr, $value:expr) => {{
+        $values.push($value);
+        $validity.push(true);
+    }};
+}
+
+impl ColumnData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_nulls(count: usize) -> Self {
+        ColumnData::Null(count)
+    }
+
+    pub fn from_values(values: Vec<CValue>) -> Self {
+        let mut column = Self::new();
+        column.extend(values);
+        column.shrink_to_fit();
+        column
+    }
+
+    /// Returns an empty buffer for the type of `value` with `nulls` nulls.
+    fn for_value(value: &CValue, nulls: usize) -> Self {
+        let mut column = match value {
+            CValue::Bool(_) => ColumnData::Bool(vec![], Validity::default()),
+            CValue::I16(_) => ColumnData::I16(vec![], Validity::default()),
+            CValue::I32(_) => ColumnData::I32(vec![], Validity::default()),
+            CValue::I64(_) => ColumnData::I64(vec![], Validity::default()),
+            CValue::Double32(_) => ColumnData::F32(vec![], Validity::default()),
+            CValue::Double64(_) => ColumnData::F64(vec![], Validity::default()),
+            CValue::String(_) => ColumnData::String(Strings::default(), Validity::default()),
+            CValue::VecString(_) => ColumnData::VecString(List::default(), Validity::default()),
+            CValue::VecI32(_) => ColumnData::VecI32(List::default(), Validity::default()),
+            CValue::VecI64(_) => ColumnData::VecI64(List::default(), Validity::default()),
+            CValue::VecF32(_) => ColumnData::VecF32(List::default(), Validity::default()),
+            CValue::VecF64(_) => ColumnData::VecF64(List::default(), Validity::default()),
+            _ => ColumnData::Mixed(vec![]),
+        };
+        for _ in 0..nulls {
+            column.push(CValue::None);
+        }
+        column
+    }
+
+    /// Pushes a value of the buffer's type, or a null, and returns the value back otherwise.
+    fn try_push(&mut self, value: CValue) -> Result<(), CValue> {
+        match (self, value) {
+            (ColumnData::Null(count), CValue::None) => *count += 1,
+            (ColumnData::Bool(v, valid), CValue::Bool(x)) => typed_push!(v, valid, x),
+            (ColumnData::I16(v, valid), CValue::I16(x)) => typed_push!(v, valid, x),
+            (ColumnData::I32(v, valid), CValue::I16(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::I32(v, valid), CValue::I32(x)) => typed_push!(v, valid, x),
+            (ColumnData::I64(v, valid), CValue::I16(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::I64(v, valid), CValue::I32(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::I64(v, valid), CValue::I64(x)) => typed_push!(v, valid, x),
+            (ColumnData::F32(v, valid), CValue::I16(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::F32(v, valid), CValue::Double32(x)) => typed_push!(v, valid, x),
+            (ColumnData::F64(v, valid), CValue::I16(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::F64(v, valid), CValue::I32(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::F64(v, valid), CValue::Double32(x)) => typed_push!(v, valid, x.into()),
+            (ColumnData::F64(v, valid), CValue::Double64(x)) => typed_push!(v, valid, x),
+            (ColumnData::String(v, valid), CValue::String(x)) => {
+                v.push(&x);
+                valid.push(true);
+            }
+            (ColumnData::VecString(v, valid), CValue::VecString(x)) => typed_push!(v, valid, x),
+            (ColumnData::VecI32(v, valid), CValue::VecI32(x)) => typed_push!(v, valid, x),
+            (ColumnData::VecI64(v, valid), CValue::VecI32(x)) => typed_push!(v, valid, x.into_iter().map(i64::from).collect()),
+            (ColumnData::VecI64(v, valid), CValue::VecI64(x)) => typed_push!(v, valid, x),
+            (ColumnData::VecF32(v, valid), CValue::VecF32(x)) => typed_push!(v, valid, x),
+            (ColumnData::VecF64(v, valid), CValue::VecI32(x)) => typed_push!(v, valid, x.into_iter().map(f64::from).collect()),
+            (ColumnData::VecF64(v, valid), CValue::VecF32(x)) => typed_push!(v, valid, x.into_iter().map(f64::from).collect()),
+            (ColumnData::VecF64(v, valid), CValue::VecF64(x)) => typed_push!(v, valid, x),
+            (ColumnData::Mixed(v), x) => v.push(x),
+            (column, CValue::None) => match column {
+                ColumnData::Bool(v, valid) => { v.push(false); valid.push(false) }
+                ColumnData::I16(v, valid) => { v.push(0); valid.push(false) }
+                ColumnData::I32(v, valid) => { v.push(0); valid.push(false) }
+                ColumnData::I64(v, valid) => { v.push(0); valid.push(false) }
+                ColumnData::F32(v, valid) => { v.push(0.0); valid.push(false) }
+                ColumnData::F64(v, valid) => { v.push(0.0); valid.push(false) }
+                ColumnData::String(v, valid) => { v.push(""); valid.push(false) }
+                ColumnData::VecString(v, valid) => { v.push(vec![]); valid.push(false) }
+                ColumnData::VecI32(v, valid) => { v.push(vec![]); valid.push(false) }
+                ColumnData::VecI64(v, valid) => { v.push(vec![]); valid.push(false) }
+                ColumnData::VecF32(v, valid) => { v.push(vec![]); valid.push(false) }
+                ColumnData::VecF64(v, valid) => { v.push(vec![]); valid.push(false) }
+                ColumnData::Null(_) | ColumnData::Mixed(_) => unreachable!(),
+            },
+            (_, x) => return Err(x),
+        }
+        Ok(())
+    }
+
+    /// Converts a numeric buffer to the wider type `dtype`, returns false if it can not be.
+    fn widen_to(&mut self, dtype: CValueType) -> bool {
+        let widened = match (std::mem::take(self), dtype) {
+            (ColumnData::I16(v, valid), CValueType::I32) => ColumnData::I32(v.into_iter().map(i32::from).collect(), valid),
+            (ColumnData::I16(v, valid), CValueType::I64) => ColumnData::I64(v.into_iter().map(i64::from).collect(), valid),
+            (ColumnData::I16(v, valid), CValueType::Double32) => ColumnData::F32(v.into_iter().map(f32::from).collect(), valid),
+            (ColumnData::I16(v, valid), CValueType::Double64) => ColumnData::F64(v.into_iter().map(f64::from).collect(), valid),
+            (ColumnData::I32(v, valid), CValueType::I64) => ColumnData::I64(v.into_iter().map(i64::from).collect(), valid),
+            (ColumnData::I32(v, valid), CValueType::Double64) => ColumnData::F64(v.into_iter().map(f64::from).collect(), valid),
+            (ColumnData::F32(v, valid), CValueType::Double64) => ColumnData::F64(v.into_iter().map(f64::from).collect(), valid),
+            (ColumnData::VecI32(v, valid), CValueType::VecI64) => ColumnData::VecI64(v.map(i64::from), valid),
+            (ColumnData::VecI32(v, valid), CValueType::VecF64) => ColumnData::VecF64(v.map(f64::from), valid),
+            (ColumnData::VecF32(v, valid), CValueType::VecF64) => ColumnData::VecF64(v.map(f64::from), valid),
+            (column, _) => {
+                *self = column;
+                return false;
+            }
+        };
+        *self = widened;
+        true
+    }
+
+    pub fn push(&mut self, value: CValue) {
+        if let ColumnData::Null(count) = self {
+            if !matches!(value, CValue::None) {
+                *self = Self::for_value(&value, *count);
+            }
+        }
+        let Err(value) = self.try_push(value) else { return };
+
+        // The value has another type than the buffer, numeric buffers are widened to hold it and
+        // other columns fall back to CValues.
+        let value = match self.dtype().widen(value.get_dtype()) {
+            Some(dtype) if self.widen_to(dtype) => match self.try_push(value) {
+                Ok(()) => return,
+                Err(value) => value
+            },
+            _ => value
+        };
+        let mut values = std::mem::take(self).into_values();
+        values.push(value);
+        *self = ColumnData::Mixed(values);
+    }
+
+    pub fn extend(&mut self, values: impl IntoIterator<Item=CValue>) {
+        for value in values {
+            self.push(value);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::Null(count) => *count,
+            ColumnData::Bool(v, _) => v.len(),
+            ColumnData::I16(v, _) => v.len(),
+            ColumnData::I32(v, _) => v.len(),
+            ColumnData::I64(v, _) => v.len(),
+            ColumnData::F32(v, _) => v.len(),
+            ColumnData::F64(v, _) => v.len(),
+            ColumnData::String(v, _) => v.len(),
+            ColumnData::VecString(v, _) => v.len(),
+            ColumnData::VecI32(v, _) => v.len(),
+            ColumnData::VecI64(v, _) => v.len(),
+            ColumnData::VecF32(v, _) => v.len(),
+            ColumnData::VecF64(v, _) => v.len(),
+            ColumnData::Mixed(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn validity(&self) -> Option<&Validity> {
+        match self {
+            ColumnData::Bool(_, valid) | ColumnData::I16(_, valid) | ColumnData::I32(_, valid) |
+            ColumnData::I64(_, valid) | ColumnData::F32(_, valid) | ColumnData::F64(_, valid) |
+            ColumnData::String(_, valid) | ColumnData::VecString(_, valid) | ColumnData::VecI32(_, valid) |
+            ColumnData::VecI64(_, valid) | ColumnData::VecF32(_, valid) | ColumnData::VecF64(_, valid) => Some(valid),
+            ColumnData::Null(_) | ColumnData::Mixed(_) => None
+        }
+    }
+
+    /// Returns the value of a row as a CValue.
+    pub fn get(&self, i: usize) -> CValue {
+        if self.validity().is_some_and(|valid| !valid.get(i)) {
+            return CValue::None;
+        }
+        match self {
+            ColumnData::Null(_) => CValue::None,
+            ColumnData::Bool(v, _) => CValue::Bool(v[i]),
+            ColumnData::I16(v, _) => CValue::I16(v[i]),
+            ColumnData::I32(v, _) => CValue::I32(v[i]),
+            ColumnData::I64(v, _) => CValue::I64(v[i]),
+            ColumnData::F32(v, _) => CValue::Double32(v[i]),
+            ColumnData::F64(v, _) => CValue::Double64(v[i]),
+            ColumnData::String(v, _) => CValue::String(v.get(i).to_string()),
+            ColumnData::VecString(v, _) => CValue::VecString(v.get(i).to_vec()),
+            ColumnData::VecI32(v, _) => CValue::VecI32(v.get(i).to_vec()),
+            ColumnData::VecI64(v, _) => CValue::VecI64(v.get(i).to_vec()),
+            ColumnData::VecF32(v, _) => CValue::VecF32(v.get(i).to_vec()),
+            ColumnData::VecF64(v, _) => CValue::VecF64(v.get(i).to_vec()),
+            ColumnData::Mixed(v) => v[i].clone(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=CValue> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    pub fn to_values(&self) -> Vec<CValue> {
+        self.iter().collect()
+    }
+
+    pub fn into_values(self) -> Vec<CValue> {
+        match self {
+            ColumnData::Mixed(values) => values,
+            column => column.to_values()
+        }
+    }
+
+    /// Replaces every value by `func(row, value)`. Values are converted one at a time into a new
+    /// column, so their type can change.
+    pub fn map<F: FnMut(usize, CValue) -> CValue>(&mut self, mut func: F) {
+        let mut mapped = ColumnData::new();
+        match std::mem::take(self) {
+            ColumnData::Mixed(values) => values.into_iter().enumerate().for_each(|(i, value)| mapped.push(func(i, value))),
+            column => (0..column.len()).for_each(|i| mapped.push(func(i, column.get(i)))),
+        }
+        mapped.shrink_to_fit();
+        *self = mapped;
+    }
+
+    /// Removes rows by their index, the buffers keep their type.
+    pub fn remove_rows(&mut self, rows: &HashSet<usize>) {
+        let keep = |i: usize| !rows.contains(&i);
+        match self {
+            ColumnData::Null(count) => *count -= rows.iter().filter(|&&i| i < *count).count(),
+            ColumnData::Bool(v, valid) => { *valid = valid.retain(keep); retain_indexed(v, keep) }
+            ColumnData::I16(v, valid) => { *valid = valid.retain(keep); retain_indexed(v, keep) }
+            ColumnData::I32(v, valid) => { *valid = valid.retain(keep); retain_indexed(v, keep) }
+            ColumnData::I64(v, valid) => { *valid = valid.retain(keep); retain_indexed(v, keep) }
+            ColumnData::F32(v, valid) => { *valid = valid.retain(keep); retain_indexed(v, keep) }
+            ColumnData::F64(v, valid) => { *valid = valid.retain(keep); retain_indexed(v, keep) }
+            ColumnData::String(v, valid) => { *valid = valid.retain(keep); *v = v.retain(keep) }
+            ColumnData::VecString(v, valid) => { *valid = valid.retain(keep); *v = v.retain(keep) }
+            ColumnData::VecI32(v, valid) => { *valid = valid.retain(keep); *v = v.retain(keep) }
+            ColumnData::VecI64(v, valid) => { *valid = valid.retain(keep); *v = v.retain(keep) }
+            ColumnData::VecF32(v, valid) => { *valid = valid.retain(keep); *v = v.retain(keep) }
+            ColumnData::VecF64(v, valid) => { *valid = valid.retain(keep); *v = v.retain(keep) }
+            ColumnData::Mixed(v) => retain_indexed(v, keep),
+        }
+    }
+
+    /// Returns the CValueType of the buffer, VecDyn for mixed values and None if all are null.
+    pub fn dtype(&self) -> CValueType {
+        match self {
+            ColumnData::Null(_) => CValueType::None,
+            ColumnData::Bool(..) => CValueType::Bool,
+            ColumnData::I16(..) => CValueType::I16,
+            ColumnData::I32(..) => CValueType::I32,
+            ColumnData::I64(..) => CValueType::I64,
+            ColumnData::F32(..) => CValueType::Double32,
+            ColumnData::F64(..) => CValueType::Double64,
+            ColumnData::String(..) => CValueType::String,
+            ColumnData::VecString(..) => CValueType::VecString,
+            ColumnData::VecI32(..) => CValueType::VecI32,
+            ColumnData::VecI64(..) => CValueType::VecI64,
+            ColumnData::VecF32(..) => CValueType::VecF32,
+            ColumnData::VecF64(..) => CValueType::VecF64,
+            ColumnData::Mixed(..) => CValueType::VecDyn,
+        }
+    }
+
+    pub fn null_count(&self) -> usize {
+        match self {
+            ColumnData::Null(count) => *count,
+            ColumnData::Mixed(v) => v.iter().filter(|v| matches!(v, CValue::None)).count(),
+            column => column.validity().unwrap().null_count()
+        }
+    }
+
+    /// Frees the spare capacity of the buffers, e.g. once a chunk is complete.
+    pub fn shrink_to_fit(&mut self) {
+        match self {
+            ColumnData::Null(_) => {}
+            ColumnData::Bool(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::I16(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::I32(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::I64(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::F32(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::F64(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::String(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::VecString(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::VecI32(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::VecI64(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::VecF32(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::VecF64(v, valid) => { v.shrink_to_fit(); valid.shrink_to_fit() }
+            ColumnData::Mixed(v) => v.shrink_to_fit(),
+        }
+    }
+
+    /// Estimates the heap memory of the column.
+    pub fn heap_size(&self) -> usize {
+        let validity = self.validity().map_or(0, |valid| valid.heap_size());
+        validity + match self {
+            ColumnData::Null(_) => 0,
+            ColumnData::Bool(v, _) => v.capacity() * size_of::<bool>(),
+            ColumnData::I16(v, _) => v.capacity() * size_of::<i16>(),
+            ColumnData::I32(v, _) => v.capacity() * size_of::<i32>(),
+            ColumnData::I64(v, _) => v.capacity() * size_of::<i64>(),
+            ColumnData::F32(v, _) => v.capacity() * size_of::<f32>(),
+            ColumnData::F64(v, _) => v.capacity() * size_of::<f64>(),
+            ColumnData::String(v, _) => v.heap_size(),
+            ColumnData::VecString(v, _) => v.heap_size() + v.values.iter().map(|s| s.capacity()).sum::<usize>(),
+            ColumnData::VecI32(v, _) => v.heap_size(),
+            ColumnData::VecI64(v, _) => v.heap_size(),
+            ColumnData::VecF32(v, _) => v.heap_size(),
+            ColumnData::VecF64(v, _) => v.heap_size(),
+            ColumnData::Mixed(v) => v.capacity() * size_of::<CValue>() + v.iter().map(cvalue_heap_size).sum::<usize>(),
+        }
+    }
+}
+
+fn physical_mem() -> usize {
+    memory_stats().map(|stats| stats.physical_mem).unwrap_or(0)
+}
+
+/// Compares the memory of `rows` vectors of `dimensions` floats, e.g. embeddings, stored as
+/// CValues and in a ColumnData, measured with `memory_stats` like `Metadata`. Returns the
+/// (CValues, ColumnData) physical memory growth in bytes.
+pub fn benchmark_memory(rows: usize, dimensions: usize) -> (usize, usize) {
+    let vector = |i: usize| CValue::VecF32((0..dimensions).map(|j| (i * j) as f32).collect());
+    let base: usize = 10;
+
+    let start = physical_mem();
+    let values: Vec<CValue> = (0..rows).map(vector).collect();
+    let cvalues = physical_mem().saturating_sub(start);
+    let cvalues_estimate = values.capacity() * size_of::<CValue>() + values.iter().map(cvalue_heap_size).sum::<usize>();
+    drop(values);
+
+    let start = physical_mem();
+    let mut column = ColumnData::new();
+    column.extend((0..rows).map(vector));
+    column.shrink_to_fit();
+    let columnar = physical_mem().saturating_sub(start);
+
+    println!("{} vectors of {} dimensions: CValues {}Mb (estimated {}Mb), ColumnData {}Mb (estimated {}Mb)",
+             rows,
+             dimensions,
+             cvalues / base.pow(6),
+             cvalues_estimate / base.pow(6),
+             columnar / base.pow(6),
+             column.heap_size() / base.pow(6));
+    (cvalues, columnar)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::mem::size_of;
+
+    use crate::experiment::column::{benchmark_memory, cvalue_heap_size, ColumnData};
+    use crate::experiment::data::{CValue, CValueType};
+
+    #[test]
+    fn test_typed_buffers() {
+        let mut column = ColumnData::new();
+        column.extend(vec![CValue::None, CValue::String("ab".to_string()), CValue::None, CValue::String("c".to_string())]);
+        assert_eq!(column.dtype(), CValueType::String);
+        assert_eq!(column.len(), 4);
+        assert_eq!(column.null_count(), 2);
+        assert!(matches!(column.get(0), CValue::None));
+        assert!(matches!(column.get(1), CValue::String(s) if s == "ab"));
+        assert!(matches!(column.get(3), CValue::String(s) if s == "c"));
+
+        let mut vectors = ColumnData::from_values(vec![CValue::VecF32(vec![1.0, 2.0]), CValue::None, CValue::VecF32(vec![3.0])]);
+        assert_eq!(vectors.dtype(), CValueType::VecF32);
+        assert!(matches!(vectors.get(2), CValue::VecF32(v) if v == vec![3.0]));
+        vectors.remove_rows(&HashSet::from([0]));
+        assert_eq!(vectors.len(), 2);
+        assert!(matches!(vectors.get(0), CValue::None));
+
+        // Another type falls back to CValues, keeping the values.
+        let mut mixed = ColumnData::from_values(vec![CValue::I32(1), CValue::None]);
+        mixed.push(CValue::Object(HashMap::new()));
+        assert_eq!(mixed.dtype(), CValueType::VecDyn);
+        assert!(matches!(mixed.to_values()[..], [CValue::I32(1), CValue::None, CValue::Object(_)]));
+
+        let nulls = ColumnData::with_nulls(70);
+        assert_eq!(nulls.dtype(), CValueType::None);
+        let mut late = nulls;
+        late.push(CValue::I64(7));
+        assert_eq!(late.null_count(), 70);
+        assert!(matches!(late.get(70), CValue::I64(7)));
+    }
+
+    #[test]
+    fn test_memory() {
+        let vectors: Vec<CValue> = (0..100).map(|_| CValue::VecF32(vec![0.5; 2048])).collect();
+        let cvalues = vectors.capacity() * size_of::<CValue>() + vectors.iter().map(cvalue_heap_size).sum::<usize>();
+        let column = ColumnData::from_values(vectors);
+
+        // One buffer of floats, instead of a CValue and an allocation per vector.
+        assert!(column.heap_size() < cvalues);
+        assert!(column.heap_size() >= 100 * 2048 * size_of::<f32>());
+
+        let integers = ColumnData::from_values((0..1000).map(CValue::I32).collect());
+        assert!(integers.heap_size() < 1000 * size_of::<CValue>() / 4);
+    }
+
+    #[test]
+    fn test_widening() {
+        // Numeric buffers are widened instead of falling back to CValues.
+        let mut column = ColumnData::from_values(vec![CValue::I32(1), CValue::None]);
+        column.push(CValue::I64(i64::MAX));
+        column.push(CValue::I16(2));
+        assert_eq!(column.dtype(), CValueType::I64);
+        assert!(matches!(column.to_values()[..], [CValue::I64(1), CValue::None, CValue::I64(i64::MAX), CValue::I64(2)]));
+        // No float holds every i64.
+        column.push(CValue::Double64(0.5));
+        assert_eq!(column.dtype(), CValueType::VecDyn);
+
+        assert_eq!(ColumnData::from_values(vec![CValue::I32(1), CValue::Double32(0.5)]).dtype(), CValueType::Double64);
+        assert_eq!(ColumnData::from_values(vec![CValue::VecF32(vec![0.5]), CValue::VecI32(vec![1])]).dtype(), CValueType::VecF64);
+
+        // Removing and mapping rows keep the typed buffer.
+        let mut numbers = ColumnData::from_values((0..100).map(CValue::I64).collect());
+        numbers.remove_rows(&HashSet::from([0, 50, 99]));
+        assert_eq!(numbers.len(), 97);
+        assert!(matches!(numbers.get(49), CValue::I64(51)));
+        numbers.map(|_, value| match value {
+            CValue::I64(i) => CValue::I64(i * 2),
+            value => value
+        });
+        assert_eq!(numbers.dtype(), CValueType::I64);
+        assert!(matches!(numbers.get(0), CValue::I64(2)));
+    }
+
+    #[test]
+    fn test_benchmark_memory() {
+        // Short vectors, where a CValue and an allocation per row are most of the memory.
+        let (cvalues, columnar) = benchmark_memory(200_000, 8);
+        assert!(cvalues > 0);
+        assert!(columnar < cvalues);
+    }
+}
@@ -1,26 +1,39 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Formatter;
+use std::net::IpAddr;
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeDelta};
 use std::str::FromStr;
 use indexmap::IndexMap;
 use indexmap::map::Entry;
 use mongodb::bson::{Document};
 use serde::{Deserialize, Serialize};
+use crate::experiment::cast::cast;
+use crate::experiment::column::ColumnData;
 use crate::experiment::schema::CSchema;
 use crate::experiment::trans::bson_to_cvalue;
+use crate::experiment::predicate::Predicate;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum DtypeStrategy {
     NewCol,
     Cast,
     Ignore,
-    Remove
+    Remove,
 }
 
+#[derive(Debug)]
 pub struct ParseDtypeStrategyError {
     message: String,
 }
 
+impl fmt::Display for ParseDtypeStrategyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
 impl FromStr for DtypeStrategy {
     type Err = ParseDtypeStrategyError;
 
@@ -44,6 +57,12 @@ pub struct CDataFrame {
     selected_columns: Vec<String>,
 }
 
+impl Default for CDataFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CDataFrame {
     /// Instantiate an empty CDataFrame.
     pub fn new() -> Self {
@@ -56,48 +75,77 @@ impl CDataFrame {
 
     pub fn from_bson(documents: Vec<Document>, schema: CSchema) -> Self {
         let mut new_dataframe = Self::new();
-
-        // Track the current columns in the dataset.
-        let mut dataset_columns: HashSet<String> = HashSet::new();
-        let mut row_count: usize = 0;
-
-        // Track the columns of the current document.
         for document in documents {
-            let doc_columns: Vec<String> = document.keys().map(|x| String::from(x)).collect();
-
-            for col in &doc_columns {
-                if !new_dataframe.has_column(col) {
-
-                    // If a new column is found, add it to the dataset.
-                    dataset_columns.insert(col.to_string());
-
-                    // Fill the columns with nulls, so if we insert a new column at length 8, 1to7 are not empty, but have nulls.
-                    new_dataframe.add_column(
-                        col.to_string(),
-                        CColumn {
-                            values: vec![CValue::None; row_count],
-                            data_type: CValueType::Unknown,
-                            expected_dtype: CValueType::Unknown,
-                            dtype_strategy: DtypeStrategy::Ignore,
-                        });
-                }
-            }
+            new_dataframe.append_bson(document);
+        }
+        new_dataframe.set_schema(&schema);
+        new_dataframe
+    }
 
-            for (col, value) in document {
-                new_dataframe.add_value_to_column(&col, bson_to_cvalue(value))
+    /// Appends a document as a new row, its new columns are added after the existing ones.
+    pub fn append_bson(&mut self, document: Document) {
+        let row_count = self.count;
+
+        for col in document.keys() {
+            if !self.has_column(col) {
+                // Fill the columns with nulls, so if we insert a new column at length 8, 1to7 are not empty, but have nulls.
+                self.add_column(
+                    col.to_string(),
+                    CColumn {
+                        values: ColumnData::with_nulls(row_count),
+                        data_type: CValueType::Unknown,
+                        expected_dtype: CValueType::Unknown,
+                        dtype_strategy: DtypeStrategy::Ignore,
+                    });
             }
+        }
 
-            for col in &dataset_columns {
-                if !doc_columns.contains(&col) {
-                    // If a document does not contain a key in the dataframe, add a None, so all
-                    // columns have the same length.
-                    new_dataframe.add_value_to_column(col, CValue::None)
-                }
+        for (name, column) in self.columns.iter_mut() {
+            // If a document does not contain a key in the dataframe, add a None, so all
+            // columns have the same length.
+            if !document.contains_key(name) {
+                column.values.push(CValue::None);
             }
-            row_count += 1;
         }
-        new_dataframe.count = row_count;
-        new_dataframe.set_schema(schema);
+
+        for (col, value) in document {
+            self.add_value_to_column(&col, bson_to_cvalue(value))
+        }
+        self.count += 1;
+    }
+
+    /// Appends the rows of another CDataFrame, e.g. the chunks of a `CDataFrameBuilder`. Its new
+    /// columns are added after the existing ones, the columns only one of them has get nulls.
+    pub fn append(&mut self, other: CDataFrame) {
+        let count = self.count;
+        for (name, column) in other.columns {
+            let target = self.columns.entry(name).or_insert_with(|| CColumn {
+                values: ColumnData::with_nulls(count),
+                data_type: column.data_type,
+                expected_dtype: column.expected_dtype,
+                dtype_strategy: column.dtype_strategy.clone(),
+            });
+            target.values.extend(column.values.iter());
+        }
+
+        self.count += other.count;
+        for column in self.columns.values_mut() {
+            let missing = self.count - column.values.len();
+            column.values.extend((0..missing).map(|_| CValue::None));
+        }
+    }
+
+    /// Returns an empty CDataFrame with the same columns and dtypes.
+    pub fn empty_like(&self) -> Self {
+        let mut new_dataframe = Self::new();
+        for (name, column) in &self.columns {
+            new_dataframe.add_column(name.clone(), CColumn {
+                values: ColumnData::new(),
+                data_type: column.data_type,
+                expected_dtype: column.expected_dtype,
+                dtype_strategy: column.dtype_strategy.clone(),
+            });
+        }
         new_dataframe
     }
 
@@ -127,7 +175,7 @@ impl CDataFrame {
 
     pub fn print(&self) {
         let headers = if self.selected_columns.is_empty() {
-            self.columns.iter().map(|(x, c)| x.to_string()).collect()
+            self.columns.iter().map(|(x, _c)| x.to_string()).collect()
         } else { self.selected_columns.clone() };
         // println!("{:?}", headers);
         for key in &headers {
@@ -137,66 +185,67 @@ impl CDataFrame {
 
         for i in 0..self.count {
             for key in &headers {
-                let vec = &self.columns[key].values;
-                if i < vec.len() {
-                    print!("| {:^1} ", vec[i]);
+                let values = &self.columns[key].values;
+                if i < values.len() {
+                    print!("| {:^10}", values.get(i).to_string());
                 } else {
-                    print!("{:<10} ", " ");
+                    print!("| {:^10}", "");
                 }
             }
             println!(); // Move to the next line after each row
         }
     }
 
-    fn set_schema(&mut self, schema: CSchema) {
-        for (name, column) in schema.columns {
-            if self.has_column(&name) {
-                self.modify_column(name, |x| {
+    fn set_schema(&mut self, schema: &CSchema) {
+        for (name, column) in &schema.columns {
+            if self.has_column(name) {
+                self.modify_column(name.clone(), |x| {
                     x.expected_dtype = column.dtype;
-                    x.dtype_strategy = column.dtype_collision_strategy;
+                    x.dtype_strategy = column.dtype_collision_strategy.clone();
                 })
             }
         }
     }
 
-    pub fn from_bson(documents: Vec<Document>, schema: CSchema) -> Self {
+    /// Builds a CDataFrame from a batch of rows, e.g. the one a source sends to a `Sink`.
+    pub fn from_rows(columns: &[String], rows: Vec<Vec<CValue>>) -> Self {
         let mut new_dataframe = Self::new();
+        for name in columns {
+            new_dataframe.add_column(name.clone(), CColumn {
+                values: ColumnData::new(),
+                data_type: CValueType::Unknown,
+                expected_dtype: CValueType::Unknown,
+                dtype_strategy: DtypeStrategy::Ignore,
+            });
+        }
 
-        // Track the current columns in the dataset.
-        let mut dataset_columns: HashSet<String> = HashSet::new();
-        let mut row_count: usize = 0;
-
-        // Track the columns of the current document.
-        for document in documents {
-            let doc_columns: Vec<String> = document.keys().map(|x| String::from(x)).collect();
-
-            for col in &doc_columns {
-                if !new_dataframe.has_column(col) {
-
-                    // If a new column is found, add it to the dataset.
-                    dataset_columns.insert(col.to_string());
-
-                    // Fill the columns with nulls, so if we insert a new column at length 8, 1to7 are not empty, but have nulls.
-                    new_dataframe.add_column(col.to_string(), CColumn { values: vec![CValue::None; row_count], data_type: CValueType::Unknown, expected_dtype: CValueType::Unknown });
-                }
+        new_dataframe.count = rows.len();
+        for row in rows {
+            for (column, value) in new_dataframe.columns.values_mut().zip(row) {
+                column.values.push(value);
             }
+        }
+        new_dataframe
+    }
 
-            for (col, value) in document {
-                new_dataframe.add_value_to_column(&col, bson_to_cvalue(value))
+    /// Returns the (columns, rows) of the CDataFrame, the inverse of `from_rows`.
+    pub fn into_rows(self) -> (Vec<String>, Vec<Vec<CValue>>) {
+        let columns: Vec<String> = self.columns.keys().cloned().collect();
+        let mut rows: Vec<Vec<CValue>> = (0..self.count).map(|_| Vec::with_capacity(columns.len())).collect();
+        for (_, column) in self.columns {
+            for (row, value) in rows.iter_mut().zip(column.values.into_values()) {
+                row.push(value);
             }
+        }
+        (columns, rows)
+    }
 
-            for col in &dataset_columns {
-                if !doc_columns.contains(&col) {
-                    // If a document does not contain a key in the dataframe, add a None, so all
-                    // columns have the same length.
-                    new_dataframe.add_value_to_column(col, CValue::None)
-                }
-            }
-            row_count += 1;
+    /// Removes rows by their index.
+    pub fn remove_rows(&mut self, rows: &HashSet<usize>) {
+        for column in self.columns.values_mut() {
+            column.values.remove_rows(rows);
         }
-        new_dataframe.count = row_count;
-        new_dataframe.set_schema(schema);
-        new_dataframe
+        self.count -= rows.iter().filter(|i| **i < self.count).count();
     }
 
     pub fn has_column(&self, column_name: &String) -> bool {
@@ -230,16 +279,268 @@ impl CDataFrame {
     pub fn get_mut(&mut self, key: &str) -> Option<&mut CColumn> {
         self.columns.get_mut(key)
     }
+
+    /// Returns the values of a row, in the order of the columns.
+    pub fn row(&self, i: usize) -> Vec<CValue> {
+        self.columns.values().map(|column| column.values.get(i)).collect()
+    }
+
+    /// Returns the value of a column, or of a dotted path into an object, in row `i`; only that
+    /// column is read.
+    fn value_at(&self, i: usize, path: &str) -> Option<CValue> {
+        let mut parts = path.split('.');
+        let column = self.columns.get(parts.next()?)?;
+        parts.try_fold(column.values.get(i), |value, key| match value {
+            CValue::Object(mut object) => object.remove(key),
+            _ => None
+        })
+    }
+
+    /// Renames a column in place, it keeps its position. Returns an error if a column is already
+    /// named `to`.
+    pub fn rename(mut self, from: &str, to: &str) -> Result<Self, TransformError> {
+        if !self.columns.contains_key(from) || from == to {
+            return Ok(self);
+        }
+        if self.columns.contains_key(to) {
+            return Err(TransformError { message: format!("Can not rename {} to {}, the column already exists", from, to) });
+        }
+        self.columns = self.columns
+            .into_iter()
+            .map(|(name, column)| if name == from { (to.to_string(), column) } else { (name, column) })
+            .collect();
+        for selected in self.selected_columns.iter_mut().filter(|c| *c == from) {
+            *selected = to.to_string();
+        }
+        Ok(self)
+    }
+
+    /// Removes columns, missing columns are ignored.
+    pub fn drop(mut self, columns: Vec<&str>) -> Self {
+        for name in columns {
+            self.columns.shift_remove(name);
+            self.selected_columns.retain(|c| c != name);
+        }
+        self
+    }
+
+    /// Casts the values of a column to `dtype` (see `cast`). Returns an error with the row of the
+    /// first value that can not be cast.
+    pub fn cast(mut self, column: &str, dtype: CValueType) -> Result<Self, TransformError> {
+        let Some(data) = self.columns.get_mut(column) else { return Ok(self) };
+        let values = data.values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| cast(&value, dtype).map_err(|e| TransformError {
+                message: format!("Row {} of column {}: {}", i, column, e)
+            }))
+            .collect::<Result<Vec<CValue>, TransformError>>()?;
+        data.values = ColumnData::from_values(values);
+        data.data_type = dtype;
+        Ok(self)
+    }
+
+    /// Adds, or replaces, a column computed from the other columns of every row.
+    pub fn with_column<F: Fn(&Row) -> CValue>(mut self, name: &str, func: F) -> Self {
+        let values: Vec<CValue> = (0..self.count)
+            .map(|index| func(&Row { dataframe: &self, index }))
+            .collect();
+        let column = CColumn {
+            data_type: values.iter().map(|v| v.get_dtype()).find(|t| *t != CValueType::None).unwrap_or(CValueType::Unknown),
+            values: ColumnData::from_values(values),
+            expected_dtype: CValueType::Unknown,
+            dtype_strategy: DtypeStrategy::Ignore,
+        };
+        self.columns.insert(name.to_string(), column);
+        self
+    }
+
+    /// Keeps the rows matching `predicate`.
+    pub fn filter(mut self, predicate: &Predicate) -> Self {
+        let removed: HashSet<usize> = (0..self.count)
+            .filter(|&i| !predicate.matches_by(&|path| self.value_at(i, path).map(Cow::Owned)))
+            .collect();
+        if !removed.is_empty() {
+            self.remove_rows(&removed);
+        }
+        self
+    }
+
+    /// Replaces an Object column with a `<column>_<key>` column per key, in place; rows whose value
+    /// is not an object have nulls. Nested objects stay objects, flatten them again to go deeper.
+    pub fn flatten(mut self, column: &str) -> Self {
+        let Some(index) = self.columns.get_index_of(column) else { return self };
+        let (_, parent) = self.columns.shift_remove_index(index).unwrap();
+        let objects = parent.values.into_values();
+
+        let mut keys: Vec<String> = objects
+            .iter()
+            .filter_map(|value| match value { CValue::Object(object) => Some(object.keys().cloned()), _ => None })
+            .flatten()
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+        keys.sort();
+
+        let mut children: Vec<Vec<CValue>> = vec![Vec::with_capacity(self.count); keys.len()];
+        for value in objects {
+            let mut object = match value { CValue::Object(object) => object, _ => HashMap::new() };
+            for (key, values) in keys.iter().zip(children.iter_mut()) {
+                values.push(object.remove(key).unwrap_or(CValue::None));
+            }
+        }
+
+        for (offset, (key, values)) in keys.into_iter().zip(children).enumerate() {
+            let name = format!("{}_{}", column, key);
+            self.columns.shift_remove(&name);
+            let column = CColumn {
+                data_type: values.iter().map(|v| v.get_dtype()).find(|t| *t != CValueType::None).unwrap_or(CValueType::Unknown),
+                values: ColumnData::from_values(values),
+                expected_dtype: CValueType::Unknown,
+                dtype_strategy: DtypeStrategy::Ignore,
+            };
+            let position = (index + offset).min(self.columns.len());
+            self.columns.shift_insert(position, name, column);
+        }
+        self
+    }
+
+    /// Turns every element of an array column into its own row, the other columns are repeated.
+    /// Nulls, empty arrays and values that are not arrays are kept as one row.
+    pub fn explode(mut self, column: &str) -> Self {
+        let Some(exploded) = self.columns.get(column).map(|c| c.values.to_values()) else { return self };
+
+        let mut repeats: Vec<usize> = Vec::with_capacity(self.count);
+        let mut elements: Vec<CValue> = Vec::with_capacity(self.count);
+        for value in exploded {
+            let values = array_elements(value);
+            repeats.push(values.len());
+            elements.extend(values);
+        }
+
+        for (name, other) in self.columns.iter_mut() {
+            if name == column {
+                other.data_type = elements.iter().map(|v| v.get_dtype()).find(|t| *t != CValueType::None).unwrap_or(CValueType::Unknown);
+                other.values = ColumnData::from_values(std::mem::take(&mut elements));
+            } else {
+                let values = std::mem::take(&mut other.values).into_values();
+                other.values = ColumnData::from_values(values
+                    .into_iter()
+                    .zip(&repeats)
+                    .flat_map(|(value, repeat)| std::iter::repeat_n(value, *repeat))
+                    .collect());
+            }
+        }
+        self.count = repeats.iter().sum();
+        self
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        for column in self.columns.values_mut() {
+            column.values.shrink_to_fit();
+        }
+    }
+
+    /// Estimates the heap memory of the values, see `ColumnData::heap_size`.
+    pub fn heap_size(&self) -> usize {
+        self.columns.values().map(|column| column.values.heap_size()).sum()
+    }
+}
+
+/// A row of a CDataFrame, given to `CDataFrame::with_column`.
+pub struct Row<'a> {
+    dataframe: &'a CDataFrame,
+    index: usize,
+}
+
+impl Row<'_> {
+    /// Returns the value of a column, or of a dotted path into an object, e.g. `user.name`;
+    /// None if it is missing.
+    pub fn get(&self, column: &str) -> CValue {
+        self.dataframe.value_at(self.index, column).unwrap_or(CValue::None)
+    }
+}
+
+/// Error of a CDataFrame transformation, e.g. a value that can not be cast.
+#[derive(Debug)]
+pub struct TransformError {
+    message: String,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error transforming the dataframe: {}", self.message)
+    }
+}
+
+/// Returns the elements of an array as one row each, anything else is one row.
+fn array_elements(value: CValue) -> Vec<CValue> {
+    let elements: Vec<CValue> = match value {
+        CValue::VecString(v) => v.into_iter().map(CValue::String).collect(),
+        CValue::VecI32(v) => v.into_iter().map(CValue::I32).collect(),
+        CValue::VecI64(v) => v.into_iter().map(CValue::I64).collect(),
+        CValue::VecF32(v) => v.into_iter().map(CValue::Double32).collect(),
+        CValue::VecF64(v) => v.into_iter().map(CValue::Double64).collect(),
+        CValue::VecDyn(v) => v,
+        other => return vec![other],
+    };
+    if elements.is_empty() { vec![CValue::None] } else { elements }
+}
+
+/// Builds CDataFrames from a stream of documents in chunks of `chunk_size` rows, so only one chunk
+/// is in memory at a time.
+///
+/// Every chunk starts with the columns of the previous ones in the order they were first seen, new
+/// columns are added after them; so a column has the same position in every chunk that has it.
+pub struct CDataFrameBuilder {
+    schema: CSchema,
+    chunk_size: usize,
+    dataframe: CDataFrame,
+}
+
+impl CDataFrameBuilder {
+    pub fn new(schema: CSchema, chunk_size: usize) -> Self {
+        Self {
+            schema,
+            chunk_size,
+            dataframe: CDataFrame::new(),
+        }
+    }
+
+    /// Appends a document, returns the chunk once it has `chunk_size` rows.
+    pub fn push(&mut self, document: Document) -> Option<CDataFrame> {
+        self.dataframe.append_bson(document);
+        if self.dataframe.count < self.chunk_size {
+            return None;
+        }
+
+        let next = self.dataframe.empty_like();
+        let mut chunk = std::mem::replace(&mut self.dataframe, next);
+        chunk.set_schema(&self.schema);
+        chunk.shrink_to_fit();
+        Some(chunk)
+    }
+
+    /// Returns the last chunk, if it has any row.
+    pub fn finish(mut self) -> Option<CDataFrame> {
+        if self.dataframe.count == 0 {
+            return None;
+        }
+        self.dataframe.set_schema(&self.schema);
+        self.dataframe.shrink_to_fit();
+        Some(self.dataframe)
+    }
 }
 
 #[derive(Debug)]
 pub struct CColumn {
-    pub values: Vec<CValue>,
+    pub values: ColumnData,
     pub data_type: CValueType,
     pub expected_dtype: CValueType,
+    pub dtype_strategy: DtypeStrategy,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CValue {
     Bool(bool),
     I16(i16),
@@ -255,6 +556,16 @@ pub enum CValue {
     VecF64(Vec<f64>),
     VecDyn(Vec<CValue>),
     Object(HashMap<String, CValue>),
+    /// Milliseconds since the epoch, UTC.
+    Timestamp(i64),
+    /// Days since the epoch.
+    Date(i32),
+    /// Numeric kept as text so no precision is lost, e.g. `"12.3400"`.
+    Decimal(String),
+    Bytes(Vec<u8>),
+    Ip(IpAddr),
+    /// Longitude and latitude, in the order CrateDB uses.
+    GeoPoint(f64, f64),
     None,
     Unknown,
 }
@@ -276,19 +587,110 @@ pub enum CValueType {
     VecF64,
     VecDyn,
     Object,
+    Timestamp,
+    Date,
+    Decimal,
+    Bytes,
+    Ip,
+    GeoPoint,
     None,
     Unknown,
 }
 
+impl CValueType {
+    /// Short name of the type, used as suffix of the columns values of this type are moved to
+    /// (see `DtypeStrategy::NewCol`) and parsed back by `from_str`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bool => "bool",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::Double32 => "f32",
+            Self::Double64 => "f64",
+            Self::String => "str",
+            Self::VecString => "vecstring",
+            Self::VecI32 => "veci32",
+            Self::VecI64 => "veci64",
+            Self::VecF32 => "vecf32",
+            Self::VecF64 => "vecf64",
+            Self::VecDyn => "vecdyn",
+            Self::Object => "object",
+            Self::Timestamp => "timestamp",
+            Self::Date => "date",
+            Self::Decimal => "decimal",
+            Self::Bytes => "bytes",
+            Self::Ip => "ip",
+            Self::GeoPoint => "geopoint",
+            Self::None => "none",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Whether every value of this type can be stored as `other` without losing precision, e.g.
+    /// I32 as I64 or Double64 but not I64 as Double64.
+    pub fn fits_in(self, other: CValueType) -> bool {
+        self == other || matches!(
+            (self, other),
+            (Self::I16, Self::I32 | Self::I64 | Self::Double32 | Self::Double64)
+                | (Self::I32, Self::I64 | Self::Double64)
+                | (Self::Double32, Self::Double64)
+                | (Self::VecI32, Self::VecI64 | Self::VecF64)
+                | (Self::VecF32, Self::VecF64)
+        )
+    }
+
+    /// Returns the type that holds the values of both types without losing precision, None if
+    /// there is none, e.g. for I64 and floats.
+    pub fn widen(self, other: CValueType) -> Option<CValueType> {
+        if other.fits_in(self) {
+            return Some(self);
+        }
+        if self.fits_in(other) {
+            return Some(other);
+        }
+        match (self, other) {
+            (Self::I32, Self::Double32) | (Self::Double32, Self::I32) => Some(Self::Double64),
+            (Self::VecI32, Self::VecF32) | (Self::VecF32, Self::VecI32) => Some(Self::VecF64),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for CValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 impl FromStr for CValueType {
     type Err = ParseCValueTypeError;
 
+    /// Parses a type from its name or its variant name, case insensitive, e.g. `f64` or `Double64`.
     fn from_str(input: &str) -> Result<CValueType, ParseCValueTypeError> {
         match input.to_lowercase().as_str() {
-            "string" => Ok(CValueType::String),
-            "vecstring" => Ok(CValueType::VecString),
+            "bool" | "boolean" => Ok(CValueType::Bool),
+            "i16" => Ok(CValueType::I16),
             "i32" => Ok(CValueType::I32),
+            "i64" => Ok(CValueType::I64),
+            "f32" | "double32" => Ok(CValueType::Double32),
+            "f64" | "double64" => Ok(CValueType::Double64),
+            "str" | "string" => Ok(CValueType::String),
+            "vecstring" | "vec_string" => Ok(CValueType::VecString),
+            "veci32" | "vec_i32" => Ok(CValueType::VecI32),
+            "veci64" | "vec_i64" => Ok(CValueType::VecI64),
+            "vecf32" | "vec_f32" => Ok(CValueType::VecF32),
+            "vecf64" | "vec_f64" => Ok(CValueType::VecF64),
+            "vecdyn" | "vec_dyn" => Ok(CValueType::VecDyn),
             "object" => Ok(CValueType::Object),
+            "timestamp" => Ok(CValueType::Timestamp),
+            "date" => Ok(CValueType::Date),
+            "decimal" => Ok(CValueType::Decimal),
+            "bytes" => Ok(CValueType::Bytes),
+            "ip" => Ok(CValueType::Ip),
+            "geopoint" | "geo_point" => Ok(CValueType::GeoPoint),
+            "none" => Ok(CValueType::None),
+            "unknown" => Ok(CValueType::Unknown),
             _ => Err(ParseCValueTypeError { message: format!("'{}' is not a valid CValue", input) }),
         }
     }
@@ -302,18 +704,28 @@ pub struct ParseCValueTypeError {
 impl CValue {
     pub fn get_dtype(&self) -> CValueType {
         match self {
+            Self::Bool(_) => CValueType::Bool,
             Self::String(_) => CValueType::String,
             Self::I16(_) => CValueType::I16,
             Self::I32(_) => CValueType::I32,
             Self::I64(_) => CValueType::I64,
+            Self::Double32(_) => CValueType::Double32,
+            Self::Double64(_) => CValueType::Double64,
+            Self::VecString(_) => CValueType::VecString,
+            Self::VecI32(_) => CValueType::VecI32,
+            Self::VecI64(_) => CValueType::VecI64,
+            Self::VecF32(_) => CValueType::VecF32,
+            Self::VecF64(_) => CValueType::VecF64,
             Self::VecDyn(_) => CValueType::VecDyn,
             Self::Object(_) => CValueType::Object,
+            Self::Timestamp(_) => CValueType::Timestamp,
+            Self::Date(_) => CValueType::Date,
+            Self::Decimal(_) => CValueType::Decimal,
+            Self::Bytes(_) => CValueType::Bytes,
+            Self::Ip(_) => CValueType::Ip,
+            Self::GeoPoint(_, _) => CValueType::GeoPoint,
             Self::None => CValueType::None,
             Self::Unknown => CValueType::Unknown,
-
-            _ => {
-                CValueType::Unknown
-            }
         }
     }
 
@@ -329,14 +741,34 @@ impl CValue {
 impl fmt::Display for CValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Self::String(s) => write!(f, "{:^10}", s),
+            Self::String(s) => write!(f, "{}", s),
             Self::I16(s) => write!(f, "{}", s),
-            Self::I32(s) => write!(f, "{:^10}", s),
+            Self::I32(s) => write!(f, "{}", s),
             Self::I64(s) => write!(f, "{}", s),
+            Self::Bool(s) => write!(f, "{}", s),
+            Self::Double32(s) => write!(f, "{}", s),
+            Self::Double64(s) => write!(f, "{}", s),
+            Self::VecString(v) => write!(f, "{:?}", v),
+            Self::VecI32(v) => write!(f, "{:?}", v),
+            Self::VecI64(v) => write!(f, "{:?}", v),
+            Self::VecF32(v) => write!(f, "{:?}", v),
+            Self::VecF64(v) => write!(f, "{:?}", v),
             Self::VecDyn(v) => write!(f, "{:?}", v),
             Self::Object(v) => write!(f, "{:?}", v),
-            Self::None => write!(f, "   None   "),
-            _ => write!(f, "")
+            Self::Timestamp(ms) => match DateTime::from_timestamp_millis(*ms) {
+                Some(timestamp) => write!(f, "{}", timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)),
+                None => write!(f, "{}", ms),
+            },
+            Self::Date(days) => match epoch_days_to_date(*days) {
+                Some(date) => write!(f, "{}", date),
+                None => write!(f, "{}", days),
+            },
+            Self::Decimal(s) => write!(f, "{}", s),
+            Self::Bytes(v) => write!(f, "\\x{}", v.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+            Self::Ip(v) => write!(f, "{}", v),
+            Self::GeoPoint(lon, lat) => write!(f, "POINT ({} {})", lon, lat),
+            Self::None => write!(f, "None"),
+            Self::Unknown => write!(f, "Unknown"),
         }
     }
 }
@@ -348,14 +780,117 @@ impl fmt::Display for ParseCValueTypeError {
 
 
 pub fn get_inner_cvalue_type_name(value: &CValue) -> String {
-    match value {
-        CValue::String(_) => "str".to_string(),
-        CValue::I64(_) => "i32".to_string(),
-        CValue::VecF32(_) => "vec_f32".to_string(),
-        CValue::I32(_) => "i32".to_string(),
-        CValue::VecString(_) => "vecstring".to_string(),
-        CValue::VecDyn(_) => "vecdyn".to_string(),
-        CValue::Object(_) => "object".to_string(),
-        _ => "none".to_string()
-    }
-}
\ No newline at end of file
+    value.get_dtype().name().to_string()
+}
+
+/// Returns the date `days` after 1970-01-01.
+pub fn epoch_days_to_date(days: i32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(1970, 1, 1)?.checked_add_signed(TimeDelta::try_days(days as i64)?)
+}
+
+/// Returns the days from 1970-01-01 to `date`, chrono's dates are all within the range of an i32.
+pub fn date_to_epoch_days(date: NaiveDate) -> i32 {
+    date.signed_duration_since(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use mongodb::bson::doc;
+
+    use crate::experiment::data::{get_inner_cvalue_type_name, CDataFrameBuilder, CValue, CValueType, DtypeStrategy};
+    use crate::experiment::schema::{CSchema, ColumnInfo};
+
+    #[test]
+    fn test_builder_chunks() {
+        let schema = CSchema {
+            columns: HashMap::from([("name".to_string(), ColumnInfo {
+                dtype: CValueType::String,
+                dtype_collision_strategy: DtypeStrategy::Cast,
+                sub_schema: None,
+                cast_fallback: None,
+                nullable: None,
+                element_dtype: None,
+            })]),
+        };
+        let mut builder = CDataFrameBuilder::new(schema, 2);
+
+        assert!(builder.push(doc! { "id": 1, "name": "a" }).is_none());
+        let first = builder.push(doc! { "id": 2, "extra": true }).unwrap();
+        assert_eq!(first.count, 2);
+        assert_eq!(first.columns.keys().collect::<Vec<_>>(), vec!["id", "name", "extra"]);
+        assert!(matches!(first.columns["extra"].values.to_values()[..], [CValue::None, CValue::Bool(true)]));
+        assert_eq!(first.columns["name"].expected_dtype, CValueType::String);
+
+        // Later chunks keep the columns and their order, new ones go last.
+        assert!(builder.push(doc! { "new": 1, "name": "c" }).is_none());
+        let last = builder.finish().unwrap();
+        assert_eq!(last.count, 1);
+        assert_eq!(last.columns.keys().collect::<Vec<_>>(), vec!["id", "name", "extra", "new"]);
+        assert!(last.columns.values().all(|c| c.values.len() == 1));
+        assert!(matches!(last.columns["id"].values.get(0), CValue::None));
+        assert_eq!(last.columns["name"].expected_dtype, CValueType::String);
+
+        // Chunks fold back into one CDataFrame.
+        let mut dataframe = first;
+        dataframe.append(last);
+        assert_eq!(dataframe.count, 3);
+        assert!(dataframe.columns.values().all(|c| c.values.len() == 3));
+        assert!(matches!(dataframe.columns["name"].values.to_values()[..], [CValue::String(_), CValue::None, CValue::String(_)]));
+        assert!(matches!(dataframe.columns["new"].values.to_values()[..], [CValue::None, CValue::None, CValue::I32(1)]));
+    }
+
+    fn every_value() -> Vec<CValue> {
+        vec![
+            CValue::Bool(true),
+            CValue::I16(1),
+            CValue::I32(2),
+            CValue::I64(3),
+            CValue::Double32(0.5),
+            CValue::Double64(1.5),
+            CValue::String("a".to_string()),
+            CValue::VecString(vec!["a".to_string()]),
+            CValue::VecI32(vec![1]),
+            CValue::VecI64(vec![2]),
+            CValue::VecF32(vec![0.5]),
+            CValue::VecF64(vec![1.5]),
+            CValue::VecDyn(vec![CValue::I32(1), CValue::None]),
+            CValue::Object(HashMap::from([("k".to_string(), CValue::I64(1))])),
+            CValue::Timestamp(1704164645000),
+            CValue::Date(19724),
+            CValue::Decimal("0.10000000000000000001".to_string()),
+            CValue::Bytes(vec![10, 255]),
+            CValue::Ip("10.0.0.1".parse().unwrap()),
+            CValue::GeoPoint(9.74, 47.4),
+            CValue::None,
+            CValue::Unknown,
+        ]
+    }
+
+    #[test]
+    fn test_dtypes() {
+        for value in every_value() {
+            let dtype = value.get_dtype();
+            assert_eq!(dtype.name().parse::<CValueType>().unwrap(), dtype);
+            assert_eq!(format!("{:?}", dtype).parse::<CValueType>().unwrap(), dtype);
+            assert_eq!(get_inner_cvalue_type_name(&value), dtype.to_string());
+            assert!(!value.to_string().is_empty());
+
+            let json = serde_json::to_string(&value).unwrap();
+            let back: CValue = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, value);
+            assert_eq!(serde_json::from_str::<CValueType>(&serde_json::to_string(&dtype).unwrap()).unwrap(), dtype);
+        }
+        assert!("i128".parse::<CValueType>().is_err());
+
+        assert_eq!(CValue::Timestamp(1704164645000).to_string(), "2024-01-02T03:04:05.000Z");
+        assert_eq!(CValue::Date(19724).to_string(), "2024-01-02");
+        assert_eq!(CValue::Bytes(vec![10, 255]).to_string(), "\\x0aff");
+        assert_eq!(CValue::GeoPoint(9.74, 47.4).to_string(), "POINT (9.74 47.4)");
+        // Values are not padded, whatever their type.
+        assert_eq!(CValue::String("a".to_string()).to_string(), "a");
+        assert_eq!(CValue::I32(7).to_string(), "7");
+        assert_eq!(CValue::None.to_string(), "None");
+    }
+}
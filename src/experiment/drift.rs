@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::experiment::data::CValueType;
+use crate::experiment::infer::PathStats;
+use crate::experiment::schema::{CSchema, ColumnInfo};
+use crate::metadata::Metadata;
+use crate::sink::cratedb::driver::{data_type_to_ddl, CrateDB, CrateDBTable};
+
+/// What would break, or change, writing a source column into the target table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriftKind {
+    /// The target table does not exist, it is created from the source's types.
+    MissingTable,
+    /// The target table does not have the column; CrateDB adds it from its first value unless
+    /// the table, or its object, is strict.
+    MissingColumn(CValueType),
+    /// A NOT NULL column of the target table that the source does not have.
+    MissingRequired(String),
+    Incompatible { dtype: CValueType, target: String },
+    /// The source type is wider than the target's and no values were observed, they may be out of
+    /// its range.
+    Narrowing { dtype: CValueType, target: String },
+    /// Observed values do not fit the target type, e.g. i64 into INTEGER, or integers above 2^53 into
+    /// DOUBLE PRECISION.
+    OutOfRange { min: f64, max: f64, target: String },
+    /// The source has nulls or misses the column in some rows, the target column is NOT NULL.
+    Nullable(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    /// Column or dotted path of an object field, e.g. `user.address.zip`.
+    pub column: String,
+    pub kind: DriftKind,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            DriftKind::MissingTable => write!(f, "the table does not exist, it will be created"),
+            DriftKind::MissingColumn(dtype) => write!(f, "{}: {:?} is not in the target, it will be added unless the table is strict", self.column, dtype),
+            DriftKind::MissingRequired(target) => write!(f, "{}: {} NOT NULL is not in the source, every insert will fail", self.column, target),
+            DriftKind::Incompatible { dtype, target } => write!(f, "{}: {:?} can not be written to {}", self.column, dtype, target),
+            DriftKind::Narrowing { dtype, target } => write!(f, "{}: {:?} may not fit {}", self.column, dtype, target),
+            DriftKind::OutOfRange { min, max, target } => write!(f, "{}: values from {} to {} do not fit {}", self.column, min, max, target),
+            DriftKind::Nullable(target) => write!(f, "{}: has nulls but {} is NOT NULL", self.column, target),
+        }
+    }
+}
+
+/// Range of the integer types, and their rank to compare their width.
+fn integer_range(ddl: &str) -> Option<(usize, f64, f64)> {
+    match ddl {
+        "BYTE" | "CHAR" => Some((0, i8::MIN as f64, i8::MAX as f64)),
+        "SMALLINT" => Some((1, i16::MIN as f64, i16::MAX as f64)),
+        "INTEGER" => Some((2, i32::MIN as f64, i32::MAX as f64)),
+        "BIGINT" => Some((3, i64::MIN as f64, i64::MAX as f64)),
+        _ => None
+    }
+}
+
+fn integer_rank(dtype: CValueType) -> Option<usize> {
+    match dtype {
+        CValueType::I16 => Some(1),
+        CValueType::I32 => Some(2),
+        CValueType::I64 => Some(3),
+        _ => None
+    }
+}
+
+/// Bits of the integer types, and of the mantissa of the float types; integers wider than the
+/// mantissa are rounded once they are above it, e.g. 2^53 + 1 into DOUBLE PRECISION.
+fn integer_bits(dtype: CValueType) -> u32 {
+    match dtype {
+        CValueType::I16 => 15,
+        CValueType::I32 => 31,
+        _ => 63
+    }
+}
+
+fn float_mantissa(ddl: &str) -> u32 {
+    if ddl == "REAL" { 24 } else { 53 }
+}
+
+fn array_element(dtype: CValueType, element_dtype: Option<CValueType>) -> Option<Option<CValueType>> {
+    match dtype {
+        CValueType::VecString => Some(Some(CValueType::String)),
+        CValueType::VecI32 => Some(Some(CValueType::I32)),
+        CValueType::VecI64 => Some(Some(CValueType::I64)),
+        CValueType::VecF32 => Some(Some(CValueType::Double32)),
+        CValueType::VecF64 => Some(Some(CValueType::Double64)),
+        CValueType::VecDyn => Some(element_dtype),
+        _ => None
+    }
+}
+
+/// Checks that values of `dtype` can be written to a column of the `target` DDL type, using the
+/// observed range of the values if there is one.
+fn check_type(dtype: CValueType, element_dtype: Option<CValueType>, target: &str, stats: Option<&PathStats>) -> Option<DriftKind> {
+    let incompatible = Some(DriftKind::Incompatible { dtype, target: target.to_string() });
+    let out_of_range = |low: f64, high: f64| {
+        let (min, max) = (stats?.min?, stats?.max?);
+        (min < low || max > high).then(|| DriftKind::OutOfRange { min, max, target: target.to_string() })
+    };
+
+    if let Some(element) = array_element(dtype, element_dtype) {
+        return match (target.strip_prefix("ARRAY(").and_then(|t| t.strip_suffix(')')), element) {
+            (Some(inner), Some(element)) => check_type(element, None, inner, stats),
+            (Some(_), None) => None,
+            _ if target == "FLOAT_VECTOR" || target == "GEO_POINT" => None,
+            _ => incompatible
+        };
+    }
+
+    match (dtype, target) {
+        (CValueType::None | CValueType::Unknown, _) => None,
+        (_, "TEXT" | "CHARACTER VARYING") if dtype != CValueType::Object => None,
+        (CValueType::Bool, "BOOLEAN") => None,
+        (CValueType::Object, target) if target.starts_with("OBJECT") => None,
+        (CValueType::String, "IP" | "GEO_POINT" | "GEO_SHAPE") => None,
+        (CValueType::String | CValueType::I32 | CValueType::I64 | CValueType::Timestamp | CValueType::Date, target) if target.starts_with("TIMESTAMP") => None,
+        (CValueType::Ip, "IP") | (CValueType::GeoPoint, "GEO_POINT") => None,
+        (CValueType::I16 | CValueType::I32 | CValueType::I64 | CValueType::Decimal, "NUMERIC") => None,
+        (CValueType::Decimal, "DOUBLE PRECISION" | "REAL") => Some(DriftKind::Narrowing { dtype, target: target.to_string() }),
+        (CValueType::I16 | CValueType::I32 | CValueType::I64, target) if integer_range(target).is_some() => {
+            let (rank, low, high) = integer_range(target).unwrap();
+            if integer_rank(dtype).unwrap() <= rank {
+                return None;
+            }
+            match stats.and_then(|s| s.min) {
+                Some(_) => out_of_range(low, high),
+                None => Some(DriftKind::Narrowing { dtype, target: target.to_string() })
+            }
+        }
+        (CValueType::I32 | CValueType::I64, "DOUBLE PRECISION" | "REAL") if float_mantissa(target) < integer_bits(dtype) => {
+            let limit = (1u64 << float_mantissa(target)) as f64;
+            match stats.and_then(|s| s.min) {
+                Some(_) => out_of_range(-limit, limit),
+                None => Some(DriftKind::Narrowing { dtype, target: target.to_string() })
+            }
+        }
+        (CValueType::I16 | CValueType::I32 | CValueType::I64 | CValueType::Double32 | CValueType::Double64, "DOUBLE PRECISION") => None,
+        (CValueType::I16 | CValueType::I32 | CValueType::Double32, "REAL") => None,
+        (CValueType::Double64, "REAL") => match stats.and_then(|s| s.min) {
+            Some(_) => out_of_range(f32::MIN as f64, f32::MAX as f64),
+            None => Some(DriftKind::Narrowing { dtype, target: target.to_string() })
+        },
+        _ => incompatible
+    }
+}
+
+/// Flattens a schema and its sub-schemas into dotted paths.
+fn flatten<'a>(columns: &'a HashMap<String, ColumnInfo>, prefix: &str, paths: &mut BTreeMap<String, &'a ColumnInfo>) {
+    for (name, column_info) in columns {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        if let Some(sub_schema) = &column_info.sub_schema {
+            flatten(sub_schema, &path, paths);
+        }
+        paths.insert(path, column_info);
+    }
+}
+
+/// Compares the declared or inferred schema of a source table with the columns of the target
+/// table, as returned by `CrateDB::get_column_definitions`. `stats`, from a `SchemaInference`,
+/// adds range and null checks with the observed values.
+pub fn compare_schema(source: &CSchema, stats: Option<&BTreeMap<String, PathStats>>, target: &[(String, String, bool)]) -> Vec<Drift> {
+    if target.is_empty() {
+        return vec![Drift { column: String::new(), kind: DriftKind::MissingTable }];
+    }
+
+    let mut paths = BTreeMap::new();
+    flatten(&source.columns, "", &mut paths);
+    let target: BTreeMap<&str, (String, bool)> = target
+        .iter()
+        .map(|(path, data_type, nullable)| (path.as_str(), (data_type_to_ddl(data_type).to_uppercase(), *nullable)))
+        .collect();
+
+    let mut drifts = vec![];
+    for (path, column_info) in &paths {
+        let drift = |kind| Drift { column: path.clone(), kind };
+        let path_stats = stats.and_then(|stats| stats.get(path));
+
+        let Some((ddl, target_nullable)) = target.get(path.as_str()) else {
+            // Fields of a missing object are added with it.
+            let parent_missing = path.rsplit_once('.').is_some_and(|(parent, _)| !target.contains_key(parent));
+            if !parent_missing {
+                drifts.push(drift(DriftKind::MissingColumn(column_info.dtype)));
+            }
+            continue;
+        };
+
+        if let Some(kind) = check_type(column_info.dtype, column_info.element_dtype, ddl, path_stats) {
+            drifts.push(drift(kind));
+        }
+        let nullable = column_info.nullable.unwrap_or(false) || path_stats.is_some_and(|s| s.nulls > 0);
+        if nullable && !target_nullable {
+            drifts.push(drift(DriftKind::Nullable(ddl.clone())));
+        }
+    }
+
+    for (path, (ddl, nullable)) in &target {
+        if !nullable && !paths.contains_key(*path) {
+            drifts.push(Drift { column: path.to_string(), kind: DriftKind::MissingRequired(ddl.clone()) });
+        }
+    }
+    drifts
+}
+
+/// Reports what would break migrating a table with the `source` schema into `<schema>.<table_name>`
+/// of `cratedb`, without writing any data; see `compare_schema`.
+pub async fn schema_drift(cratedb: &CrateDB, schema: &str, table_name: &str, source: &CSchema, stats: Option<&BTreeMap<String, PathStats>>) -> Result<Vec<Drift>, sqlx::Error> {
+    let pool = cratedb.get_pool().await?;
+    let table = CrateDBTable { schema: schema.to_string(), name: table_name.to_string() };
+    let target = cratedb.get_column_definitions(&pool, &table).await?;
+
+    Ok(compare_schema(source, stats, &target))
+}
+
+pub fn print_drift(table: &str, drifts: &Vec<Drift>, metadata: &Metadata) {
+    if drifts.is_empty() {
+        metadata.print_line(&format!("{}: the source schema matches the target", table));
+        return;
+    }
+    metadata.print_line(&format!("{}: {} differences with the target", table, drifts.len()));
+    for drift in drifts {
+        metadata.print_line(&format!("  {}", drift));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::experiment::data::{CValue, CValueType};
+    use crate::experiment::drift::{compare_schema, Drift, DriftKind};
+    use crate::experiment::infer::SchemaInference;
+
+    fn target() -> Vec<(String, String, bool)> {
+        vec![
+            ("id".to_string(), "integer".to_string(), false),
+            ("score".to_string(), "real".to_string(), true),
+            ("name".to_string(), "text".to_string(), true),
+            ("tags".to_string(), "text_array".to_string(), true),
+            ("user".to_string(), "object".to_string(), true),
+            ("user.age".to_string(), "smallint".to_string(), true),
+            ("created".to_string(), "timestamp with time zone".to_string(), false),
+            ("views".to_string(), "double precision".to_string(), true),
+        ]
+    }
+
+    fn drift(column: &str, kind: DriftKind) -> Drift {
+        Drift { column: column.to_string(), kind }
+    }
+
+    #[test]
+    fn test_declared_schema() {
+        let schema = serde_json::from_str(r#"{
+            "id": {"dtype": "I64", "dtype_collision_strategy": "Cast"},
+            "score": {"dtype": "Double64", "dtype_collision_strategy": "Cast"},
+            "name": {"dtype": "I32", "dtype_collision_strategy": "Cast"},
+            "tags": {"dtype": "VecI64", "dtype_collision_strategy": "Cast"},
+            "user": {"dtype": "Object", "dtype_collision_strategy": "Cast", "sub_schema": {
+                "age": {"dtype": "String", "dtype_collision_strategy": "Cast"},
+                "email": {"dtype": "String", "dtype_collision_strategy": "Cast"}
+            }},
+            "address": {"dtype": "Object", "dtype_collision_strategy": "Cast", "sub_schema": {
+                "zip": {"dtype": "String", "dtype_collision_strategy": "Cast"}
+            }},
+            "views": {"dtype": "I64", "dtype_collision_strategy": "Cast"}
+        }"#).unwrap();
+
+        assert_eq!(compare_schema(&schema, None, &target()), vec![
+            drift("address", DriftKind::MissingColumn(CValueType::Object)),
+            drift("id", DriftKind::Narrowing { dtype: CValueType::I64, target: "INTEGER".to_string() }),
+            drift("score", DriftKind::Narrowing { dtype: CValueType::Double64, target: "REAL".to_string() }),
+            drift("user.age", DriftKind::Incompatible { dtype: CValueType::String, target: "SMALLINT".to_string() }),
+            drift("user.email", DriftKind::MissingColumn(CValueType::String)),
+            drift("views", DriftKind::Narrowing { dtype: CValueType::I64, target: "DOUBLE PRECISION".to_string() }),
+            drift("created", DriftKind::MissingRequired("TIMESTAMP WITH TIME ZONE".to_string())),
+        ]);
+        assert!(compare_schema(&schema, None, &[])[0].kind == DriftKind::MissingTable);
+    }
+
+    #[test]
+    fn test_inferred_schema() {
+        let inference = SchemaInference::new(None);
+        let columns = vec!["id".to_string(), "score".to_string(), "tags".to_string(), "user".to_string(), "created".to_string(), "views".to_string()];
+        let user = |age: i64| CValue::Object(HashMap::from([("age".to_string(), CValue::I64(age))]));
+        inference.observe(&columns, &[
+            vec![CValue::I64(1), CValue::Double64(1.5), CValue::VecDyn(vec![CValue::I64(1)]), user(30), CValue::String("2024-01-01".to_string()), CValue::I64(1 << 60)],
+            vec![CValue::None, CValue::Double64(1e40), CValue::None, user(40_000), CValue::I64(1704067200000), CValue::I64(10)],
+        ]);
+
+        assert_eq!(compare_schema(&inference.schema(), Some(&inference.stats()), &target()), vec![
+            drift("id", DriftKind::Nullable("INTEGER".to_string())),
+            drift("score", DriftKind::OutOfRange { min: 1.5, max: 1e40, target: "REAL".to_string() }),
+            drift("user.age", DriftKind::OutOfRange { min: 30.0, max: 40_000.0, target: "SMALLINT".to_string() }),
+            drift("views", DriftKind::OutOfRange { min: 10.0, max: (1u64 << 60) as f64, target: "DOUBLE PRECISION".to_string() }),
+        ]);
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::experiment::data::{CValue, CValueType, DtypeStrategy};
+use crate::experiment::schema::{CSchema, ColumnInfo};
+use crate::metadata::Metadata;
+use crate::source::source::{MigrationError, Sink, SinkError, Source};
+
+/// Types observed at a column or nested path, e.g. `user.address.zip`; the elements of arrays of
+/// objects share the path of the array.
+#[derive(Debug, Clone, Default)]
+pub struct PathStats {
+    /// Values seen, nulls included.
+    pub count: usize,
+    pub nulls: usize,
+    /// Objects seen, the nested paths with fewer values are missing in some of them.
+    pub objects: usize,
+    pub empty_arrays: usize,
+    pub types: Vec<(CValueType, usize)>,
+    pub element_types: Vec<(CValueType, usize)>,
+    /// Smallest and largest number seen, of scalars and array elements.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+fn tally(types: &mut Vec<(CValueType, usize)>, dtype: CValueType) {
+    match types.iter_mut().find(|(t, _)| *t == dtype) {
+        Some((_, count)) => *count += 1,
+        None => types.push((dtype, 1))
+    }
+}
+
+fn element_dtype(value: &CValue) -> Option<CValueType> {
+    match value {
+        CValue::VecString(_) => Some(CValueType::String),
+        CValue::VecI32(_) => Some(CValueType::I32),
+        CValue::VecI64(_) => Some(CValueType::I64),
+        CValue::VecF32(_) => Some(CValueType::Double32),
+        CValue::VecF64(_) => Some(CValueType::Double64),
+        _ => None
+    }
+}
+
+fn is_empty_array(value: &CValue) -> bool {
+    match value {
+        CValue::VecString(v) => v.is_empty(),
+        CValue::VecI32(v) => v.is_empty(),
+        CValue::VecI64(v) => v.is_empty(),
+        CValue::VecF32(v) => v.is_empty(),
+        CValue::VecF64(v) => v.is_empty(),
+        CValue::VecDyn(v) => v.is_empty(),
+        _ => false
+    }
+}
+
+/// Returns the type that holds both types, e.g. the wider integer or Double64 for integers and
+/// floats, None if there is none. Unlike `CValueType::widen` it is not lossless: integers above
+/// 2^53 lose precision as Double64, which the suggested Cast strategy does not flag.
+fn widen(a: CValueType, b: CValueType) -> Option<CValueType> {
+    let rank = |t: CValueType| match t {
+        CValueType::I16 => Some(0),
+        CValueType::I32 => Some(1),
+        CValueType::I64 => Some(2),
+        CValueType::Double32 => Some(3),
+        CValueType::Double64 => Some(4),
+        _ => None
+    };
+    let array_rank = |t: CValueType| match t {
+        CValueType::VecI32 => Some(0),
+        CValueType::VecI64 => Some(1),
+        CValueType::VecF32 => Some(2),
+        CValueType::VecF64 => Some(3),
+        _ => None
+    };
+
+    if a == b {
+        return Some(a);
+    }
+    match (rank(a), rank(b), array_rank(a), array_rank(b)) {
+        // A float can not hold every i64, integers and floats are kept as Double64.
+        (Some(x), Some(y), _, _) if x.max(y) >= 3 => Some(CValueType::Double64),
+        (Some(x), Some(y), _, _) => Some(if x > y { a } else { b }),
+        (_, _, Some(x), Some(y)) if x.max(y) >= 2 => Some(CValueType::VecF64),
+        (_, _, Some(x), Some(y)) => Some(if x > y { a } else { b }),
+        _ => None
+    }
+}
+
+impl PathStats {
+    fn observe_number(&mut self, value: f64) {
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn observe_numbers(&mut self, value: &CValue) {
+        match value {
+            CValue::I16(v) => self.observe_number(*v as f64),
+            CValue::I32(v) => self.observe_number(*v as f64),
+            CValue::I64(v) => self.observe_number(*v as f64),
+            CValue::Double32(v) => self.observe_number(*v as f64),
+            CValue::Double64(v) => self.observe_number(*v),
+            CValue::VecI32(v) => v.iter().for_each(|v| self.observe_number(*v as f64)),
+            CValue::VecI64(v) => v.iter().for_each(|v| self.observe_number(*v as f64)),
+            CValue::VecF32(v) => v.iter().for_each(|v| self.observe_number(*v as f64)),
+            CValue::VecF64(v) => v.iter().for_each(|v| self.observe_number(*v)),
+            CValue::VecDyn(v) => v.iter().for_each(|v| if !matches!(v, CValue::VecDyn(_)) { self.observe_numbers(v) }),
+            _ => {}
+        }
+    }
+
+    /// Returns the dtype that holds most values, the most frequent type widened with the types
+    /// compatible with it (see `widen`).
+    pub fn dominant_dtype(&self) -> CValueType {
+        let mut types = self.types.clone();
+        types.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let Some((mut dominant, _)) = types.first().cloned() else {
+            return if self.empty_arrays > 0 { CValueType::VecDyn } else { CValueType::Unknown };
+        };
+        for (dtype, _) in &types[1..] {
+            if let Some(wider) = widen(dominant, *dtype) {
+                dominant = wider;
+            }
+        }
+        dominant
+    }
+
+    /// Returns the observed types the dominant dtype does not hold, with how many values had them.
+    pub fn conflicts(&self) -> Vec<(CValueType, usize)> {
+        let dominant = self.dominant_dtype();
+        self.types.iter().filter(|(dtype, _)| widen(dominant, *dtype) != Some(dominant)).cloned().collect()
+    }
+
+    pub fn dominant_element_dtype(&self) -> Option<CValueType> {
+        let mut types = self.element_types.clone();
+        types.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let (first, _) = types.first().cloned()?;
+        Some(types[1..].iter().fold(first, |dominant, (dtype, _)| widen(dominant, *dtype).unwrap_or(dominant)))
+    }
+
+    /// Suggests how to handle the values of other types: casting if every conflicting type can be
+    /// cast to the dominant one, e.g. anything to String or numeric strings to numbers, with the
+    /// values that can not be cast moved to a new column; a new column for objects and arrays.
+    pub fn suggested_strategy(&self) -> (DtypeStrategy, Option<DtypeStrategy>) {
+        let conflicts = self.conflicts();
+        if conflicts.is_empty() {
+            return (DtypeStrategy::Cast, None);
+        }
+        let scalar = |dtype: CValueType| matches!(dtype,
+            CValueType::Bool | CValueType::I16 | CValueType::I32 | CValueType::I64 |
+            CValueType::Double32 | CValueType::Double64 | CValueType::String |
+            CValueType::Timestamp | CValueType::Date | CValueType::Decimal | CValueType::Ip);
+
+        match self.dominant_dtype() {
+            CValueType::String => (DtypeStrategy::Cast, None),
+            dominant if scalar(dominant) && conflicts.iter().all(|(dtype, _)| scalar(*dtype)) => {
+                (DtypeStrategy::Cast, Some(DtypeStrategy::NewCol))
+            }
+            _ => (DtypeStrategy::NewCol, None)
+        }
+    }
+}
+
+/// A path whose values have types the inferred dtype does not hold.
+#[derive(Debug, Clone)]
+pub struct TypeConflict {
+    pub path: String,
+    pub dtype: CValueType,
+    pub strategy: DtypeStrategy,
+    pub conflicts: Vec<(CValueType, usize)>,
+    pub count: usize,
+}
+
+impl fmt::Display for TypeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let conflicts: Vec<String> = self.conflicts.iter().map(|(dtype, count)| format!("{:?} ({})", dtype, count)).collect();
+        write!(f, "{}: {:?}, but {} of {} values are {}, suggested {:?}",
+               self.path,
+               self.dtype,
+               self.conflicts.iter().map(|(_, count)| count).sum::<usize>(),
+               self.count,
+               conflicts.join(", "),
+               self.strategy)
+    }
+}
+
+/// Infers a `CSchema` from the rows of a table. It is a `Sink`, so it can be given to any
+/// migration, see `infer_schema`, or fed rows with `observe`.
+///
+/// Only the first `sample` rows are looked at. Once they are seen, batches return an error so the
+/// source stops reading the table; `infer_schema` ends there.
+#[derive(Debug, Default)]
+pub struct SchemaInference {
+    pub(crate) sample: Option<usize>,
+    rows: Mutex<usize>,
+    paths: Mutex<BTreeMap<String, PathStats>>,
+}
+
+impl SchemaInference {
+    pub fn new(sample: Option<usize>) -> Self {
+        Self { sample, ..Default::default() }
+    }
+
+    fn observe_value(paths: &mut BTreeMap<String, PathStats>, path: &str, value: &CValue) {
+        let stats = paths.entry(path.to_string()).or_default();
+        stats.count += 1;
+
+        match value {
+            CValue::None | CValue::Unknown => stats.nulls += 1,
+            value if is_empty_array(value) => stats.empty_arrays += 1,
+            CValue::Object(object) => {
+                tally(&mut stats.types, CValueType::Object);
+                stats.objects += 1;
+                for (key, value) in object {
+                    Self::observe_value(paths, &format!("{}.{}", path, key), value);
+                }
+            }
+            CValue::VecDyn(values) => {
+                tally(&mut stats.types, CValueType::VecDyn);
+                stats.observe_numbers(value);
+                for value in values.iter().filter(|v| !matches!(v, CValue::None | CValue::Unknown)) {
+                    tally(&mut stats.element_types, value.get_dtype());
+                }
+                // The fields of an array of objects are tallied like the fields of an object.
+                for value in values {
+                    if let CValue::Object(object) = value {
+                        let stats = paths.get_mut(path).unwrap();
+                        stats.objects += 1;
+                        for (key, value) in object {
+                            Self::observe_value(paths, &format!("{}.{}", path, key), value);
+                        }
+                    }
+                }
+            }
+            value => {
+                tally(&mut stats.types, value.get_dtype());
+                stats.observe_numbers(value);
+                if let Some(dtype) = element_dtype(value) {
+                    tally(&mut stats.element_types, dtype);
+                }
+            }
+        }
+    }
+
+    /// Tallies the types of a batch of rows, stops once `sample` rows were seen.
+    pub fn observe(&self, columns: &[String], rows: &[Vec<CValue>]) {
+        let mut seen = self.rows.lock().unwrap();
+        let rows = match self.sample {
+            Some(sample) => &rows[..rows.len().min(sample.saturating_sub(*seen))],
+            None => rows
+        };
+        *seen += rows.len();
+
+        let mut paths = self.paths.lock().unwrap();
+        for row in rows {
+            for (column, value) in columns.iter().zip(row) {
+                Self::observe_value(&mut paths, column, value);
+            }
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        *self.rows.lock().unwrap()
+    }
+
+    /// Whether the first `sample` rows were seen, never without a sample.
+    pub fn is_full(&self) -> bool {
+        self.sample.is_some_and(|sample| self.rows() >= sample)
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, PathStats> {
+        self.paths.lock().unwrap().clone()
+    }
+
+    fn column_info(&self, paths: &BTreeMap<String, PathStats>, path: &str, parent_count: usize) -> ColumnInfo {
+        let stats = &paths[path];
+        let (strategy, fallback) = stats.suggested_strategy();
+        let children: HashMap<String, ColumnInfo> = paths
+            .range(format!("{}.", path)..)
+            .take_while(|(child, _)| child.starts_with(&format!("{}.", path)))
+            .filter(|(child, _)| !child[path.len() + 1..].contains('.'))
+            .map(|(child, _)| (child[path.len() + 1..].to_string(), self.column_info(paths, child, stats.objects)))
+            .collect();
+
+        ColumnInfo {
+            dtype: stats.dominant_dtype(),
+            dtype_collision_strategy: strategy,
+            sub_schema: if children.is_empty() { None } else { Some(children) },
+            cast_fallback: fallback,
+            nullable: Some(stats.nulls > 0 || stats.count < parent_count),
+            element_dtype: stats.dominant_element_dtype(),
+        }
+    }
+
+    /// Returns the inferred schema: the dominant dtype of every column and nested path, whether it
+    /// is nullable, the element dtype of arrays and a suggested strategy for the other types.
+    pub fn schema(&self) -> CSchema {
+        let paths = self.paths.lock().unwrap();
+        let rows = self.rows();
+        CSchema {
+            columns: paths
+                .keys()
+                .filter(|path| !path.contains('.'))
+                .map(|path| (path.clone(), self.column_info(&paths, path, rows)))
+                .collect()
+        }
+    }
+
+    /// Returns the paths with values of types their dtype does not hold.
+    pub fn conflicts(&self) -> Vec<TypeConflict> {
+        self.paths
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, stats)| !stats.conflicts().is_empty())
+            .map(|(path, stats)| TypeConflict {
+                path: path.clone(),
+                dtype: stats.dominant_dtype(),
+                strategy: stats.suggested_strategy().0,
+                conflicts: stats.conflicts(),
+                count: stats.count,
+            })
+            .collect()
+    }
+
+    pub fn print_report(&self, metadata: &Metadata) {
+        let conflicts = self.conflicts();
+        metadata.print_line(&format!("Inferred the schema of {} columns from {} rows, {} with type conflicts",
+                                     self.paths.lock().unwrap().len(),
+                                     self.rows(),
+                                     conflicts.len()));
+        for conflict in conflicts {
+            metadata.print_line(&format!("  {}", conflict));
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for &SchemaInference {
+    async fn create_table(&self, _schema: &str, _table_name: &str, _columns: &[(String, String)]) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn list_columns(&self, _schema: &str, _table_name: &str) -> Vec<String> {
+        vec![]
+    }
+
+    async fn add_column(&self, _schema: &str, _table_name: &str, _column: &str, _dtype: &str) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn send_batch(&self, _schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        self.observe(columns, &buffer);
+        match self.is_full() {
+            true => Err(SinkError::new(format!("The sample of {} rows of {} is read", self.rows(), table_name))),
+            false => Ok(())
+        }
+    }
+}
+
+/// Reads a table and infers its schema from its first `sample` rows, or all of them. The source
+/// stops reading after the batch that completes the sample.
+pub async fn infer_schema<T: Source>(source: &T, schema: &str, table: &T::TableType, sample: Option<usize>, metadata: &mut Metadata) -> Result<SchemaInference, MigrationError<T::ErrorType>> {
+    let inference = SchemaInference::new(sample);
+    match source.migrate_table_to_cratedb(schema, table, vec![], &inference, metadata).await {
+        Err(MigrationError::Sink(_)) if inference.is_full() => {}
+        result => result?,
+    }
+    inference.print_report(metadata);
+    Ok(inference)
+}
+
+#[cfg(test)]
+mod tests {
+    
+
+    use crate::experiment::data::{CValue, CValueType, DtypeStrategy};
+    use crate::experiment::infer::SchemaInference;
+    use crate::source::source::Sink;
+    use crate::testing::object;
+
+    #[test]
+    fn test_infer_schema() {
+        let inference = SchemaInference::new(Some(4));
+        let columns = vec!["id".to_string(), "score".to_string(), "tags".to_string(), "user".to_string()];
+        inference.observe(&columns, &[
+            vec![CValue::I32(1), CValue::I64(10), CValue::VecString(vec!["a".to_string()]), object(vec![("name", CValue::String("a".to_string())), ("age", CValue::I32(3))])],
+            vec![CValue::I32(2), CValue::Double64(1.5), CValue::VecString(vec![]), object(vec![("name", CValue::I32(5))])],
+            vec![CValue::I32(3), CValue::String("n/a".to_string()), CValue::None, CValue::String("unknown".to_string())],
+        ]);
+        inference.observe(&columns, &[
+            vec![CValue::I64(4), CValue::I32(7), CValue::VecString(vec!["b".to_string()]), object(vec![("name", CValue::String("b".to_string()))])],
+            // Past the sample.
+            vec![CValue::String("x".to_string()), CValue::None, CValue::None, CValue::None],
+        ]);
+        assert_eq!(inference.rows(), 4);
+
+        let schema = inference.schema();
+        let id = &schema.columns["id"];
+        assert_eq!(id.dtype, CValueType::I64);
+        assert_eq!(id.nullable, Some(false));
+        assert!(matches!(id.dtype_collision_strategy, DtypeStrategy::Cast));
+
+        let score = &schema.columns["score"];
+        assert_eq!(score.dtype, CValueType::Double64);
+        assert!(matches!(score.cast_fallback, Some(DtypeStrategy::NewCol)));
+        assert_eq!(inference.stats()["score"].min, Some(1.5));
+        assert_eq!(inference.stats()["score"].max, Some(10.0));
+
+        let tags = &schema.columns["tags"];
+        assert_eq!(tags.dtype, CValueType::VecString);
+        assert_eq!(tags.element_dtype, Some(CValueType::String));
+        assert_eq!(tags.nullable, Some(true));
+
+        let user = &schema.columns["user"];
+        assert_eq!(user.dtype, CValueType::Object);
+        assert!(matches!(user.dtype_collision_strategy, DtypeStrategy::NewCol));
+        let sub_schema = user.sub_schema.as_ref().unwrap();
+        assert_eq!(sub_schema["name"].dtype, CValueType::String);
+        assert_eq!(sub_schema["name"].nullable, Some(false));
+        assert_eq!(sub_schema["age"].nullable, Some(true));
+
+        let conflicts = inference.conflicts();
+        let paths: Vec<&str> = conflicts.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["score", "user", "user.name"]);
+        assert_eq!(conflicts[0].conflicts, vec![(CValueType::String, 1)]);
+        assert_eq!(conflicts[0].to_string(), "score: Double64, but 1 of 4 values are String (1), suggested Cast");
+    }
+
+    #[tokio::test]
+    async fn test_sample_stops_the_source() {
+        let inference = SchemaInference::new(Some(3));
+        let columns = vec!["id".to_string()];
+        let batch = || vec![vec![CValue::I32(1)], vec![CValue::I32(2)]];
+
+        assert!((&inference).send_batch("doc", "t", &columns, batch()).await.is_ok());
+        assert!(!inference.is_full());
+        assert!((&inference).send_batch("doc", "t", &columns, batch()).await.is_err());
+        assert!(inference.is_full());
+        assert_eq!(inference.rows(), 3);
+        assert!(!SchemaInference::new(None).is_full());
+    }
+}
@@ -1,3 +1,8 @@
 pub mod trans;
 pub mod data;
 pub mod schema;
+pub mod cast;
+pub mod infer;
+pub mod drift;
+pub mod column;
+pub mod predicate;
\ No newline at end of file
@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use crate::experiment::cast::parse_timestamp;
+use crate::experiment::data::CValue;
+
+/// Row predicate, pushed down to the source as a `WHERE` clause or a MongoDB query document when
+/// it supports it and checked against the `CValue` rows otherwise (see `FilteredSink`).
+///
+/// Columns can be dotted paths into objects, e.g. `tenant.id`.
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    Eq(String, CValue),
+    Ne(String, CValue),
+    Gt(String, CValue),
+    Gte(String, CValue),
+    Lt(String, CValue),
+    Lte(String, CValue),
+    In(String, Vec<CValue>),
+    IsNull(String),
+    IsNotNull(String),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>),
+    /// Raw SQL condition, only SQL sources can use it.
+    Sql(String),
+}
+
+/// Orders two values, numbers of any width are compared with each other.
+pub(crate) fn compare(a: &CValue, b: &CValue) -> Option<Ordering> {
+    let as_f64 = |v: &CValue| match v {
+        CValue::I16(v) => Some(*v as f64),
+        CValue::I32(v) => Some(*v as f64),
+        CValue::I64(v) => Some(*v as f64),
+        CValue::Double32(v) => Some(*v as f64),
+        CValue::Double64(v) => Some(*v),
+        _ => None
+    };
+
+    match (a, b) {
+        (CValue::I64(a), CValue::I64(b)) => Some(a.cmp(b)),
+        (CValue::String(a), CValue::String(b)) => Some(a.cmp(b)),
+        (CValue::Bool(a), CValue::Bool(b)) => Some(a.cmp(b)),
+        (CValue::Timestamp(a), CValue::Timestamp(b)) => Some(a.cmp(b)),
+        (CValue::Date(a), CValue::Date(b)) => Some(a.cmp(b)),
+        (CValue::Ip(a), CValue::Ip(b)) => Some(a.cmp(b)),
+        // Timestamps against values written as text, e.g. `Gte("created", "2024-01-01")`.
+        (CValue::Timestamp(a), CValue::String(b)) => Some(a.cmp(&parse_timestamp(b)?)),
+        (CValue::String(a), CValue::Timestamp(b)) => Some(parse_timestamp(a)?.cmp(b)),
+        _ => as_f64(a)?.partial_cmp(&as_f64(b)?)
+    }
+}
+
+pub(crate) fn lookup<'a>(columns: &[String], row: &'a [CValue], path: &str) -> Option<&'a CValue> {
+    let mut parts = path.split('.');
+    let first = parts.next()?;
+    let i = columns.iter().position(|c| c == first)?;
+    parts.try_fold(row.get(i)?, |value, key| match value {
+        CValue::Object(object) => object.get(key),
+        _ => None
+    })
+}
+
+fn is_null(value: Option<&CValue>) -> bool {
+    matches!(value, None | Some(CValue::None) | Some(CValue::Unknown))
+}
+
+impl Predicate {
+    /// Whether `matches` can evaluate the predicate, raw SQL can not.
+    pub fn is_evaluable(&self) -> bool {
+        match self {
+            Predicate::Sql(_) => false,
+            Predicate::And(predicates) | Predicate::Or(predicates) => predicates.iter().all(|p| p.is_evaluable()),
+            Predicate::Not(predicate) => predicate.is_evaluable(),
+            _ => true
+        }
+    }
+
+    /// Checks a row, comparisons with nulls or values of different types are false like in SQL.
+    /// Raw SQL always matches, it has to be pushed down.
+    pub fn matches(&self, columns: &[String], row: &[CValue]) -> bool {
+        self.matches_by(&|path| lookup(columns, row, path).map(Cow::Borrowed))
+    }
+
+    /// Like `matches`, with the values of a row returned by `get` given a column or dotted path;
+    /// `CDataFrame::filter` uses it to read only the columns the predicate needs.
+    pub fn matches_by<'a>(&self, get: &dyn Fn(&str) -> Option<Cow<'a, CValue>>) -> bool {
+        let ordering = |c: &str, v: &CValue| get(c).and_then(|value| compare(&value, v));
+        match self {
+            Predicate::Eq(c, CValue::None) | Predicate::IsNull(c) => is_null(get(c).as_deref()),
+            Predicate::Ne(c, CValue::None) | Predicate::IsNotNull(c) => !is_null(get(c).as_deref()),
+            Predicate::Eq(c, v) => ordering(c, v) == Some(Ordering::Equal),
+            Predicate::Ne(c, v) => matches!(ordering(c, v), Some(o) if o != Ordering::Equal),
+            Predicate::Gt(c, v) => ordering(c, v) == Some(Ordering::Greater),
+            Predicate::Gte(c, v) => matches!(ordering(c, v), Some(Ordering::Greater | Ordering::Equal)),
+            Predicate::Lt(c, v) => ordering(c, v) == Some(Ordering::Less),
+            Predicate::Lte(c, v) => matches!(ordering(c, v), Some(Ordering::Less | Ordering::Equal)),
+            Predicate::In(c, values) => get(c).is_some_and(|value| {
+                values.iter().any(|v| compare(&value, v) == Some(Ordering::Equal))
+            }),
+            Predicate::And(predicates) => predicates.iter().all(|p| p.matches_by(get)),
+            Predicate::Or(predicates) => predicates.iter().any(|p| p.matches_by(get)),
+            Predicate::Not(predicate) => !predicate.matches_by(get),
+            Predicate::Sql(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::experiment::data::CValue;
+    use crate::experiment::predicate::Predicate;
+
+    fn predicate() -> Predicate {
+        Predicate::And(vec![
+            Predicate::Eq("tenant.id".to_string(), CValue::String("o'neil".to_string())),
+            Predicate::Or(vec![
+                Predicate::Gte("created".to_string(), CValue::I64(100)),
+                Predicate::IsNull("created".to_string()),
+            ]),
+        ])
+    }
+
+    #[test]
+    fn test_matches() {
+        let columns = vec!["created".to_string(), "tenant".to_string()];
+        let tenant = CValue::Object(HashMap::from([("id".to_string(), CValue::String("o'neil".to_string()))]));
+
+        assert!(predicate().matches(&columns, &[CValue::Double64(150.0), tenant.clone()]));
+        assert!(predicate().matches(&columns, &[CValue::None, tenant.clone()]));
+        assert!(!predicate().matches(&columns, &[CValue::I32(50), tenant.clone()]));
+        assert!(!predicate().matches(&columns, &[CValue::I32(150), CValue::None]));
+        assert!(!Predicate::Ne("created".to_string(), CValue::I32(1)).matches(&columns, &[CValue::None, tenant.clone()]));
+
+        let since = Predicate::Gte("created".to_string(), CValue::String("2024-01-02".to_string()));
+        assert!(since.matches(&columns, &[CValue::Timestamp(1704164645000), tenant.clone()]));
+        assert!(!since.matches(&columns, &[CValue::Timestamp(0), tenant]));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fs;
 use serde::{Deserialize, Serialize};
 use crate::experiment::data::{CValueType, DtypeStrategy};
 
@@ -6,11 +7,36 @@ use crate::experiment::data::{CValueType, DtypeStrategy};
 pub struct ColumnInfo {
     pub dtype: CValueType,
     pub dtype_collision_strategy: DtypeStrategy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sub_schema: Option<HashMap<String, ColumnInfo>>,
+    /// Strategy for the values that can not be cast when the strategy is `Cast`, nulls them if
+    /// missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cast_fallback: Option<DtypeStrategy>,
+    /// Whether the column can be null or missing, informative.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nullable: Option<bool>,
+    /// Type of the elements of an array column, informative.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element_dtype: Option<CValueType>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CSchema {
     #[serde(flatten)]
     pub columns: HashMap<String, ColumnInfo>,
+}
+
+impl CSchema {
+    /// Reads a CSchema from a JSON file, e.g.
+    /// `{"id": {"dtype": "I32", "dtype_collision_strategy": "Cast"}}`.
+    pub fn from_file(path: &str) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_else(|_| panic!("Could not read the schema {}", path));
+        serde_json::from_str(&content).unwrap_or_else(|_| panic!("Invalid schema {}", path))
+    }
+
+    pub fn to_file(&self, path: &str) {
+        let content = serde_json::to_string_pretty(&self).unwrap();
+        fs::write(path, content).unwrap_or_else(|_| panic!("Could not write the schema {}", path));
+    }
 }
\ No newline at end of file
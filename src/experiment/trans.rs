@@ -1,24 +1,23 @@
-use std::collections::{HashMap};
-use std::str::FromStr;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use indexmap::IndexMap;
 use mongodb::bson::{doc, Bson, Document};
 use mongodb::Collection;
-use crate::experiment::data::{get_inner_cvalue_type_name, CColumn, CDataFrame, CValue, CValueType, DtypeStrategy};
-use crate::experiment::schema::CSchema;
+use serde_json::{Map, Number, Value};
+use crate::experiment::cast::cast;
+use crate::experiment::column::ColumnData;
+use crate::experiment::data::{get_inner_cvalue_type_name, CColumn, CDataFrame, CDataFrameBuilder, CValue, CValueType, DtypeStrategy};
+use crate::experiment::schema::{CSchema, ColumnInfo};
 use crate::metadata::Metadata;
 
 
-fn row_to_normalized_row(row: Document) -> Vec<CValue> {
-    // Transform a mongodb Document into a vector of CValue(s)
-    row.into_iter().map(|(_, v)| { bson_to_cvalue(v) }).collect()
-}
-
 pub fn bson_to_cvalue(row: Bson) -> CValue {
     match row {
         Bson::String(v) => CValue::String(v),
         Bson::Int32(v) => CValue::I32(v),
         Bson::Int64(v) => CValue::I64(v),
         Bson::Double(v) => CValue::Double64(v),
-        Bson::Decimal128(v) => CValue::Double64(v.to_string().parse().unwrap()),
+        // Kept exact, a f64 would round it.
+        Bson::Decimal128(v) => CValue::Decimal(v.to_string()),
         Bson::ObjectId(v) => CValue::String(v.to_string()),
         Bson::Boolean(v) => CValue::Bool(v),
         Bson::Null => CValue::None,
@@ -26,10 +25,10 @@ pub fn bson_to_cvalue(row: Bson) -> CValue {
             if v.is_empty() {
                 return CValue::VecString(vec![])
             }
-            let vals: Vec<CValue> = v.into_iter().map(|el| bson_to_cvalue(el)).collect();
+            let vals: Vec<CValue> = v.into_iter().map(bson_to_cvalue).collect();
             CValue::VecDyn(vals)
         }
-        Bson::DateTime(v) => CValue::I64(v.timestamp_millis()),
+        Bson::DateTime(v) => CValue::Timestamp(v.timestamp_millis()),
         Bson::Document(v) => {
             let mut map: HashMap<String, CValue> = HashMap::new();
             for (k, v) in v {
@@ -47,76 +46,352 @@ pub fn bson_to_cvalue(row: Bson) -> CValue {
 
 
 
-fn check_dataset(mut dataframe: CDataFrame, schema: CSchema) -> CDataFrame {
+pub fn json_to_cvalue(value: Value) -> CValue {
+    match value {
+        Value::Null => CValue::None,
+        Value::Bool(v) => CValue::Bool(v),
+        Value::String(v) => CValue::String(v),
+        Value::Number(v) => {
+            // Integers above i64::MAX are kept exact as decimals, a f64 would round them.
+            match (v.as_i64(), v.as_u64()) {
+                (Some(i), _) => CValue::I64(i),
+                (None, Some(u)) => CValue::Decimal(u.to_string()),
+                _ => CValue::Double64(v.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        Value::Array(v) => {
+            if v.is_empty() {
+                return CValue::VecString(vec![])
+            }
+            let vals: Vec<CValue> = v.into_iter().map(json_to_cvalue).collect();
+            CValue::VecDyn(vals)
+        }
+        Value::Object(v) => {
+            let mut map: HashMap<String, CValue> = HashMap::new();
+            for (k, v) in v {
+                map.insert(k, json_to_cvalue(v));
+            }
+            CValue::Object(map)
+        }
+    }
+}
+
+/// Turns a vector of CValue(s) into a typed vector if all of its values have the same scalar type,
+/// so it can be bound as an ARRAY; integers are widened to i64 and floats to f64. Otherwise VecDyn.
+pub fn collapse_vec(values: Vec<CValue>) -> CValue {
+    if values.is_empty() {
+        return CValue::VecString(vec![]);
+    }
+    if values.iter().all(|v| matches!(v, CValue::String(_))) {
+        return CValue::VecString(values.into_iter().filter_map(|v| match v { CValue::String(s) => Some(s), _ => None }).collect());
+    }
+    if values.iter().all(|v| matches!(v, CValue::I16(_) | CValue::I32(_) | CValue::I64(_))) {
+        return CValue::VecI64(values.into_iter().filter_map(|v| match v {
+            CValue::I16(i) => Some(i as i64),
+            CValue::I32(i) => Some(i as i64),
+            CValue::I64(i) => Some(i),
+            _ => None
+        }).collect());
+    }
+    if values.iter().all(|v| matches!(v, CValue::Double32(_) | CValue::Double64(_))) {
+        return CValue::VecF64(values.into_iter().filter_map(|v| match v {
+            CValue::Double32(f) => Some(f as f64),
+            CValue::Double64(f) => Some(f),
+            _ => None
+        }).collect());
+    }
+    CValue::VecDyn(values)
+}
+
+pub fn cvalue_to_json(value: &CValue) -> Value {
+    // Plain JSON representation of a CValue, unlike its serde implementation this one is not tagged,
+    // so it can be sent to databases that take JSON for nested types; e.g. CrateDB's OBJECT.
+    match value {
+        CValue::Bool(v) => Value::Bool(*v),
+        CValue::I16(v) => Value::from(*v),
+        CValue::I32(v) => Value::from(*v),
+        CValue::I64(v) => Value::from(*v),
+        CValue::Double32(v) => Number::from_f64(*v as f64).map_or(Value::Null, Value::Number),
+        CValue::Double64(v) => Number::from_f64(*v).map_or(Value::Null, Value::Number),
+        CValue::String(v) => Value::String(v.clone()),
+        CValue::VecString(v) => Value::from(v.clone()),
+        CValue::VecI32(v) => Value::from(v.clone()),
+        CValue::VecI64(v) => Value::from(v.clone()),
+        CValue::VecF32(v) => Value::from(v.clone()),
+        CValue::VecF64(v) => Value::from(v.clone()),
+        CValue::VecDyn(v) => Value::Array(v.iter().map(cvalue_to_json).collect()),
+        CValue::Object(v) => {
+            let mut map = Map::new();
+            for (k, v) in v {
+                map.insert(k.clone(), cvalue_to_json(v));
+            }
+            Value::Object(map)
+        }
+        CValue::Timestamp(v) => Value::from(*v),
+        CValue::Date(v) => Value::from(*v as i64 * 86_400_000),
+        CValue::Decimal(v) => Value::String(v.clone()),
+        CValue::Bytes(_) => Value::String(value.to_string()),
+        CValue::Ip(v) => Value::String(v.to_string()),
+        CValue::GeoPoint(lon, lat) => Value::from(vec![*lon, *lat]),
+        CValue::None | CValue::Unknown => Value::Null,
+    }
+}
+
+
+/// How many values of a column every DtypeStrategy affected, a value is the column of one row.
+/// The values of removed rows only count for the strategies that removed them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnReport {
+    pub cast: usize,
+    /// Values that could not be cast, they are also counted in their fallback strategy.
+    pub cast_failed: usize,
+    pub new_col: usize,
+    pub nulled: usize,
+    /// Values whose row, or element of an array of objects, was removed.
+    pub removed: usize,
+    /// Mismatching values passed through as they are.
+    pub ignored: usize,
+}
+
+/// What `check_dataset` did to one or more CDataFrames.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrategyReport {
+    pub rows: usize,
+    pub rows_removed: usize,
+    pub columns: BTreeMap<String, ColumnReport>,
+}
+
+impl ColumnReport {
+    pub fn merge(&mut self, other: &ColumnReport) {
+        self.cast += other.cast;
+        self.cast_failed += other.cast_failed;
+        self.new_col += other.new_col;
+        self.nulled += other.nulled;
+        self.removed += other.removed;
+        self.ignored += other.ignored;
+    }
+}
+
+impl StrategyReport {
+    pub fn merge(&mut self, other: StrategyReport) {
+        self.rows += other.rows;
+        self.rows_removed += other.rows_removed;
+        for (column, report) in other.columns {
+            self.columns.entry(column).or_default().merge(&report);
+        }
+    }
+
+    pub fn print(&self, metadata: &Metadata) {
+        metadata.print_line(&format!("{} rows checked, {} removed", self.rows, self.rows_removed));
+        metadata.print_line(&format!("{:<30} {:>8} {:>12} {:>8} {:>8} {:>8} {:>8}", "column", "cast", "cast_failed", "new_col", "nulled", "removed", "ignored"));
+        for (column, report) in &self.columns {
+            metadata.print_line(&format!("{:<30} {:>8} {:>12} {:>8} {:>8} {:>8} {:>8}",
+                                         column, report.cast, report.cast_failed, report.new_col, report.nulled, report.removed, report.ignored));
+        }
+    }
+}
+
+/// Enforces `schema` on a CDataFrame, values whose dtype does not match their column's expected
+/// dtype are resolved with the column's DtypeStrategy:
+///
+/// * `Cast` converts the value (see `cast`), values that can not be converted are resolved with the
+///   column's `cast_fallback`, they are nulled by default.
+/// * `NewCol` moves the value to a `<column>_<type>` column.
+/// * `Remove` drops the whole row, or only the element for values in an array of objects.
+/// * `Ignore` passes the value through as it is, it is only counted in the report.
+///
+/// Objects, and arrays of objects, are checked against their column's `sub_schema` the same way.
+pub fn check_dataset(dataframe: CDataFrame, schema: &CSchema) -> CDataFrame {
+    check_dataset_with_report(dataframe, schema).0
+}
+
+/// Like `check_dataset`, also returns how many rows and values every strategy affected.
+pub fn check_dataset_with_report(mut dataframe: CDataFrame, schema: &CSchema) -> (CDataFrame, StrategyReport) {
     let mut new_rows: Vec<(CValue, usize, String)> = vec![];
+    let mut removed_rows: HashSet<usize> = HashSet::new();
+    let mut report = StrategyReport { rows: dataframe.count, ..Default::default() };
+    // (row, column, report) of every value a strategy affected, counted once the removed rows are known.
+    let mut counts: Vec<(usize, String, ColumnReport)> = vec![];
 
     // We go through every defined column in the Schema and resolve its schema, if there is a value dtype mismatch
     // the DtypeStrategy solution will be applied to resolve it.
     for (column, row) in &mut dataframe.columns {
-        let expected_dtype: Option<CValueType> = get_expected_dtype(&schema, column);
-        let strategy: Option<DtypeStrategy> = get_strategy(&schema, column);
-
-        if let (Some(expected_dtype), Some(strategy)) = (expected_dtype, strategy) {
-            row.data_type = expected_dtype.clone();
-            row.expected_dtype = expected_dtype.clone();
-            for (i, value) in row.values.iter_mut().enumerate() {
-
-                // If the expected dtype is not equal to the value dtype
-                if !&value.equals_dtype(&CValue::None) && !value.is_dtype(expected_dtype) {
-                    match strategy {
-                        DtypeStrategy::NewCol => {
-                            new_rows.push((value.clone(), i, column.clone()));
-                            *value = CValue::None;
-                        }
-
-                        DtypeStrategy::Cast => {
-                            match (&expected_dtype, &value) {
-                                (CValueType::String, CValue::String(_)) => continue,
-                                (CValueType::String, CValue::VecString(v)) => *value = CValue::String(v.join(" ")),
-                                (CValueType::I32, CValue::String(v)) => *value = CValue::I32(v.parse().unwrap()),
-                                (CValueType::String, CValue::I32(v)) => *value = CValue::String(v.to_string()),
-                                _ => continue
-                            }
-                        }
-                        _ => continue
-                    }
+        let name = column.as_str();
+        let Some(column_info) = schema.columns.get(name) else { continue };
+
+        row.data_type = column_info.dtype;
+        row.expected_dtype = column_info.dtype;
+        row.dtype_strategy = column_info.dtype_collision_strategy.clone();
+
+        row.values.map(|i, mut value| {
+            let mut column_report = ColumnReport::default();
+            let remove = match resolve_value(&mut value, column_info, &mut column_report) {
+                Resolution::Keep => false,
+                Resolution::NewCol(moved) => {
+                    new_rows.push((moved, i, column.clone()));
+                    false
                 }
+                Resolution::Remove => true
+            };
+            if column_report != ColumnReport::default() {
+                counts.push((i, name.to_string(), column_report));
+            }
+
+            let mut nested_report = StrategyReport::default();
+            let remove = remove || check_nested(&mut value, column_info, name, &mut nested_report);
+            counts.extend(nested_report.columns
+                .into_iter()
+                .filter(|(_, column_report)| column_report != &ColumnReport::default())
+                .map(|(path, column_report)| (i, path, column_report)));
+
+            if remove {
+                // Nulled so it does not widen the column, its row is removed anyway.
+                removed_rows.insert(i);
+                return CValue::None;
             }
+            value
+        });
+    }
+
+    for (i, column, column_report) in counts {
+        if column_report.removed > 0 || !removed_rows.contains(&i) {
+            report.columns.entry(column).or_default().merge(&column_report);
         }
     }
 
-    let mut last_padding: usize = 0;
+    // Values moved to a new column of a removed row are dropped with it.
+    new_rows.retain(|(_, i, _)| !removed_rows.contains(i));
 
-    // Add new rows for strategy new_column.
+    // Add new rows for strategy new_column, the values of a new column are gathered as CValues and
+    // stored once.
+    let mut new_columns: IndexMap<String, Vec<CValue>> = IndexMap::new();
     for (value, i, column) in new_rows {
-        let mut col_values: Vec<CValue> = Vec::with_capacity(i + 1);
-        let new_column_name = format!("{}_{}", column, get_inner_cvalue_type_name(&value)).to_string();
+        let new_column_name = format!("{}_{}", column, get_inner_cvalue_type_name(&value));
 
-        if !dataframe.has_column(&new_column_name) {
-            let expected_dtype = get_expected_dtype(&schema, &new_column_name).or(Some(CValueType::Unknown));
-            let dtype = value.get_dtype();
-            last_padding = i;
-            col_values.extend(vec![CValue::None; i]);
-            col_values.push(value);
-            dataframe.add_column(
-                new_column_name,
-                CColumn {
-                    values: col_values,
-                    expected_dtype: expected_dtype.unwrap(),
-                    data_type: dtype,
-                    dtype_strategy: DtypeStrategy::Ignore
-                });
-        } else {
-            col_values.extend(vec![CValue::None; i - last_padding]);
-            col_values.push(value);
+        if !new_columns.contains_key(&new_column_name) {
+            let values = match dataframe.get_mut(&new_column_name) {
+                Some(existing) => std::mem::take(&mut existing.values).into_values(),
+                None => {
+                    let expected_dtype = get_expected_dtype(schema, &new_column_name).unwrap_or(CValueType::Unknown);
+                    dataframe.add_column(
+                        new_column_name.clone(),
+                        CColumn {
+                            values: ColumnData::new(),
+                            expected_dtype,
+                            data_type: value.get_dtype(),
+                            dtype_strategy: DtypeStrategy::Ignore
+                        });
+                    vec![CValue::None; dataframe.count]
+                }
+            };
+            new_columns.insert(new_column_name.clone(), values);
+        }
+        new_columns.get_mut(&new_column_name).unwrap()[i] = value;
+    }
+    for (name, values) in new_columns {
+        dataframe.get_mut(&name).unwrap().values = ColumnData::from_values(values);
+    }
+
+    if !removed_rows.is_empty() {
+        dataframe.remove_rows(&removed_rows);
+        report.rows_removed = removed_rows.len();
+    }
+    (dataframe, report)
+}
 
-            dataframe.add_values_to_column(new_column_name, col_values);
+/// What to do with a value after `resolve_value`.
+enum Resolution {
+    Keep,
+    /// The value was nulled and has to be moved to a `<column>_<type>` column.
+    NewCol(CValue),
+    /// The row has to be removed.
+    Remove,
+}
+
+/// Applies the column's DtypeStrategy to a value that does not match its expected dtype.
+fn resolve_value(value: &mut CValue, column_info: &ColumnInfo, column_report: &mut ColumnReport) -> Resolution {
+    let expected_dtype = column_info.dtype;
+    if value.equals_dtype(&CValue::None) || value.is_dtype(expected_dtype) {
+        return Resolution::Keep;
+    }
+    // Columns widen their numeric buffers (see `ColumnData`), so a wider number that the expected
+    // type holds exactly, e.g. I64(5) for I32, is cast whatever the strategy.
+    if expected_dtype.fits_in(value.get_dtype()) {
+        if let Ok(narrowed) = cast(value, expected_dtype) {
+            *value = narrowed;
+            column_report.cast += 1;
+            return Resolution::Keep;
         }
     }
 
+    let mut strategy = &column_info.dtype_collision_strategy;
+    if let DtypeStrategy::Cast = strategy {
+        match cast(value, expected_dtype) {
+            Ok(cast_value) => {
+                *value = cast_value;
+                column_report.cast += 1;
+                return Resolution::Keep;
+            }
+            Err(_) => {
+                column_report.cast_failed += 1;
+                strategy = column_info.cast_fallback.as_ref().unwrap_or(strategy)
+            }
+        }
+    }
 
+    match strategy {
+        DtypeStrategy::NewCol => {
+            column_report.new_col += 1;
+            Resolution::NewCol(std::mem::replace(value, CValue::None))
+        }
+        DtypeStrategy::Remove => {
+            column_report.removed += 1;
+            Resolution::Remove
+        }
+        // Values that can not be cast, without a fallback or with `Cast` as fallback.
+        DtypeStrategy::Cast => {
+            *value = CValue::None;
+            column_report.nulled += 1;
+            Resolution::Keep
+        }
+        DtypeStrategy::Ignore => {
+            column_report.ignored += 1;
+            Resolution::Keep
+        }
+    }
+}
 
-    dataframe
+/// Enforces the `sub_schema` of a column on an object, or on every object of an array, reported
+/// by dotted path, e.g. `name.sub_id`. Values moved by `NewCol` go to a `<key>_<type>` key of
+/// their object. Elements of an array that have to be removed are removed from the array, returns
+/// whether the value itself has to be removed.
+fn check_nested(value: &mut CValue, column_info: &ColumnInfo, path: &str, report: &mut StrategyReport) -> bool {
+    let Some(sub_schema) = &column_info.sub_schema else { return false };
+
+    match value {
+        CValue::Object(object) => {
+            let mut remove = false;
+            let mut moved: Vec<(String, CValue)> = vec![];
+
+            for (key, key_info) in sub_schema {
+                let Some(nested) = object.get_mut(key) else { continue };
+                let nested_path = format!("{}.{}", path, key);
+                match resolve_value(nested, key_info, report.columns.entry(nested_path.clone()).or_default()) {
+                    Resolution::Keep => {}
+                    Resolution::NewCol(value) => moved.push((format!("{}_{}", key, get_inner_cvalue_type_name(&value)), value)),
+                    Resolution::Remove => remove = true
+                }
+                remove |= check_nested(nested, key_info, &nested_path, report);
+            }
+            object.extend(moved);
+            remove
+        }
+        CValue::VecDyn(values) => {
+            values.retain_mut(|value| !check_nested(value, column_info, path, report));
+            false
+        }
+        _ => false
+    }
 }
 
 fn get_expected_dtype(schema: &CSchema, column_name: &str) -> Option<CValueType> {
@@ -126,11 +401,8 @@ fn get_expected_dtype(schema: &CSchema, column_name: &str) -> Option<CValueType>
         .and_then(|column_info| Option::from(column_info.dtype))
 }
 
-fn get_strategy(schema: &CSchema, column_name: &str) -> Option<DtypeStrategy> {
-    schema.columns.get(column_name).and_then(|column_info| Option::from(column_info.dtype_collision_strategy.clone()))
-}
-
-pub async fn iter_cols(table: &Collection<Document>, metadata: &mut Metadata) {
+/// Reads a collection into a CDataFrame chunk by chunk, only the `k` column of every chunk is kept.
+pub async fn iter_cols(table: &Collection<Document>, metadata: &mut Metadata) -> CDataFrame {
     let data = r#"
         {
             "name": {"dtype": "String", "dtype_collision_strategy": "NewCol", "sub_schema": { "sub_id": {"dtype": "I32", "dtype_collision_strategy": "NewCol"} }},
@@ -141,48 +413,129 @@ pub async fn iter_cols(table: &Collection<Document>, metadata: &mut Metadata) {
 
     let schema: CSchema = serde_json::from_str(data).unwrap();
     let batch_size: usize = 3000;
-    let mut buffer = vec![];
     let mut cursor = table.find(doc! {}).batch_size(batch_size as u32).await.expect("Could not create a cursor in MongoDB, is the server up?");
 
+    // Documents are turned into a CDataFrame chunk by chunk and only the `k` column of each chunk is
+    // kept, so the collection does not have to fit in memory.
+    let mut builder = CDataFrameBuilder::new(schema, batch_size);
+    let mut chunks: usize = 0;
+    let mut dataframe = CDataFrame::new();
 
     while cursor.advance().await.expect("Could not advance cursor; maybe Mongo connection was lost") {
         let document = cursor.deserialize_current().unwrap();
-        buffer.push(document);
-    }
-
-    let mut dataframe = CDataFrame::from_bson(buffer, schema);
-    dataframe = dataframe.select(vec!["k".to_string()]);
-    dataframe.print_schema();
-    // dataframe = dataframe.select(vec![
-    //     "id".to_string(),
-    //     "_id".to_string(),
-    //     "k".to_string()
-    // ]);
-    dataframe.print();
-
-    // print_dataset(&dataframe,
-    //               Some(vec![
-    //                   "k".to_string(),
-    //                   "id".to_string(),
-    //                   "name_i32".to_string(),
-    //                   "id".to_string(),
-    //                   "name".to_string(),
-    //                   "sub_id".to_string(),
-    //                   "surname".to_string()
-    //               ]));
-    // let new_dataset = check_dataset(dataframe, expected_schema);
-
-    // print_dataset(&new_dataset, Some(vec![
-    //     "k".to_string(),
-    //     "_id".to_string(),
-    //     "another".to_string(),
-    //     "id".to_string(),
-    //     "name_i32".to_string(),
-    //     "id".to_string(),
-    //     "name".to_string(),
-    //     "sub_id".to_string(),
-    //     "surname".to_string(),
-    //     "sub_id_str".to_string(),
-    //     "name_vecdyn".to_string(),
-    // ]));
-}
\ No newline at end of file
+        if let Some(mut chunk) = builder.push(document) {
+            chunks += 1;
+            metadata.print_step(format!("Built chunk {} of {} rows, {}Kb of values", chunks, chunk.count, chunk.heap_size() / 1000).as_str());
+            chunk.columns.retain(|name, _| name == "k");
+            dataframe.append(chunk);
+        }
+    }
+    if let Some(mut chunk) = builder.finish() {
+        chunk.columns.retain(|name, _| name == "k");
+        dataframe.append(chunk);
+    }
+    dataframe
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{Bson, Decimal128};
+
+    use crate::experiment::data::{CDataFrame, CValue};
+    use crate::experiment::schema::CSchema;
+    use crate::experiment::trans::{bson_to_cvalue, check_dataset_with_report, ColumnReport};
+    use crate::testing::object;
+
+    #[test]
+    fn test_cast_fallback() {
+        let schema: CSchema = serde_json::from_str(r#"{
+            "score": {"dtype": "I16", "dtype_collision_strategy": "Cast", "cast_fallback": "Remove"},
+            "ratio": {"dtype": "Double32", "dtype_collision_strategy": "Cast", "cast_fallback": "NewCol"},
+            "id": {"dtype": "Double64", "dtype_collision_strategy": "Cast"}
+        }"#).unwrap();
+        let columns = vec!["score".to_string(), "ratio".to_string(), "id".to_string()];
+        let rows = vec![
+            vec![CValue::I64(5), CValue::Double64(0.5), CValue::I64(1)],
+            vec![CValue::I64(100_000), CValue::Double64(0.5), CValue::I64(2)],
+            vec![CValue::String("7".to_string()), CValue::Double64(0.1), CValue::I64(i64::MAX)],
+        ];
+
+        let (dataframe, report) = check_dataset_with_report(CDataFrame::from_rows(&columns, rows), &schema);
+        let (columns, rows) = dataframe.into_rows();
+        assert_eq!(columns, vec!["score", "ratio", "id", "ratio_f64"]);
+        assert_eq!(rows.len(), 2);
+
+        assert!(matches!(rows[0][0], CValue::I16(5)));
+        assert!(matches!(rows[0][1], CValue::Double32(v) if v == 0.5));
+        assert!(matches!(rows[0][2], CValue::Double64(v) if v == 1.0));
+
+        // 0.1 is not exactly a f32 and moves to ratio_f64, i64::MAX is not exactly a f64 and is nulled.
+        assert!(matches!(rows[1][0], CValue::I16(7)));
+        assert!(matches!(rows[1][1], CValue::None));
+        assert!(matches!(rows[1][2], CValue::None));
+        assert!(matches!(rows[1][3], CValue::Double64(v) if v == 0.1));
+
+        assert_eq!(report.columns["score"], ColumnReport { cast: 2, cast_failed: 1, removed: 1, ..Default::default() });
+        // The second row is removed, its ratio and id do not count.
+        assert_eq!(report.columns["ratio"], ColumnReport { cast: 1, cast_failed: 1, new_col: 1, ..Default::default() });
+        assert_eq!(report.columns["id"], ColumnReport { cast: 1, cast_failed: 1, nulled: 1, ..Default::default() });
+    }
+
+    #[test]
+    fn test_nested_schema() {
+        let schema: CSchema = serde_json::from_str(r#"{
+            "user": {"dtype": "Object", "dtype_collision_strategy": "Ignore", "sub_schema": {
+                "id": {"dtype": "I64", "dtype_collision_strategy": "Cast", "cast_fallback": "Remove"},
+                "name": {"dtype": "String", "dtype_collision_strategy": "NewCol"},
+                "address": {"dtype": "Object", "dtype_collision_strategy": "Cast", "sub_schema": {
+                    "zip": {"dtype": "String", "dtype_collision_strategy": "Cast"}
+                }}
+            }},
+            "items": {"dtype": "VecDyn", "dtype_collision_strategy": "Cast", "sub_schema": {
+                "price": {"dtype": "Double64", "dtype_collision_strategy": "Remove"}
+            }}
+        }"#).unwrap();
+
+        let columns = vec!["user".to_string(), "items".to_string()];
+        let rows = vec![
+            vec![
+                object(vec![("id", CValue::String("1".to_string())), ("name", CValue::I32(5)), ("address", object(vec![("zip", CValue::I32(1010))]))]),
+                CValue::VecDyn(vec![object(vec![("price", CValue::Double64(1.5))])]),
+            ],
+            vec![
+                object(vec![("id", CValue::I64(2)), ("address", CValue::String("unknown".to_string()))]),
+                CValue::VecDyn(vec![object(vec![("price", CValue::Double64(1.0))]), object(vec![("price", CValue::String("free".to_string()))])]),
+            ],
+            vec![object(vec![("id", CValue::String("x".to_string()))]), CValue::None],
+        ];
+
+        let (dataframe, report) = check_dataset_with_report(CDataFrame::from_rows(&columns, rows), &schema);
+        let (_, rows) = dataframe.into_rows();
+        assert_eq!(rows.len(), 2);
+
+        let CValue::Object(user) = &rows[0][0] else { panic!("user is not an object") };
+        assert!(matches!(user.get("id"), Some(CValue::I64(1))));
+        assert!(matches!(user.get("name"), Some(CValue::None)));
+        assert!(matches!(user.get("name_i32"), Some(CValue::I32(5))));
+        assert!(matches!(user.get("address"), Some(CValue::Object(a)) if matches!(a.get("zip"), Some(CValue::String(z)) if z == "1010")));
+
+        // Only the element with a mismatching price is removed from the array, not its row.
+        let CValue::Object(user) = &rows[1][0] else { panic!("user is not an object") };
+        assert!(matches!(user.get("address"), Some(CValue::None)));
+        assert!(matches!(&rows[1][1], CValue::VecDyn(items) if items.len() == 1));
+
+        assert_eq!(report.rows_removed, 1);
+        assert_eq!(report.columns["user.id"], ColumnReport { cast: 1, cast_failed: 1, removed: 1, ..Default::default() });
+        assert_eq!(report.columns["user.name"], ColumnReport { new_col: 1, ..Default::default() });
+        assert_eq!(report.columns["user.address"], ColumnReport { cast_failed: 1, nulled: 1, ..Default::default() });
+        assert_eq!(report.columns["user.address.zip"], ColumnReport { cast: 1, ..Default::default() });
+        assert_eq!(report.columns["items.price"], ColumnReport { removed: 1, ..Default::default() });
+        assert!(!report.columns.contains_key("user"));
+    }
+
+    #[test]
+    fn test_bson_decimal() {
+        let decimal: Decimal128 = "12345678901234567890.123".parse().unwrap();
+        assert_eq!(bson_to_cvalue(Bson::Decimal128(decimal)), CValue::Decimal("12345678901234567890.123".to_string()));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod utils;
+pub mod source;
+pub mod sink;
+pub mod metadata;
+pub mod experiment;
+#[cfg(test)]
+mod testing;
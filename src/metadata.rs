@@ -1,16 +1,22 @@
 use std::time::{Duration, Instant};
 use memory_stats::memory_stats;
 
-pub(crate) struct Metadata {
+pub struct Metadata {
     _start: Option<Instant>,
     _last: Option<Duration>,
 }
 
+impl Default for Metadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Metadata {
-    pub(crate) fn start(&mut self) {
+    pub fn start(&mut self) {
         self._start = Some(Instant::now());
     }
-    pub(crate) fn new() -> Self {
+    pub fn new() -> Self {
         Metadata {
             _start: None,
             _last: Some(Duration::new(0, 0)),
@@ -28,6 +34,12 @@ impl Metadata {
         self._last = Some(self._start.unwrap().elapsed());
     }
 
+    /// Prints a line of a report, e.g. a schema change or a drift, without the duration and memory
+    /// use of `print_step`.
+    pub fn print_line(&self, line: &str) {
+        println!("{}", line);
+    }
+
     pub fn print_total_duration(&self) {
         match self._start {
             None => panic!("Cannot print total duration without calling .start() first"),
@@ -35,6 +47,13 @@ impl Metadata {
         }
     }
     pub fn elapsed(&self) -> Duration {
-        return self._start.unwrap().elapsed();
+        self._start.unwrap().elapsed()
+    }
+
+    /// Prints the rows sent per second since `.start()`, migrations faster than a millisecond
+    /// count as one.
+    pub fn print_rows_per_second(&mut self, rows: usize) {
+        let rate = rows as u128 * 1000 / self.elapsed().as_millis().max(1);
+        self.print_step(format!("Rows per seconds: {}", rate).as_str());
     }
 }
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{create_dir_all, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use std::borrow::Borrow;
+
+use arrow::array::{new_null_array, ArrayRef, BinaryBuilder, BooleanArray, BooleanBuilder, Date32Builder, Float32Array, Float32Builder, Float64Array, Float64Builder, Int16Array, Int16Builder, Int32Array, Int32Builder, Int64Array, Int64Builder, ListBuilder, StringArray, StringBuilder, TimestampMillisecondBuilder};
+use arrow::buffer::{BooleanBuffer, NullBuffer};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+
+use crate::experiment::column::{ColumnData, Validity};
+use crate::experiment::data::{CDataFrame, CValue, CValueType};
+use crate::experiment::trans::cvalue_to_json;
+use crate::source::source::{Sink, SinkError};
+
+/// Canonical Arrow extension type for JSON encoded strings, used for `Object` and `VecDyn` values.
+pub const JSON_EXTENSION: &str = "arrow.json";
+
+/// Maps a CValueType to an Arrow data type, nested values are stored as JSON strings and decimals,
+/// IPs and geo points as their text. Columns with only nulls are of the Null type.
+pub fn cvalue_type_to_arrow(dtype: &CValueType) -> DataType {
+    let list = |item: DataType| DataType::List(Arc::new(Field::new("item", item, true)));
+    match dtype {
+        CValueType::Bool => DataType::Boolean,
+        CValueType::I16 => DataType::Int16,
+        CValueType::I32 => DataType::Int32,
+        CValueType::I64 => DataType::Int64,
+        CValueType::Double32 => DataType::Float32,
+        CValueType::Double64 => DataType::Float64,
+        CValueType::VecString => list(DataType::Utf8),
+        CValueType::VecI32 => list(DataType::Int32),
+        CValueType::VecI64 => list(DataType::Int64),
+        CValueType::VecF32 => list(DataType::Float32),
+        CValueType::VecF64 => list(DataType::Float64),
+        CValueType::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        CValueType::Date => DataType::Date32,
+        CValueType::Bytes => DataType::Binary,
+        CValueType::Decimal | CValueType::Ip | CValueType::GeoPoint => DataType::Utf8,
+        CValueType::None => DataType::Null,
+        CValueType::String | CValueType::VecDyn | CValueType::Object | CValueType::Unknown => DataType::Utf8,
+    }
+}
+
+/// Returns the type of a column, the widest of the types of its non-null values (see `CValueType::widen`). Values
+/// that can not be widened to it are left for `build_array` to report.
+fn column_dtype(dtypes: impl Iterator<Item=CValueType>) -> CValueType {
+    dtypes
+        .filter(|dtype| !matches!(dtype, CValueType::None | CValueType::Unknown))
+        .reduce(|a, b| a.widen(b).unwrap_or(a))
+        .unwrap_or(CValueType::None)
+}
+
+fn arrow_field(name: &str, dtype: &CValueType) -> Field {
+    let field = Field::new(name, cvalue_type_to_arrow(dtype), true);
+    match dtype {
+        CValueType::Object | CValueType::VecDyn => field.with_metadata(HashMap::from([
+            ("ARROW:extension:name".to_string(), JSON_EXTENSION.to_string()),
+        ])),
+        _ => field
+    }
+}
+
+/// Builds the Arrow schema of rows, every column has the widest type of its values.
+pub fn infer_schema(columns: &[String], rows: &[Vec<CValue>]) -> Schema {
+    let fields: Vec<Field> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| arrow_field(name, &column_dtype(rows.iter().filter_map(|row| row.get(i)).map(CValue::get_dtype))))
+        .collect();
+    Schema::new(fields)
+}
+
+macro_rules! build_list {
+    ($values:expr, $builder:expr, $($variant:path => $cast:ty),+) => {
+        {
+            let mut builder = ListBuilder::new($builder);
+            for value in $values {
+                match value {
+                    $($variant(v) => {
+                        for x in v {
+                            builder.values().append_value(*x as $cast);
+                        }
+                        builder.append(true);
+                    })+
+                    _ => builder.append_null()
+                }
+            }
+            Arc::new(builder.finish()) as ArrayRef
+        }
+    };
+}
+
+/// Whether `value` can be written to a column of `field`'s type without losing precision.
+pub fn fits_field(field: &Field, value: &CValue) -> bool {
+    let dtype = value.get_dtype();
+    let is_json = field.metadata().get("ARROW:extension:name").is_some_and(|n| n == JSON_EXTENSION);
+    match field.data_type() {
+        _ if matches!(dtype, CValueType::None | CValueType::Unknown) => true,
+        DataType::Null => false,
+        DataType::Boolean => dtype == CValueType::Bool,
+        DataType::Int16 => dtype.fits_in(CValueType::I16),
+        DataType::Int32 => dtype.fits_in(CValueType::I32),
+        DataType::Int64 => dtype.fits_in(CValueType::I64),
+        DataType::Float32 => dtype.fits_in(CValueType::Double32),
+        DataType::Float64 => dtype.fits_in(CValueType::Double64),
+        DataType::Timestamp(_, _) => dtype == CValueType::Timestamp,
+        DataType::Date32 => dtype == CValueType::Date,
+        DataType::Binary => dtype == CValueType::Bytes,
+        DataType::List(item) => match item.data_type() {
+            DataType::Int32 => dtype.fits_in(CValueType::VecI32),
+            DataType::Int64 => dtype.fits_in(CValueType::VecI64),
+            DataType::Float32 => dtype.fits_in(CValueType::VecF32),
+            DataType::Float64 => dtype.fits_in(CValueType::VecF64),
+            _ => dtype == CValueType::VecString
+        },
+        _ if is_json => matches!(dtype, CValueType::Object | CValueType::VecDyn),
+        _ => matches!(dtype, CValueType::String | CValueType::Decimal | CValueType::Ip | CValueType::GeoPoint)
+    }
+}
+
+/// Builds an Arrow array of `field`'s type, integers and floats are widened to it (see
+/// `CValueType::fits_in`) and other values are an error.
+fn build_array<V: Borrow<CValue>>(field: &Field, values: impl Iterator<Item=V>) -> Result<ArrayRef, ArrowError> {
+    let values: Vec<V> = values.collect();
+    if let Some(value) = values.iter().map(|value| value.borrow()).find(|value| !fits_field(field, value)) {
+        return Err(ArrowError::InvalidArgumentError(format!("Column {} of type {} can not hold the {} value {}", field.name(), field.data_type(), value.get_dtype(), value)));
+    }
+    let values = values.iter().map(|value| value.borrow());
+    let is_json = field.metadata().get("ARROW:extension:name").is_some_and(|n| n == JSON_EXTENSION);
+
+    let array: ArrayRef = match field.data_type() {
+        DataType::Null => new_null_array(&DataType::Null, values.count()),
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            values.for_each(|v| match v { CValue::Bool(b) => builder.append_value(*b), _ => builder.append_null() });
+            Arc::new(builder.finish())
+        }
+        DataType::Int16 => {
+            let mut builder = Int16Builder::new();
+            values.for_each(|v| match v { CValue::I16(i) => builder.append_value(*i), _ => builder.append_null() });
+            Arc::new(builder.finish())
+        }
+        DataType::Int32 => {
+            let mut builder = Int32Builder::new();
+            values.for_each(|v| match v {
+                CValue::I16(i) => builder.append_value(*i as i32),
+                CValue::I32(i) => builder.append_value(*i),
+                _ => builder.append_null()
+            });
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            values.for_each(|v| match v {
+                CValue::I16(i) => builder.append_value(*i as i64),
+                CValue::I32(i) => builder.append_value(*i as i64),
+                CValue::I64(i) => builder.append_value(*i),
+                _ => builder.append_null()
+            });
+            Arc::new(builder.finish())
+        }
+        DataType::Float32 => {
+            let mut builder = Float32Builder::new();
+            values.for_each(|v| match v {
+                CValue::I16(i) => builder.append_value(*i as f32),
+                CValue::Double32(f) => builder.append_value(*f),
+                _ => builder.append_null()
+            });
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            values.for_each(|v| match v {
+                CValue::I16(i) => builder.append_value(*i as f64),
+                CValue::I32(i) => builder.append_value(*i as f64),
+                CValue::Double32(f) => builder.append_value(*f as f64),
+                CValue::Double64(f) => builder.append_value(*f),
+                _ => builder.append_null()
+            });
+            Arc::new(builder.finish())
+        }
+        DataType::Timestamp(_, timezone) => {
+            let mut builder = TimestampMillisecondBuilder::new().with_timezone_opt(timezone.clone());
+            values.for_each(|v| match v { CValue::Timestamp(ms) => builder.append_value(*ms), _ => builder.append_null() });
+            Arc::new(builder.finish())
+        }
+        DataType::Date32 => {
+            let mut builder = Date32Builder::new();
+            values.for_each(|v| match v { CValue::Date(days) => builder.append_value(*days), _ => builder.append_null() });
+            Arc::new(builder.finish())
+        }
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            values.for_each(|v| match v { CValue::Bytes(b) => builder.append_value(b), _ => builder.append_null() });
+            Arc::new(builder.finish())
+        }
+        DataType::List(item) => {
+            match item.data_type() {
+                DataType::Int32 => build_list!(values, Int32Builder::new(), CValue::VecI32 => i32),
+                DataType::Int64 => build_list!(values, Int64Builder::new(), CValue::VecI32 => i64, CValue::VecI64 => i64),
+                DataType::Float32 => build_list!(values, Float32Builder::new(), CValue::VecF32 => f32),
+                DataType::Float64 => build_list!(values, Float64Builder::new(), CValue::VecI32 => f64, CValue::VecF32 => f64, CValue::VecF64 => f64),
+                _ => {
+                    let mut builder = ListBuilder::new(StringBuilder::new());
+                    for value in values {
+                        match value {
+                            CValue::VecString(v) => {
+                                v.iter().for_each(|x| builder.values().append_value(x));
+                                builder.append(true);
+                            }
+                            _ => builder.append_null()
+                        }
+                    }
+                    Arc::new(builder.finish())
+                }
+            }
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            values.for_each(|v| match v {
+                CValue::String(s) if !is_json => builder.append_value(s),
+                CValue::Decimal(_) | CValue::Ip(_) | CValue::GeoPoint(_, _) if !is_json => builder.append_value(v.to_string()),
+                CValue::Object(_) | CValue::VecDyn(_) if is_json => builder.append_value(cvalue_to_json(v).to_string()),
+                _ => builder.append_null()
+            });
+            Arc::new(builder.finish())
+        }
+    };
+    Ok(array)
+}
+
+/// Builds a RecordBatch from rows following `schema`, columns missing in `columns` are nulls.
+pub fn rows_to_record_batch(schema: SchemaRef, columns: &[String], rows: &[Vec<CValue>]) -> Result<RecordBatch, ArrowError> {
+    let arrays = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            match columns.iter().position(|c| c == field.name()) {
+                Some(i) => build_array(field, rows.iter().map(|row| row.get(i).unwrap_or(&CValue::None))),
+                None => build_array(field, std::iter::repeat_n(&CValue::None, rows.len())),
+            }
+        })
+        .collect::<Result<Vec<ArrayRef>, ArrowError>>()?;
+    RecordBatch::try_new(schema, arrays)
+}
+
+/// Builds the array of a dataframe column, buffers of the field's type are copied as they are and
+/// other columns are built value by value.
+fn column_to_array(field: &Field, values: &ColumnData) -> Result<ArrayRef, ArrowError> {
+    let is_json = field.metadata().get("ARROW:extension:name").is_some_and(|n| n == JSON_EXTENSION);
+    let nulls = |valid: &Validity| Some(NullBuffer::new(BooleanBuffer::collect_bool(values.len(), |i| valid.get(i))));
+    let array: ArrayRef = match (values, field.data_type()) {
+        (ColumnData::Null(count), _) => new_null_array(field.data_type(), *count),
+        (ColumnData::Bool(v, valid), DataType::Boolean) => Arc::new(BooleanArray::new(BooleanBuffer::collect_bool(v.len(), |i| v[i]), nulls(valid))),
+        (ColumnData::I16(v, valid), DataType::Int16) => Arc::new(Int16Array::new(v.clone().into(), nulls(valid))),
+        (ColumnData::I32(v, valid), DataType::Int32) => Arc::new(Int32Array::new(v.clone().into(), nulls(valid))),
+        (ColumnData::I64(v, valid), DataType::Int64) => Arc::new(Int64Array::new(v.clone().into(), nulls(valid))),
+        (ColumnData::F32(v, valid), DataType::Float32) => Arc::new(Float32Array::new(v.clone().into(), nulls(valid))),
+        (ColumnData::F64(v, valid), DataType::Float64) => Arc::new(Float64Array::new(v.clone().into(), nulls(valid))),
+        (ColumnData::String(v, valid), DataType::Utf8) if !is_json => Arc::new((0..v.len()).map(|i| valid.get(i).then(|| v.get(i))).collect::<StringArray>()),
+        (ColumnData::Mixed(v), _) => return build_array(field, v.iter()),
+        _ => return build_array(field, values.iter()),
+    };
+    Ok(array)
+}
+
+/// Builds a RecordBatch from a CDataFrame, the expected dtype of a column is used if it is known.
+pub fn dataframe_to_record_batch(dataframe: &CDataFrame) -> Result<RecordBatch, ArrowError> {
+    let fields: Vec<Field> = dataframe.columns
+        .iter()
+        .map(|(name, column)| {
+            let dtype = match (column.expected_dtype, &column.values) {
+                (CValueType::Unknown | CValueType::None, ColumnData::Mixed(values)) => column_dtype(values.iter().map(CValue::get_dtype)),
+                (CValueType::Unknown | CValueType::None, values) => values.dtype(),
+                (dtype, _) => dtype
+            };
+            arrow_field(name, &dtype)
+        })
+        .collect();
+
+    let arrays: Vec<ArrayRef> = fields
+        .iter()
+        .map(|field| column_to_array(field, &dataframe.columns[field.name()].values))
+        .collect::<Result<Vec<ArrayRef>, ArrowError>>()?;
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+}
+
+/// Sink that writes every table to an Arrow IPC file (Feather v2) `<directory>/<schema>.<table>.arrow`.
+///
+/// The schema of a file is decided by its first batch, later batches are written with it and values
+/// that do not fit its types are an error. A batch with new columns, or with values for a column
+/// that only had nulls, rewrites the file with the wider schema; the file is read back whole for
+/// that. The footers are written by `finish` or when the sink is dropped.
+pub struct ArrowIPCSink {
+    pub(crate) directory: String,
+    writers: Mutex<HashMap<String, FileWriter<File>>>,
+}
+
+impl fmt::Debug for ArrowIPCSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ArrowIPCSink({})", self.directory)
+    }
+}
+
+impl ArrowIPCSink {
+    pub fn new(directory: &str) -> Self {
+        create_dir_all(directory).unwrap_or_else(|_| panic!("Could not create directory {}", directory));
+        Self {
+            directory: directory.to_string(),
+            writers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_path(&self, schema: &str, table_name: &str) -> PathBuf {
+        PathBuf::from(&self.directory).join(format!("{}.{}.arrow", schema, table_name))
+    }
+
+    fn write_batch(&self, schema: &str, table_name: &str, batch_schema: SchemaRef, columns: &[String], rows: &[Vec<CValue>]) -> Result<(), ArrowError> {
+        let mut writers = self.writers.lock().unwrap();
+        let key = format!("{}.{}", schema, table_name);
+        let path = self.get_path(schema, table_name);
+
+        match writers.remove(&key) {
+            None => {
+                let file = File::create(&path)?;
+                writers.insert(key.clone(), FileWriter::try_new(file, &batch_schema)?);
+            }
+            Some(writer) => {
+                let merged = merge_schemas(writer.schema(), &batch_schema);
+                let writer = match &merged == writer.schema().as_ref() {
+                    true => writer,
+                    false => rewrite_file(&path, writer, Arc::new(merged))?
+                };
+                writers.insert(key.clone(), writer);
+            }
+        }
+        let writer = writers.get_mut(&key).unwrap();
+        let batch = rows_to_record_batch(writer.schema().clone(), columns, rows)?;
+        writer.write(&batch)
+    }
+
+    /// Writes a whole CDataFrame as the table `table_name`.
+    pub fn write_dataframe(&self, schema: &str, table_name: &str, dataframe: &CDataFrame) -> Result<(), ArrowError> {
+        let batch = dataframe_to_record_batch(dataframe)?;
+        let file = File::create(self.get_path(schema, table_name))?;
+        let mut writer = FileWriter::try_new(file, &batch.schema())?;
+        writer.write(&batch)?;
+        writer.finish()
+    }
+
+    /// Writes the footers and closes every file.
+    pub fn finish(&self) -> Result<(), ArrowError> {
+        let mut writers = self.writers.lock().unwrap();
+        for (_, mut writer) in writers.drain() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the schema of a file with the columns of `batch` it does not have yet and its null
+/// columns typed by `batch`, the other columns keep their type.
+fn merge_schemas(file: &Schema, batch: &Schema) -> Schema {
+    let mut fields: Vec<Field> = file.fields()
+        .iter()
+        .map(|field| match batch.field_with_name(field.name()) {
+            Ok(typed) if field.data_type() == &DataType::Null => typed.clone(),
+            _ => field.as_ref().clone()
+        })
+        .collect();
+    fields.extend(batch.fields().iter().filter(|field| file.field_with_name(field.name()).is_err()).map(|field| field.as_ref().clone()));
+    Schema::new(fields)
+}
+
+/// Finishes a file and writes its batches again with `schema`, null columns are cast to their new
+/// type and new columns are nulls. Returns the writer of the new file.
+fn rewrite_file(path: &Path, mut writer: FileWriter<File>, schema: SchemaRef) -> Result<FileWriter<File>, ArrowError> {
+    writer.finish()?;
+    let batches = FileReader::try_new(File::open(path)?, None)?.collect::<Result<Vec<RecordBatch>, ArrowError>>()?;
+
+    let mut writer = FileWriter::try_new(File::create(path)?, &schema)?;
+    for batch in batches {
+        let arrays = schema.fields()
+            .iter()
+            .map(|field| match batch.column_by_name(field.name()) {
+                Some(array) => cast(array, field.data_type()),
+                None => Ok(new_null_array(field.data_type(), batch.num_rows()))
+            })
+            .collect::<Result<Vec<ArrayRef>, ArrowError>>()?;
+        writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+    }
+    Ok(writer)
+}
+
+impl Drop for ArrowIPCSink {
+    fn drop(&mut self) {
+        // A poisoned lock means a write panicked, its file is left without footer.
+        let Ok(writers) = self.writers.get_mut() else { return };
+        for (key, mut writer) in writers.drain() {
+            if let Err(e) = writer.finish() {
+                eprintln!("Could not finish the Arrow file of {}: {}", key, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for ArrowIPCSink {
+    async fn create_table(&self, _schema: &str, _table_name: &str, _columns: &[(String, String)]) -> Result<(), SinkError> {
+        // Files are created with the schema of their first batch.
+        Ok(())
+    }
+
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        // The columns of the open file, new ones are added by the batch that has them.
+        let key = format!("{}.{}", schema, table_name);
+        self.writers
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|writer| writer.schema().fields().iter().map(|field| field.name().clone()).collect())
+            .unwrap_or_default()
+    }
+
+    async fn add_column(&self, _schema: &str, _table_name: &str, _column: &str, _dtype: &str) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let batch_schema = Arc::new(infer_schema(columns, &buffer));
+        Ok(self.write_batch(schema, table_name, batch_schema, columns, &buffer)?)
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::fs::{create_dir_all, remove_file, File};
+use std::io::{BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sqlx::Row;
+use sqlx::types::Json;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::experiment::data::CValue;
+use crate::experiment::trans::cvalue_to_json;
+use crate::sink::cratedb::driver::CrateDB;
+use crate::source::source::{Sink, SinkError};
+use crate::utils::get_fqn_table;
+
+/// Where batches are staged for CrateDB to read them.
+#[derive(Debug, Clone)]
+pub enum Staging {
+    /// A directory mounted in the CrateDB nodes too, `cratedb_path` is where the nodes see it,
+    /// e.g. `/data/staging` written locally as `/mnt/staging`.
+    SharedDirectory { local_path: String, cratedb_path: String },
+    /// A local directory served by a built-in HTTP server bound to `bind`, `public_url` is how
+    /// CrateDB reaches it, e.g. `http://10.0.0.5:8080`.
+    Http { local_path: String, bind: SocketAddr, public_url: String },
+}
+
+impl Staging {
+    fn local_path(&self) -> &str {
+        match self {
+            Staging::SharedDirectory { local_path, .. } | Staging::Http { local_path, .. } => local_path
+        }
+    }
+
+    /// Returns the URI CrateDB reads a staged file from.
+    fn uri(&self, file_name: &str) -> String {
+        match self {
+            Staging::SharedDirectory { cratedb_path, .. } => format!("file://{}/{}", cratedb_path.trim_end_matches('/'), file_name),
+            Staging::Http { public_url, .. } => format!("{}/{}", public_url.trim_end_matches('/'), file_name),
+        }
+    }
+}
+
+/// One row of `COPY ... RETURN SUMMARY`, errors are `{message: {count, line_numbers}}`.
+#[derive(Debug, Clone)]
+pub struct CopySummary {
+    pub uri: String,
+    pub success_count: i64,
+    pub error_count: i64,
+    pub errors: Value,
+}
+
+impl CopySummary {
+    /// Returns the failed rows of the summary, e.g. `2 rows imported, 1 failed: "Cannot cast..." (1 rows, lines [3])`.
+    pub fn error_report(&self) -> String {
+        let errors: Vec<String> = match &self.errors {
+            Value::Object(errors) => errors
+                .iter()
+                .map(|(message, error)| format!("{:?} ({} rows, lines {})",
+                                                message,
+                                                error.get("count").unwrap_or(&Value::Null),
+                                                error.get("line_numbers").unwrap_or(&Value::Null)))
+                .collect(),
+            Value::Null => vec![],
+            other => vec![other.to_string()]
+        };
+        format!("{} rows imported, {} failed: {}", self.success_count, self.error_count, errors.join(", "))
+    }
+}
+
+/// Checks the summaries of a staged file, it is an error if no node read it or any row failed.
+fn check_summaries(file_name: &str, summaries: &[CopySummary]) -> Result<(), SinkError> {
+    if summaries.is_empty() {
+        return Err(SinkError::new(format!("COPY FROM {} - no node read the file, is the staging directory reachable?", file_name)));
+    }
+    let failed: Vec<String> = summaries
+        .iter()
+        .filter(|s| s.error_count > 0)
+        .map(|s| format!("COPY FROM {} - {}", s.uri, s.error_report()))
+        .collect();
+    match failed.is_empty() {
+        true => Ok(()),
+        false => Err(SinkError::new(failed.join("; ")))
+    }
+}
+
+/// Sink that loads batches with `COPY FROM` instead of `INSERT`, for very large tables.
+///
+/// Every batch is written as a JSON file to the staging directory and imported with
+/// `COPY <table> FROM '<uri>' RETURN SUMMARY`. Files that imported without errors are removed. A
+/// batch with failed rows, or that no node read, is an error and its file is kept to inspect it;
+/// the summaries are kept in `summaries`.
+pub struct CrateDBCopySink {
+    pub(crate) cratedb: CrateDB,
+    pub(crate) staging: Staging,
+    files: AtomicUsize,
+    summaries: Mutex<Vec<CopySummary>>,
+    server: Option<(SocketAddr, JoinHandle<()>)>,
+}
+
+/// Answers a GET with a file of `directory`, only plain file names are served.
+async fn serve_file(stream: TcpStream, directory: PathBuf) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+
+    // Headers are not needed, they are read until the empty line.
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line == "\r\n" {
+            break;
+        }
+    }
+
+    let mut stream = reader.into_inner();
+    let name = request_line.split_whitespace().nth(1).unwrap_or("/").trim_start_matches('/');
+    let file = match name.is_empty() || name.contains('/') || name.contains("..") {
+        true => None,
+        false => tokio::fs::File::open(directory.join(name)).await.ok()
+    };
+
+    match file {
+        Some(mut file) => {
+            let length = file.metadata().await?.len();
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", length);
+            stream.write_all(header.as_bytes()).await?;
+            tokio::io::copy(&mut file, &mut stream).await?;
+        }
+        None => stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await?
+    }
+    stream.shutdown().await
+}
+
+impl CrateDBCopySink {
+    /// Creates the staging directory and, for `Staging::Http`, starts the file server.
+    pub async fn new(cratedb: CrateDB, staging: Staging) -> std::io::Result<Self> {
+        create_dir_all(staging.local_path())?;
+
+        let server = match &staging {
+            Staging::Http { local_path, bind, .. } => {
+                let listener = TcpListener::bind(bind).await?;
+                let address = listener.local_addr()?;
+                let directory = PathBuf::from(local_path);
+                Some((address, tokio::spawn(async move {
+                    while let Ok((stream, _)) = listener.accept().await {
+                        let directory = directory.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = serve_file(stream, directory).await {
+                                eprintln!("Could not serve staged file - {}", e);
+                            }
+                        });
+                    }
+                })))
+            }
+            Staging::SharedDirectory { .. } => None
+        };
+
+        Ok(Self {
+            cratedb,
+            staging,
+            files: AtomicUsize::new(0),
+            summaries: Mutex::new(vec![]),
+            server,
+        })
+    }
+
+    /// Writes rows as JSON lines to a new staged file and returns its name.
+    fn stage_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: &[Vec<CValue>]) -> std::io::Result<String> {
+        let file_name = format!("{}.{}.{:06}.json", schema, table_name, self.files.fetch_add(1, Ordering::SeqCst));
+        let mut file = BufWriter::new(File::create(PathBuf::from(self.staging.local_path()).join(&file_name))?);
+        for row in buffer {
+            let object: Map<String, Value> = columns
+                .iter()
+                .zip(row)
+                .filter(|(_, value)| !matches!(value, CValue::None | CValue::Unknown))
+                .map(|(column, value)| (column.to_string(), cvalue_to_json(value)))
+                .collect();
+            serde_json::to_writer(&mut file, &object)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(file_name)
+    }
+
+    /// Runs `COPY FROM` for a staged file and returns its summary, one row per node that read it.
+    async fn copy_file(&self, schema: &str, table_name: &str, file_name: &str) -> Result<Vec<CopySummary>, sqlx::Error> {
+        // With `shared` every node does not import its own copy of the file.
+        let stmt = format!("COPY {} FROM '{}' WITH (format = 'json', shared = true) RETURN SUMMARY",
+                           get_fqn_table(schema, table_name),
+                           self.staging.uri(file_name));
+
+        let pool = self.cratedb.get_pool().await?;
+        let rows = sqlx::query(&stmt).fetch_all(&pool).await?;
+        rows.iter()
+            .map(|row| Ok(CopySummary {
+                uri: row.try_get("uri")?,
+                success_count: row.try_get::<Option<i64>, _>("success_count")?.unwrap_or(0),
+                error_count: row.try_get::<Option<i64>, _>("error_count")?.unwrap_or(0),
+                errors: row.try_get::<Option<Json<Value>>, _>("errors")?.map(|e| e.0).unwrap_or(Value::Null),
+            }))
+            .collect()
+    }
+
+    /// Address the file server is bound to, useful when binding to port 0.
+    pub fn server_address(&self) -> Option<SocketAddr> {
+        self.server.as_ref().map(|(address, _)| *address)
+    }
+
+    /// Summaries of every `COPY FROM` run so far.
+    pub fn summaries(&self) -> Vec<CopySummary> {
+        self.summaries.lock().unwrap().clone()
+    }
+}
+
+impl Drop for CrateDBCopySink {
+    fn drop(&mut self) {
+        if let Some((_, server)) = &self.server {
+            server.abort();
+        }
+    }
+}
+
+impl fmt::Debug for CrateDBCopySink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CrateDBCopySink({:?})", self.staging)
+    }
+}
+
+#[async_trait]
+impl Sink for CrateDBCopySink {
+    async fn create_table(&self, schema: &str, table_name: &str, columns: &[(String, String)]) -> Result<(), SinkError> {
+        self.cratedb.create_table(schema, table_name, columns).await
+    }
+
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        self.cratedb.list_columns(schema, table_name).await
+    }
+
+    async fn add_column(&self, schema: &str, table_name: &str, column: &str, dtype: &str) -> Result<(), SinkError> {
+        self.cratedb.add_column(schema, table_name, column, dtype).await
+    }
+
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let file_name = self.stage_batch(schema, table_name, columns, &buffer)?;
+        let summaries = self.copy_file(schema, table_name, &file_name).await?;
+
+        let result = check_summaries(&file_name, &summaries);
+        if result.is_ok() {
+            let _ = remove_file(PathBuf::from(self.staging.local_path()).join(&file_name));
+        }
+        self.summaries.lock().unwrap().extend(summaries);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use serde_json::json;
+
+    use crate::experiment::data::CValue;
+    use crate::sink::cratedb::copy::{check_summaries, CopySummary, CrateDBCopySink, Staging};
+    use crate::sink::cratedb::driver::CrateDB;
+
+    #[tokio::test]
+    async fn test_staged_file_is_served() {
+        let directory = std::env::temp_dir().join("cdctest_copy_staging");
+        let _ = fs::remove_dir_all(&directory);
+        let staging = Staging::Http {
+            local_path: directory.to_str().unwrap().to_string(),
+            bind: "127.0.0.1:0".parse().unwrap(),
+            public_url: "http://127.0.0.1:0".to_string(),
+        };
+        let sink = CrateDBCopySink::new(CrateDB::default(), staging).await.unwrap();
+
+        let columns = vec!["id".to_string(), "obj".to_string(), "missing".to_string()];
+        let rows = vec![vec![CValue::I32(1), CValue::Object(HashMap::from([("a".to_string(), CValue::VecI64(vec![1, 2]))])), CValue::None]];
+        let file_name = sink.stage_batch("doc", "data", &columns, &rows).unwrap();
+        assert_eq!(file_name, "doc.data.000000.json");
+        assert_eq!(sink.staging.uri(&file_name), "http://127.0.0.1:0/doc.data.000000.json");
+
+        let address = sink.server_address().unwrap();
+        let body = reqwest::get(format!("http://{}/{}", address, file_name)).await.unwrap().text().await.unwrap();
+        assert_eq!(body, "{\"id\":1,\"obj\":{\"a\":[1,2]}}\n");
+
+        let status = reqwest::get(format!("http://{}/..%2Fetc%2Fpasswd", address)).await.unwrap().status();
+        assert_eq!(status, 404);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_summaries() {
+        let summary = |uri: &str, success_count: i64, error_count: i64, errors| CopySummary { uri: uri.to_string(), success_count, error_count, errors };
+        let imported = summary("http://a/doc.data.000000.json", 10, 0, json!({}));
+        let failed = summary("http://a/doc.data.000000.json", 8, 2, json!({
+            "Cannot cast value": {"count": 2, "line_numbers": [3, 7]}
+        }));
+
+        assert!(check_summaries("doc.data.000000.json", std::slice::from_ref(&imported)).is_ok());
+        assert_eq!(failed.error_report(), r#"8 rows imported, 2 failed: "Cannot cast value" (2 rows, lines [3,7])"#);
+        let error = check_summaries("doc.data.000000.json", &[imported, failed]).unwrap_err().to_string();
+        assert!(error.contains("COPY FROM http://a/doc.data.000000.json - 8 rows imported, 2 failed"));
+        assert!(check_summaries("doc.data.000000.json", &[]).unwrap_err().to_string().contains("no node read the file"));
+    }
+}
@@ -1 +1,2 @@
 pub mod driver;
+pub mod copy;
\ No newline at end of file
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::experiment::data::{CDataFrame, CValue};
+use crate::experiment::schema::CSchema;
+use crate::experiment::trans::{check_dataset_with_report, StrategyReport};
+use crate::metadata::Metadata;
+use crate::source::source::{Sink, SinkError, SinkWrapper};
+use crate::utils::lookup_table;
+
+/// Sink that enforces a `CSchema` on every batch before sending it to `sink`, values whose type
+/// does not match their column's dtype are cast, moved to a `<column>_<type>` column or their row
+/// removed, depending on the column's `DtypeStrategy` (see `check_dataset`).
+///
+/// Schemas are looked up by `<schema>.<table>` and then by `<table>`, columns not in the schema are
+/// sent as they are. What every strategy did is added up per table in `reports`.
+pub struct SchemaEnforcement<S: Sink> {
+    pub(crate) sink: S,
+    pub(crate) schemas: HashMap<String, CSchema>,
+    reports: Mutex<HashMap<String, StrategyReport>>,
+}
+
+impl<S: Sink> SchemaEnforcement<S> {
+    pub fn new(sink: S) -> Self {
+        Self { sink, schemas: HashMap::new(), reports: Mutex::new(HashMap::new()) }
+    }
+
+    /// Returns the report of every table, `<schema>.<table>` -> report.
+    pub fn reports(&self) -> HashMap<String, StrategyReport> {
+        self.reports.lock().unwrap().clone()
+    }
+
+    pub fn print_reports(&self, metadata: &Metadata) {
+        for (table, report) in self.reports.lock().unwrap().iter() {
+            metadata.print_line(&format!("\nSchema enforcement of {}", table));
+            report.print(metadata);
+        }
+    }
+
+    pub fn with_schema(mut self, table: &str, schema: CSchema) -> Self {
+        self.schemas.insert(table.to_string(), schema);
+        self
+    }
+
+    fn get_schema(&self, schema: &str, table_name: &str) -> Option<&CSchema> {
+        lookup_table(&self.schemas, schema, table_name)
+    }
+}
+
+impl<S: Sink> fmt::Debug for SchemaEnforcement<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SchemaEnforcement({:?}, {} schemas)", self.sink, self.schemas.len())
+    }
+}
+
+#[async_trait]
+impl<S: Sink> SinkWrapper for SchemaEnforcement<S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        &self.sink
+    }
+
+    fn passes_through(&self, schema: &str, table_name: &str) -> bool {
+        self.get_schema(schema, table_name).is_none()
+    }
+
+    async fn wrap_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let Some(table_schema) = self.get_schema(schema, table_name) else {
+            return self.sink.send_batch(schema, table_name, columns, buffer).await;
+        };
+
+        let (dataframe, report) = check_dataset_with_report(CDataFrame::from_rows(columns, buffer), table_schema);
+        self.reports.lock().unwrap().entry(format!("{}.{}", schema, table_name)).or_default().merge(report);
+
+        let (columns, buffer) = dataframe.into_rows();
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        self.sink.send_batch(schema, table_name, &columns, buffer).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    
+
+    use crate::experiment::data::{CDataFrame, CValue};
+    use crate::experiment::schema::CSchema;
+    use crate::experiment::trans::{check_dataset, ColumnReport};
+    use crate::sink::cratedb::driver::CrateDB;
+    use crate::sink::enforce::SchemaEnforcement;
+    use crate::source::source::Sink;
+    use crate::testing::MemorySink;
+
+    fn schema() -> CSchema {
+        serde_json::from_str(r#"{
+            "id": {"dtype": "I32", "dtype_collision_strategy": "Cast"},
+            "name": {"dtype": "String", "dtype_collision_strategy": "NewCol"},
+            "age": {"dtype": "I32", "dtype_collision_strategy": "Remove"},
+            "score": {"dtype": "I16", "dtype_collision_strategy": "Cast", "cast_fallback": "Remove"},
+            "tag": {"dtype": "String", "dtype_collision_strategy": "Ignore"}
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_check_dataset() {
+        let columns = vec!["id".to_string(), "name".to_string(), "age".to_string()];
+        let rows = vec![
+            vec![CValue::String("1".to_string()), CValue::String("a".to_string()), CValue::I32(30)],
+            vec![CValue::String("x".to_string()), CValue::I32(7), CValue::None],
+            vec![CValue::I32(3), CValue::String("c".to_string()), CValue::String("old".to_string())],
+        ];
+
+        let (columns, rows) = check_dataset(CDataFrame::from_rows(&columns, rows), &schema()).into_rows();
+        assert_eq!(columns, vec!["id", "name", "age", "name_i32"]);
+        assert_eq!(rows.len(), 2);
+
+        assert!(matches!(rows[0][0], CValue::I32(1)));
+        assert!(matches!(&rows[0][1], CValue::String(s) if s == "a"));
+        assert!(matches!(rows[0][3], CValue::None));
+
+        // "x" can not be cast and is nulled, 7 is moved to name_i32.
+        assert!(matches!(rows[1][0], CValue::None));
+        assert!(matches!(rows[1][1], CValue::None));
+        assert!(matches!(rows[1][3], CValue::I32(7)));
+    }
+
+    #[test]
+    fn test_get_schema() {
+        let sink = SchemaEnforcement::new(CrateDB::default())
+            .with_schema("users", schema())
+            .with_schema("app.orders", schema());
+
+        assert!(sink.get_schema("doc", "users").is_some());
+        assert!(sink.get_schema("app", "orders").is_some());
+        assert!(sink.get_schema("doc", "orders").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reports() {
+        let sink = SchemaEnforcement::new(MemorySink::default()).with_schema("users", schema());
+        let columns = vec!["name".to_string(), "age".to_string(), "score".to_string(), "tag".to_string()];
+
+        sink.send_batch("doc", "users", &columns, vec![
+            vec![CValue::I32(1), CValue::String("old".to_string()), CValue::String("7".to_string()), CValue::None],
+            vec![CValue::String("b".to_string()), CValue::I32(1), CValue::String("many".to_string()), CValue::None],
+        ]).await.unwrap();
+        sink.send_batch("doc", "users", &columns, vec![
+            vec![CValue::Bool(true), CValue::I32(2), CValue::I32(3), CValue::I32(5)],
+        ]).await.unwrap();
+
+        // The first row is removed for its age, its name and score do not count.
+        let report = sink.reports().remove("doc.users").unwrap();
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.rows_removed, 2);
+        assert_eq!(report.columns["name"], ColumnReport { new_col: 1, ..Default::default() });
+        assert_eq!(report.columns["age"], ColumnReport { removed: 1, ..Default::default() });
+        assert_eq!(report.columns["score"], ColumnReport { cast: 1, cast_failed: 1, removed: 1, ..Default::default() });
+        assert_eq!(report.columns["tag"], ColumnReport { ignored: 1, ..Default::default() });
+    }
+}
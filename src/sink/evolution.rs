@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::experiment::data::CValue;
+use crate::metadata::Metadata;
+use crate::sink::cratedb::driver::cvalue_type_to_cratedb;
+use crate::source::source::{Sink, SinkError};
+
+/// What to do with fields that the target table does not have.
+#[derive(Debug, Clone)]
+pub enum SchemaPolicy {
+    /// Adds them with `ALTER TABLE ADD COLUMN`, typed from their first non-null value.
+    AddColumns,
+    /// Keeps the table's columns and moves new fields into the OBJECT column `column`, e.g. for
+    /// tables with a strict schema.
+    Overflow { column: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChangeKind {
+    /// A column was added with the given type.
+    Added(String),
+    /// The column could not be typed (e.g. mixed arrays) and is left to the sink's dynamic columns.
+    Untyped,
+    /// The field is written to the overflow column.
+    Overflowed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaChange {
+    pub table: String,
+    pub column: String,
+    pub kind: SchemaChangeKind,
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            SchemaChangeKind::Added(dtype) => write!(f, "Schema change: added column {} {} to {}", self.column, dtype, self.table),
+            SchemaChangeKind::Untyped => write!(f, "Schema change: column {} of {} has no type, it is sent as it is", self.column, self.table),
+            SchemaChangeKind::Overflowed(overflow) => write!(f, "Schema change: field {} of {} is written to {}", self.column, self.table, overflow),
+        }
+    }
+}
+
+/// Sink that evolves the target tables when batches bring new fields and then sends them to `sink`.
+///
+/// The columns of every table are read from the sink (`information_schema.columns` for CrateDB and
+/// PostgreSQL) and read again after columns are added. Every change is kept in `changes`, see
+/// `print_changes`. Tables without columns, e.g. not created yet or files not written yet, are read again
+/// on the next batch and their batches are passed as they are.
+pub struct SchemaEvolution<S: Sink> {
+    pub(crate) sink: S,
+    pub(crate) policy: SchemaPolicy,
+    columns: Mutex<HashMap<String, Vec<String>>>,
+    changes: Mutex<Vec<SchemaChange>>,
+}
+
+impl<S: Sink> SchemaEvolution<S> {
+    pub fn new(sink: S, policy: SchemaPolicy) -> Self {
+        Self {
+            sink,
+            policy,
+            columns: Mutex::new(HashMap::new()),
+            changes: Mutex::new(vec![]),
+        }
+    }
+
+    /// Every schema change made so far.
+    pub fn changes(&self) -> Vec<SchemaChange> {
+        self.changes.lock().unwrap().clone()
+    }
+
+    pub fn print_changes(&self, metadata: &Metadata) {
+        for change in self.changes.lock().unwrap().iter() {
+            metadata.print_line(&change.to_string());
+        }
+    }
+
+    fn log_change(&self, table: &str, column: &str, kind: SchemaChangeKind) {
+        self.changes.lock().unwrap().push(SchemaChange { table: table.to_string(), column: column.to_string(), kind });
+    }
+
+    async fn known_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        let key = format!("{}.{}", schema, table_name);
+        if let Some(columns) = self.columns.lock().unwrap().get(&key) {
+            return columns.clone();
+        }
+
+        let columns = self.sink.list_columns(schema, table_name).await;
+        if !columns.is_empty() {
+            self.columns.lock().unwrap().insert(key, columns.clone());
+        }
+        columns
+    }
+
+    /// Reads the columns of a table from the sink again, keeping the tracked ones it does not list,
+    /// e.g. untyped columns or fields of the overflow column.
+    async fn refresh_columns(&self, schema: &str, table_name: &str) {
+        let mut columns = self.sink.list_columns(schema, table_name).await;
+        let key = format!("{}.{}", schema, table_name);
+        let mut known = self.columns.lock().unwrap();
+        for column in known.remove(&key).unwrap_or_default() {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        known.insert(key, columns);
+    }
+
+    fn track_column(&self, schema: &str, table_name: &str, column: &str) {
+        let key = format!("{}.{}", schema, table_name);
+        self.columns.lock().unwrap().entry(key).or_default().push(column.to_string());
+    }
+
+    /// Adds the new columns to the table, typed from their first non-null value. Columns that are
+    /// only nulls are removed from the batch and left for a later one since their type is unknown.
+    async fn add_columns(&self, schema: &str, table_name: &str, columns: Vec<String>, buffer: Vec<Vec<CValue>>, new_columns: Vec<usize>) -> Result<(Vec<String>, Vec<Vec<CValue>>), SinkError> {
+        let table = format!("{}.{}", schema, table_name);
+        let mut null_columns: Vec<usize> = vec![];
+        for i in new_columns {
+            let first_value = buffer.iter().map(|row| &row[i]).find(|value| !matches!(value, CValue::None));
+            let Some(first_value) = first_value else {
+                null_columns.push(i);
+                continue;
+            };
+
+            let column = &columns[i];
+            match cvalue_type_to_cratedb(&first_value.get_dtype()) {
+                Some(dtype) => {
+                    self.sink.add_column(schema, table_name, column, &dtype).await?;
+                    self.log_change(&table, column, SchemaChangeKind::Added(dtype));
+                }
+                None => self.log_change(&table, column, SchemaChangeKind::Untyped)
+            }
+            self.track_column(schema, table_name, column);
+        }
+        self.refresh_columns(schema, table_name).await;
+
+        if null_columns.is_empty() {
+            return Ok((columns, buffer));
+        }
+        let keep = |i: &usize| !null_columns.contains(i);
+        let columns = columns.into_iter().enumerate().filter(|(i, _)| keep(i)).map(|(_, c)| c).collect();
+        let buffer = buffer
+            .into_iter()
+            .map(|row| row.into_iter().enumerate().filter(|(i, _)| keep(i)).map(|(_, v)| v).collect())
+            .collect();
+        Ok((columns, buffer))
+    }
+
+    /// Moves the values of the new columns into the overflow column, adding it if needed.
+    async fn overflow(&self, schema: &str, table_name: &str, overflow: &str, known: &[String], (columns, buffer): (Vec<String>, Vec<Vec<CValue>>), new_columns: Vec<usize>) -> Result<(Vec<String>, Vec<Vec<CValue>>), SinkError> {
+        let table = format!("{}.{}", schema, table_name);
+        if !known.iter().any(|c| c == overflow) {
+            self.sink.add_column(schema, table_name, overflow, "OBJECT(DYNAMIC)").await?;
+            self.log_change(&table, overflow, SchemaChangeKind::Added("OBJECT(DYNAMIC)".to_string()));
+            self.track_column(schema, table_name, overflow);
+            self.refresh_columns(schema, table_name).await;
+        }
+
+        let existing_overflow = columns.iter().position(|c| c == overflow);
+
+        // Fields are only reported the first time, they are tracked as `<overflow>['field']`.
+        for &i in &new_columns {
+            let tracked = format!("{}['{}']", overflow, columns[i]);
+            if !known.contains(&tracked) {
+                self.log_change(&table, &columns[i], SchemaChangeKind::Overflowed(overflow.to_string()));
+                self.track_column(schema, table_name, &tracked);
+            }
+        }
+
+        let kept: Vec<usize> = (0..columns.len()).filter(|i| !new_columns.contains(i) && Some(*i) != existing_overflow).collect();
+        let mut new_names: Vec<String> = kept.iter().map(|&i| columns[i].clone()).collect();
+        new_names.push(overflow.to_string());
+
+        let rows = buffer
+            .into_iter()
+            .map(|mut row| {
+                let mut object = match existing_overflow.map(|i| std::mem::replace(&mut row[i], CValue::None)) {
+                    Some(CValue::Object(object)) => object,
+                    _ => HashMap::new()
+                };
+                for &i in &new_columns {
+                    let value = std::mem::replace(&mut row[i], CValue::None);
+                    if !matches!(value, CValue::None) {
+                        object.insert(columns[i].clone(), value);
+                    }
+                }
+
+                let mut new_row: Vec<CValue> = kept.iter().map(|&i| std::mem::replace(&mut row[i], CValue::None)).collect();
+                new_row.push(if object.is_empty() { CValue::None } else { CValue::Object(object) });
+                new_row
+            })
+            .collect();
+        Ok((new_names, rows))
+    }
+}
+
+impl<S: Sink> fmt::Debug for SchemaEvolution<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SchemaEvolution({:?}, {:?})", self.sink, self.policy)
+    }
+}
+
+#[async_trait]
+impl<S: Sink> Sink for SchemaEvolution<S> {
+    async fn create_table(&self, schema: &str, table_name: &str, columns: &[(String, String)]) -> Result<(), SinkError> {
+        self.sink.create_table(schema, table_name, columns).await?;
+        // The table could have existed with other columns, they are read again on the next batch.
+        self.columns.lock().unwrap().remove(&format!("{}.{}", schema, table_name));
+        Ok(())
+    }
+
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        self.known_columns(schema, table_name).await
+    }
+
+    async fn add_column(&self, schema: &str, table_name: &str, column: &str, dtype: &str) -> Result<(), SinkError> {
+        self.sink.add_column(schema, table_name, column, dtype).await?;
+        self.track_column(schema, table_name, column);
+        Ok(())
+    }
+
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let known = self.known_columns(schema, table_name).await;
+        if known.is_empty() {
+            return self.sink.send_batch(schema, table_name, columns, buffer).await;
+        }
+
+        let new_columns: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !known.contains(c))
+            .map(|(i, _)| i)
+            .collect();
+        if new_columns.is_empty() {
+            return self.sink.send_batch(schema, table_name, columns, buffer).await;
+        }
+
+        match &self.policy {
+            SchemaPolicy::AddColumns => {
+                let (columns, buffer) = self.add_columns(schema, table_name, columns.to_vec(), buffer, new_columns).await?;
+                self.sink.send_batch(schema, table_name, &columns, buffer).await
+            }
+            SchemaPolicy::Overflow { column } => {
+                let (columns, buffer) = self.overflow(schema, table_name, column, &known, (columns.to_vec(), buffer), new_columns).await?;
+                self.sink.send_batch(schema, table_name, &columns, buffer).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::experiment::data::CValue;
+    use crate::sink::evolution::{SchemaChangeKind, SchemaEvolution, SchemaPolicy};
+    use crate::source::source::Sink;
+    use crate::testing::MemorySink;
+
+    async fn sink_with_table(policy: SchemaPolicy) -> SchemaEvolution<MemorySink> {
+        let sink = SchemaEvolution::new(MemorySink::default(), policy);
+        sink.create_table("doc", "data", &[("id".to_string(), "BIGINT".to_string())]).await.unwrap();
+        sink
+    }
+
+    #[tokio::test]
+    async fn test_add_columns() {
+        let sink = sink_with_table(SchemaPolicy::AddColumns).await;
+        let columns = vec!["id".to_string(), "name".to_string(), "empty".to_string()];
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(1), CValue::String("a".to_string()), CValue::None]]).await.unwrap();
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(2), CValue::String("b".to_string()), CValue::None]]).await.unwrap();
+
+        assert_eq!(*sink.sink.columns.lock().unwrap(), vec!["id", "name"]);
+        assert_eq!(sink.sink.batches.lock().unwrap()[0].0, vec!["id", "name"]);
+        let changes = sink.changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].column, "name");
+        assert_eq!(changes[0].kind, SchemaChangeKind::Added("TEXT".to_string()));
+        assert_eq!(changes[0].to_string(), "Schema change: added column name TEXT to doc.data");
+    }
+
+    #[tokio::test]
+    async fn test_overflow() {
+        let sink = sink_with_table(SchemaPolicy::Overflow { column: "extra".to_string() }).await;
+        let columns = vec!["id".to_string(), "name".to_string()];
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(1), CValue::String("a".to_string())], vec![CValue::I64(2), CValue::None]]).await.unwrap();
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(3), CValue::String("c".to_string())]]).await.unwrap();
+
+        assert_eq!(*sink.sink.columns.lock().unwrap(), vec!["id", "extra"]);
+        let batches = sink.sink.batches.lock().unwrap();
+        assert_eq!(batches[0].0, vec!["id", "extra"]);
+        assert!(matches!(&batches[0].1[0][1], CValue::Object(o) if matches!(o.get("name"), Some(CValue::String(s)) if s == "a")));
+        assert!(matches!(&batches[0].1[1][1], CValue::None));
+
+        // The field is reported once, the overflow column once.
+        let kinds: Vec<SchemaChangeKind> = sink.changes().into_iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, vec![SchemaChangeKind::Added("OBJECT(DYNAMIC)".to_string()), SchemaChangeKind::Overflowed("extra".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_table_created_later() {
+        // A table without columns is read again, so batches after it is created are evolved.
+        let sink = SchemaEvolution::new(MemorySink::default(), SchemaPolicy::AddColumns);
+        let columns = vec!["id".to_string(), "name".to_string()];
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(1), CValue::String("a".to_string())]]).await.unwrap();
+        assert!(sink.changes().is_empty());
+
+        sink.sink.create_table("doc", "data", &[("id".to_string(), "BIGINT".to_string())]).await.unwrap();
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(2), CValue::String("b".to_string())]]).await.unwrap();
+        assert_eq!(*sink.sink.columns.lock().unwrap(), vec!["id", "name"]);
+        assert_eq!(sink.changes().len(), 1);
+    }
+}
\ No newline at end of file
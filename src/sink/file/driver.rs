@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use arrow::datatypes::SchemaRef;
+use async_trait::async_trait;
+use flate2::write::GzEncoder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+
+use crate::experiment::data::CValue;
+use crate::experiment::trans::cvalue_to_json;
+use crate::sink::arrow::driver::{fits_field, infer_schema, rows_to_record_batch};
+use crate::source::source::{Sink, SinkError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileSinkFormat {
+    JsonLines,
+    Csv,
+    Parquet,
+}
+
+/// Compression of the files, JSON Lines and CSV files are compressed as a whole (`.gz`, `.zst`)
+/// and Parquet files by column chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// When to start a new file of a table, whichever limit is reached first. Sizes are uncompressed
+/// bytes for JSON Lines and CSV.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rotation {
+    pub max_rows: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct WriteFileError {
+    message: String,
+}
+
+impl fmt::Display for WriteFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error writing file: {}", self.message)
+    }
+}
+
+macro_rules! impl_from_error {
+    ($($error:ty),+) => {
+        $(impl From<$error> for WriteFileError {
+            fn from(e: $error) -> Self {
+                WriteFileError { message: e.to_string() }
+            }
+        })+
+    };
+}
+
+impl_from_error!(std::io::Error, csv::Error, parquet::errors::ParquetError, arrow::error::ArrowError);
+
+/// Writer of uncompressed bytes that counts them, used to rotate text files by size.
+struct Encoder {
+    inner: EncoderKind,
+    bytes: usize,
+}
+
+enum EncoderKind {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Encoder {
+    fn new(file: File, compression: FileCompression) -> Result<Self, WriteFileError> {
+        let file = BufWriter::new(file);
+        let inner = match compression {
+            FileCompression::None => EncoderKind::Plain(file),
+            FileCompression::Gzip => EncoderKind::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            FileCompression::Zstd => EncoderKind::Zstd(zstd::Encoder::new(file, 0)?),
+        };
+        Ok(Self { inner, bytes: 0 })
+    }
+
+    /// Writes the compression footer and flushes the file.
+    fn finish(self) -> Result<(), WriteFileError> {
+        let mut file = match self.inner {
+            EncoderKind::Plain(file) => file,
+            EncoderKind::Gzip(encoder) => encoder.finish()?,
+            EncoderKind::Zstd(encoder) => encoder.finish()?,
+        };
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = match &mut self.inner {
+            EncoderKind::Plain(w) => w.write(buf)?,
+            EncoderKind::Gzip(w) => w.write(buf)?,
+            EncoderKind::Zstd(w) => w.write(buf)?,
+        };
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.inner {
+            EncoderKind::Plain(w) => w.flush(),
+            EncoderKind::Gzip(w) => w.flush(),
+            EncoderKind::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+enum TableWriter {
+    JsonLines(Encoder),
+    Csv(Box<csv::Writer<Encoder>>),
+    Parquet(ArrowWriter<File>),
+}
+
+/// The file a table is currently written to.
+struct OpenFile {
+    writer: TableWriter,
+    columns: Vec<String>,
+    schema: SchemaRef,
+    rows: usize,
+}
+
+impl OpenFile {
+    fn bytes(&self) -> usize {
+        match &self.writer {
+            TableWriter::JsonLines(encoder) => encoder.bytes,
+            TableWriter::Csv(writer) => writer.get_ref().bytes,
+            TableWriter::Parquet(writer) => writer.bytes_written() + writer.in_progress_size(),
+        }
+    }
+
+    fn close(self) -> Result<(), WriteFileError> {
+        match self.writer {
+            TableWriter::JsonLines(encoder) => encoder.finish(),
+            TableWriter::Csv(writer) => (*writer).into_inner().map_err(|e| WriteFileError { message: e.to_string() })?.finish(),
+            TableWriter::Parquet(writer) => writer.close().map(|_| ()).map_err(WriteFileError::from),
+        }
+    }
+}
+
+/// Whether every value of `rows` fits the type of its column in `schema`, see `fits_field`.
+fn rows_fit(schema: &SchemaRef, rows: &[Vec<CValue>]) -> bool {
+    schema.fields().iter().enumerate().all(|(i, field)| rows.iter().all(|row| row.get(i).is_none_or(|value| fits_field(field, value))))
+}
+
+/// Formats a value as a CSV field, nested values are JSON and nulls are empty.
+fn cvalue_to_csv(value: &CValue) -> String {
+    match value {
+        CValue::None | CValue::Unknown => String::new(),
+        CValue::String(v) => v.clone(),
+        _ => cvalue_to_json(value).to_string()
+    }
+}
+
+/// Sink that writes every table to files `<directory>/<schema>.<table>.<part>.<extension>` in JSON
+/// Lines, CSV or Parquet.
+///
+/// A new part is started when the rotation limits are reached. CSV and Parquet files have the
+/// columns of their first batch, a batch with other columns starts a new part too, as does a batch
+/// whose values do not fit the types of the Parquet file, e.g. values for a column that only had
+/// nulls. The files are closed by `finish` or when
+/// the sink is dropped.
+pub struct FileSink {
+    pub(crate) directory: String,
+    pub(crate) format: FileSinkFormat,
+    pub(crate) compression: FileCompression,
+    pub(crate) rotation: Rotation,
+    files: Mutex<HashMap<String, OpenFile>>,
+    parts: Mutex<HashMap<String, usize>>,
+}
+
+impl FileSink {
+    pub fn new(directory: &str, format: FileSinkFormat, compression: FileCompression, rotation: Rotation) -> Self {
+        create_dir_all(directory).unwrap_or_else(|_| panic!("Could not create directory {}", directory));
+        Self {
+            directory: directory.to_string(),
+            format,
+            compression,
+            rotation,
+            files: Mutex::new(HashMap::new()),
+            parts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_path(&self, key: &str, part: usize) -> PathBuf {
+        let extension = match (self.format, self.compression) {
+            (FileSinkFormat::Parquet, _) => "parquet",
+            (FileSinkFormat::JsonLines, FileCompression::None) => "jsonl",
+            (FileSinkFormat::JsonLines, FileCompression::Gzip) => "jsonl.gz",
+            (FileSinkFormat::JsonLines, FileCompression::Zstd) => "jsonl.zst",
+            (FileSinkFormat::Csv, FileCompression::None) => "csv",
+            (FileSinkFormat::Csv, FileCompression::Gzip) => "csv.gz",
+            (FileSinkFormat::Csv, FileCompression::Zstd) => "csv.zst",
+        };
+        PathBuf::from(&self.directory).join(format!("{}.{:05}.{}", key, part, extension))
+    }
+
+    fn open_file(&self, key: &str, columns: &[String], schema: SchemaRef) -> Result<OpenFile, WriteFileError> {
+        let mut parts = self.parts.lock().unwrap();
+        let part = parts.entry(key.to_string()).or_insert(0);
+        let file = File::create(self.get_path(key, *part))?;
+        *part += 1;
+
+        let writer = match self.format {
+            FileSinkFormat::JsonLines => TableWriter::JsonLines(Encoder::new(file, self.compression)?),
+            FileSinkFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(Encoder::new(file, self.compression)?);
+                writer.write_record(columns)?;
+                TableWriter::Csv(Box::new(writer))
+            }
+            FileSinkFormat::Parquet => {
+                let compression = match self.compression {
+                    FileCompression::None => Compression::UNCOMPRESSED,
+                    FileCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+                    FileCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+                };
+                let properties = WriterProperties::builder().set_compression(compression).build();
+                TableWriter::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(properties))?)
+            }
+        };
+
+        Ok(OpenFile { writer, columns: columns.to_vec(), schema, rows: 0 })
+    }
+
+    fn is_full(&self, file: &OpenFile) -> bool {
+        self.rotation.max_rows.is_some_and(|max| file.rows >= max)
+            || self.rotation.max_bytes.is_some_and(|max| file.bytes() >= max)
+    }
+
+    /// Returns how many rows can still be written to the file before rotating by row count.
+    fn remaining_rows(&self, file: &OpenFile) -> usize {
+        self.rotation.max_rows.map_or(usize::MAX, |max| max.saturating_sub(file.rows))
+    }
+
+    fn write_rows(&self, file: &mut OpenFile, rows: &[Vec<CValue>]) -> Result<(), WriteFileError> {
+        match &mut file.writer {
+            TableWriter::JsonLines(encoder) => {
+                for row in rows {
+                    // Written by hand instead of as a serde_json Map to keep the order of the columns.
+                    let fields: Vec<String> = file.columns
+                        .iter()
+                        .zip(row)
+                        .map(|(column, value)| format!("{}:{}", Value::from(column.as_str()), cvalue_to_json(value)))
+                        .collect();
+                    writeln!(encoder, "{{{}}}", fields.join(","))?;
+                }
+            }
+            TableWriter::Csv(writer) => {
+                for row in rows {
+                    writer.write_record(row.iter().map(cvalue_to_csv))?;
+                }
+            }
+            TableWriter::Parquet(writer) => {
+                let batch = rows_to_record_batch(file.schema.clone(), &file.columns, rows)?;
+                writer.write(&batch)?;
+            }
+        }
+        file.rows += rows.len();
+        Ok(())
+    }
+
+    fn write_batch(&self, schema: &str, table_name: &str, columns: &[String], rows: &[Vec<CValue>]) -> Result<(), WriteFileError> {
+        let key = format!("{}.{}", schema, table_name);
+        let mut files = self.files.lock().unwrap();
+
+        // CSV headers and Parquet schemas can not change within a file, Parquet files whose types
+        // can not hold the batch are rotated too.
+        let columns_changed = files.get(&key).is_some_and(|f| f.columns != columns);
+        let types_changed = self.format == FileSinkFormat::Parquet && files.get(&key).is_some_and(|f| !rows_fit(&f.schema, rows));
+        if (columns_changed && self.format != FileSinkFormat::JsonLines) || types_changed {
+            files.remove(&key).unwrap().close()?;
+        }
+
+        // Text files are checked row by row, Parquet files after every written slice since their
+        // size is only known once rows are encoded.
+        let step = if self.format == FileSinkFormat::Parquet { usize::MAX } else { 1 };
+        let mut written = 0;
+        while written < rows.len() {
+            if files.get(&key).is_some_and(|f| self.is_full(f)) {
+                files.remove(&key).unwrap().close()?;
+            }
+            if !files.contains_key(&key) {
+                let batch_schema = Arc::new(infer_schema(columns, rows));
+                files.insert(key.clone(), self.open_file(&key, columns, batch_schema)?);
+            }
+
+            let file = files.get_mut(&key).unwrap();
+            let count = step.min(self.remaining_rows(file)).min(rows.len() - written);
+            self.write_rows(file, &rows[written..written + count])?;
+            written += count;
+        }
+        Ok(())
+    }
+
+    /// Closes every file, writing compression and Parquet footers.
+    pub fn finish(&self) -> Result<(), WriteFileError> {
+        let mut files = self.files.lock().unwrap();
+        for (_, file) in files.drain() {
+            file.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        // A poisoned lock means a write panicked, its files are left unfinished.
+        let Ok(files) = self.files.get_mut() else { return };
+        for (key, file) in files.drain() {
+            if let Err(e) = file.close() {
+                eprintln!("Could not close the file of {}: {}", key, e);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FileSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileSink({}, {:?})", self.directory, self.format)
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    async fn create_table(&self, _schema: &str, _table_name: &str, _columns: &[(String, String)]) -> Result<(), SinkError> {
+        // Files are created with the columns of their first batch.
+        Ok(())
+    }
+
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        // The columns of the open file, a batch with others starts a new part for CSV and Parquet.
+        let key = format!("{}.{}", schema, table_name);
+        self.files.lock().unwrap().get(&key).map(|file| file.columns.clone()).unwrap_or_default()
+    }
+
+    async fn add_column(&self, _schema: &str, _table_name: &str, _column: &str, _dtype: &str) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        Ok(self.write_batch(schema, table_name, columns, &buffer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use crate::experiment::data::CValue;
+    use crate::sink::file::driver::{FileCompression, FileSink, FileSinkFormat, Rotation};
+    use crate::source::source::Sink;
+
+    fn rows(n: i64) -> (Vec<String>, Vec<Vec<CValue>>) {
+        let columns = vec!["id".to_string(), "name".to_string(), "attributes".to_string()];
+        let rows = (0..n)
+            .map(|i| vec![
+                CValue::I64(i),
+                if i % 2 == 0 { CValue::String(format!("a,\"{}\"", i)) } else { CValue::None },
+                CValue::Object(HashMap::from([("tags".to_string(), CValue::VecDyn(vec![CValue::I64(i), CValue::String("x".to_string())]))])),
+            ])
+            .collect();
+        (columns, rows)
+    }
+
+    fn directory(name: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("cdctest_file_sink_{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        directory.to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_rotation_and_gzip() {
+        let directory = directory("jsonl");
+        let sink = FileSink::new(&directory, FileSinkFormat::JsonLines, FileCompression::Gzip, Rotation { max_rows: Some(4), max_bytes: None });
+        let (columns, rows) = rows(10);
+        sink.send_batch("doc", "data", &columns, rows).await.unwrap();
+        sink.finish().unwrap();
+
+        let mut lines = vec![];
+        for part in 0..3 {
+            let file = fs::File::open(format!("{}/doc.data.{:05}.jsonl.gz", directory, part)).unwrap();
+            let mut content = String::new();
+            GzDecoder::new(file).read_to_string(&mut content).unwrap();
+            lines.extend(content.lines().map(String::from).collect::<Vec<String>>());
+        }
+
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], r#"{"id":0,"name":"a,\"0\"","attributes":{"tags":[0,"x"]}}"#);
+        assert_eq!(lines[1], r#"{"id":1,"name":null,"attributes":{"tags":[1,"x"]}}"#);
+    }
+
+    #[tokio::test]
+    async fn test_csv_new_part_on_column_change() {
+        let directory = directory("csv");
+        let sink = FileSink::new(&directory, FileSinkFormat::Csv, FileCompression::None, Rotation::default());
+        let (columns, rows) = rows(2);
+        sink.send_batch("doc", "data", &columns, rows).await.unwrap();
+        sink.send_batch("doc", "data", &["id".to_string()], vec![vec![CValue::I32(5)]]).await.unwrap();
+        sink.finish().unwrap();
+
+        let first = fs::read_to_string(format!("{}/doc.data.00000.csv", directory)).unwrap();
+        assert_eq!(first, "id,name,attributes\n0,\"a,\"\"0\"\"\",\"{\"\"tags\"\":[0,\"\"x\"\"]}\"\n1,,\"{\"\"tags\"\":[1,\"\"x\"\"]}\"\n");
+        let second = fs::read_to_string(format!("{}/doc.data.00001.csv", directory)).unwrap();
+        assert_eq!(second, "id\n5\n");
+    }
+
+    #[tokio::test]
+    async fn test_parquet_zstd() {
+        let directory = directory("parquet");
+        let sink = FileSink::new(&directory, FileSinkFormat::Parquet, FileCompression::Zstd, Rotation { max_rows: Some(6), max_bytes: None });
+        let (columns, rows) = rows(10);
+        sink.send_batch("doc", "data", &columns, rows).await.unwrap();
+        sink.finish().unwrap();
+
+        let counts: Vec<i64> = (0..2)
+            .map(|part| {
+                let file = fs::File::open(format!("{}/doc.data.{:05}.parquet", directory, part)).unwrap();
+                SerializedFileReader::new(file).unwrap().metadata().file_metadata().num_rows()
+            })
+            .collect();
+        assert_eq!(counts, vec![6, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_parquet_new_part_on_type_change() {
+        let directory = directory("parquet_types");
+        let sink = FileSink::new(&directory, FileSinkFormat::Parquet, FileCompression::None, Rotation::default());
+        let columns = vec!["n".to_string()];
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I64(1)]]).await.unwrap();
+        // Widened to the file's type.
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I32(2)]]).await.unwrap();
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::String("3".to_string())]]).await.unwrap();
+        // The files are closed when the sink is dropped.
+        drop(sink);
+
+        let counts: Vec<i64> = (0..2)
+            .map(|part| {
+                let file = fs::File::open(format!("{}/doc.data.{:05}.parquet", directory, part)).unwrap();
+                SerializedFileReader::new(file).unwrap().metadata().file_metadata().num_rows()
+            })
+            .collect();
+        assert_eq!(counts, vec![2, 1]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
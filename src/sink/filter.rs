@@ -0,0 +1,61 @@
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::experiment::data::CValue;
+use crate::experiment::predicate::Predicate;
+use crate::source::filter::{get_filter, Filters};
+use crate::source::source::{Sink, SinkError, SinkWrapper};
+
+/// Sink that drops the rows not matching their table's predicate before sending batches to `sink`.
+///
+/// For sources that can not push a filter down, e.g. files, Redis or InfluxDB. Filters are looked up
+/// by `<schema>.<table>` and then by `<table>`, with the names the source migrates to.
+pub struct FilteredSink<S: Sink> {
+    pub(crate) sink: S,
+    pub(crate) filters: Filters,
+}
+
+impl<S: Sink> FilteredSink<S> {
+    pub fn new(sink: S) -> Self {
+        Self { sink, filters: Filters::new() }
+    }
+
+    /// Only sends the rows of `table` matching `predicate`. It is checked against every row, so it
+    /// can not have raw SQL.
+    pub fn with_filter(mut self, table: &str, predicate: Predicate) -> Self {
+        assert!(predicate.is_evaluable(), "Filter of {} has raw SQL, it can not be checked against the rows", table);
+        self.filters.insert(table.to_string(), predicate);
+        self
+    }
+}
+
+impl<S: Sink> fmt::Debug for FilteredSink<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FilteredSink({:?}, {} filters)", self.sink, self.filters.len())
+    }
+}
+
+#[async_trait]
+impl<S: Sink> SinkWrapper for FilteredSink<S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        &self.sink
+    }
+
+    fn passes_through(&self, schema: &str, table_name: &str) -> bool {
+        get_filter(&self.filters, schema, table_name).is_none()
+    }
+
+    async fn wrap_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let buffer: Vec<Vec<CValue>> = match get_filter(&self.filters, schema, table_name) {
+            Some(predicate) => buffer.into_iter().filter(|row| predicate.matches(columns, row)).collect(),
+            None => buffer
+        };
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        self.sink.send_batch(schema, table_name, columns, buffer).await
+    }
+}
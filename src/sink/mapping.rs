@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::experiment::data::CValue;
+use crate::source::mongodb::driver::{StringRow};
+use crate::source::source::{Sink, SinkError};
+use crate::utils::lookup_table;
+
+/// Where a source table goes and how its columns are renamed.
+#[derive(Debug, Clone, Default)]
+pub struct TableMapping {
+    /// Target schema, the one the source migrates to (usually `doc`) if None.
+    pub schema: Option<String>,
+    /// Target table, the source's name if None.
+    pub table: Option<String>,
+    /// (source, target) column pairs applied in order. Paths into objects are dotted, so
+    /// (`obj.seven.eight`, `eight`) flattens a nested field and (`lat`, `location.lat`) nests one.
+    pub columns: Vec<(String, String)>,
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('.').collect()
+}
+
+fn position(columns: &[String], name: &str) -> Option<usize> {
+    columns.iter().position(|c| c == name)
+}
+
+fn is_empty_object(value: &CValue) -> bool {
+    matches!(value, CValue::Object(object) if object.is_empty())
+}
+
+/// Takes the value at `path` inside `value`, leaving None (or removing the key of its object).
+/// Objects left empty are removed too.
+fn take_path(value: &mut CValue, path: &[&str]) -> Option<CValue> {
+    match path {
+        [] => Some(std::mem::replace(value, CValue::None)),
+        [key] => match value {
+            CValue::Object(object) => object.remove(*key),
+            _ => None
+        },
+        [key, rest @ ..] => match value {
+            CValue::Object(object) => {
+                let child = object.get_mut(*key)?;
+                let taken = take_path(child, rest);
+                if taken.is_some() && is_empty_object(child) {
+                    object.remove(*key);
+                }
+                taken
+            }
+            _ => None
+        }
+    }
+}
+
+/// Puts `new_value` at `path` inside `value`, creating the objects on the way. Values that are not
+/// objects are not overwritten to nest into them, that is an error.
+fn put_path(value: &mut CValue, path: &[&str], new_value: CValue) -> Result<(), SinkError> {
+    let Some((key, rest)) = path.split_first() else {
+        *value = new_value;
+        return Ok(());
+    };
+
+    if matches!(value, CValue::None) {
+        *value = CValue::Object(HashMap::new());
+    }
+    match value {
+        CValue::Object(object) => put_path(object.entry(key.to_string()).or_insert(CValue::None), rest, new_value),
+        _ => Err(SinkError::new(format!("Could not nest {} into {:?}, it is not an object", path.join("."), value)))
+    }
+}
+
+/// Applies column mappings to a batch, every row of a batch has the same columns so a mapping
+/// is applied if the batch has its source column. Nulls are not nested, the target stays null.
+pub fn apply_column_mappings(mut columns: Vec<String>, mut rows: Vec<Vec<CValue>>, mappings: &Vec<(String, String)>) -> Result<(Vec<String>, Vec<Vec<CValue>>), SinkError> {
+    for (source, target) in mappings {
+        let source_path = split_path(source);
+        let target_path = split_path(target);
+        let Some(source_i) = position(&columns, source_path[0]) else { continue };
+
+        // Top level renames keep the column where it was.
+        if source_path.len() == 1 && target_path.len() == 1 && position(&columns, target_path[0]).is_none() {
+            columns[source_i] = target.clone();
+            continue;
+        }
+
+        let values: Vec<Option<CValue>> = rows
+            .iter_mut()
+            .map(|row| {
+                let value = take_path(&mut row[source_i], &source_path[1..]);
+                if value.is_some() && is_empty_object(&row[source_i]) {
+                    row[source_i] = CValue::None;
+                }
+                value
+            })
+            .collect();
+        if source_path.len() == 1 {
+            columns.remove(source_i);
+            rows.iter_mut().for_each(|row| { row.remove(source_i); });
+        }
+
+        let target_i = match position(&columns, target_path[0]) {
+            Some(i) => i,
+            None => {
+                columns.push(target_path[0].to_string());
+                rows.iter_mut().for_each(|row| row.push(CValue::None));
+                columns.len() - 1
+            }
+        };
+        for (row, value) in rows.iter_mut().zip(values) {
+            match value {
+                None | Some(CValue::None) | Some(CValue::Unknown) => {}
+                Some(value) => put_path(&mut row[target_i], &target_path[1..], value)?
+            }
+        }
+    }
+    Ok((columns, rows))
+}
+
+/// Sink that renames tables, moves them across schemas and renames, flattens or nests their
+/// columns before sending batches to `sink`.
+///
+/// Mappings are looked up by `<schema>.<table>` and then by `<table>`, with the names the source
+/// migrates to, e.g. `doc.users` or `users` for MongoDB's `users` collection.
+pub struct MappedSink<S: Sink> {
+    pub(crate) sink: S,
+    pub(crate) tables: HashMap<String, TableMapping>,
+}
+
+impl<S: Sink> MappedSink<S> {
+    pub fn new(sink: S) -> Self {
+        Self { sink, tables: HashMap::new() }
+    }
+
+    pub fn with_table(mut self, source_table: &str, mapping: TableMapping) -> Self {
+        self.tables.insert(source_table.to_string(), mapping);
+        self
+    }
+
+    fn get_mapping(&self, schema: &str, table_name: &str) -> Option<&TableMapping> {
+        lookup_table(&self.tables, schema, table_name)
+    }
+
+    /// Returns the target (schema, table) of a source table.
+    pub fn target(&self, schema: &str, table_name: &str) -> (String, String) {
+        match self.get_mapping(schema, table_name) {
+            Some(mapping) => (
+                mapping.schema.clone().unwrap_or(schema.to_string()),
+                mapping.table.clone().unwrap_or(table_name.to_string()),
+            ),
+            None => (schema.to_string(), table_name.to_string())
+        }
+    }
+
+    fn target_column(&self, schema: &str, table_name: &str, column: &str) -> String {
+        self.get_mapping(schema, table_name)
+            .and_then(|mapping| mapping.columns.iter().find(|(source, target)| source == column && !target.contains('.')))
+            .map(|(_, target)| target.clone())
+            .unwrap_or(column.to_string())
+    }
+}
+
+impl<S: Sink> fmt::Debug for MappedSink<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MappedSink({:?}, {} tables)", self.sink, self.tables.len())
+    }
+}
+
+#[async_trait]
+impl<S: Sink> Sink for MappedSink<S> {
+    async fn create_table(&self, schema: &str, table_name: &str, columns: &[(String, String)]) -> Result<(), SinkError> {
+        let (target_schema, target_table) = self.target(schema, table_name);
+        let mut definitions = columns.to_vec();
+
+        // Renamed and nested columns keep their type, flattened fields have none and are added when
+        // they arrive (CrateDB's dynamic columns or `SchemaEvolution`).
+        for (source, target) in self.get_mapping(schema, table_name).map(|m| m.columns.clone()).unwrap_or_default() {
+            let source_path = split_path(&source);
+            let target_path = split_path(&target);
+            if source_path.len() > 1 {
+                continue;
+            }
+            let Some(i) = definitions.iter().position(|(name, _)| name == &source) else { continue };
+
+            if target_path.len() == 1 {
+                definitions[i].0 = target;
+            } else {
+                definitions.remove(i);
+                if !definitions.iter().any(|(name, _)| name == target_path[0]) {
+                    definitions.push((target_path[0].to_string(), "OBJECT(DYNAMIC)".to_string()));
+                }
+            }
+        }
+        self.sink.create_table(&target_schema, &target_table, &definitions).await
+    }
+
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        let (target_schema, target_table) = self.target(schema, table_name);
+        self.sink.list_columns(&target_schema, &target_table).await
+    }
+
+    async fn add_column(&self, schema: &str, table_name: &str, column: &str, dtype: &str) -> Result<(), SinkError> {
+        let (target_schema, target_table) = self.target(schema, table_name);
+        let column = self.target_column(schema, table_name, column);
+        self.sink.add_column(&target_schema, &target_table, &column, dtype).await
+    }
+
+    async fn send_batch_http(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<StringRow>>) -> Result<(), SinkError> {
+        // StringRows can not be nested or flattened, only tables and top level columns are renamed.
+        let (target_schema, target_table) = self.target(schema, table_name);
+        let columns: Vec<String> = columns.iter().map(|c| self.target_column(schema, table_name, c)).collect();
+        self.sink.send_batch_http(&target_schema, &target_table, &columns, buffer).await
+    }
+
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let (target_schema, target_table) = self.target(schema, table_name);
+        let (columns, buffer) = match self.get_mapping(schema, table_name) {
+            Some(mapping) => apply_column_mappings(columns.to_vec(), buffer, &mapping.columns)?,
+            None => (columns.to_vec(), buffer)
+        };
+        self.sink.send_batch(&target_schema, &target_table, &columns, buffer).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    
+
+    use crate::experiment::data::CValue;
+    use crate::sink::cratedb::driver::CrateDB;
+    use crate::sink::mapping::{apply_column_mappings, MappedSink, TableMapping};
+    use crate::testing::object;
+
+    #[test]
+    fn test_apply_column_mappings() {
+        let columns = vec!["id".to_string(), "obj".to_string(), "lat".to_string()];
+        let rows = vec![
+            vec![CValue::I64(1), object(vec![("seven", object(vec![("eight", CValue::I64(8))])), ("keep", CValue::Bool(true))]), CValue::Double64(1.5)],
+            vec![CValue::I64(2), CValue::None, CValue::None],
+        ];
+        let mappings = vec![
+            ("id".to_string(), "user_id".to_string()),
+            ("obj.seven.eight".to_string(), "eight".to_string()),
+            ("lat".to_string(), "location.lat".to_string()),
+            ("missing".to_string(), "other".to_string()),
+        ];
+
+        let (columns, rows) = apply_column_mappings(columns, rows, &mappings).unwrap();
+        assert_eq!(columns, vec!["user_id", "obj", "eight", "location"]);
+
+        assert!(matches!(rows[0][0], CValue::I64(1)));
+        // `seven` is empty once `eight` is flattened, so it is removed.
+        assert!(matches!(&rows[0][1], CValue::Object(o) if o.len() == 1 && o.contains_key("keep")));
+        assert!(matches!(rows[0][2], CValue::I64(8)));
+        assert!(matches!(&rows[0][3], CValue::Object(o) if matches!(o.get("lat"), Some(CValue::Double64(v)) if *v == 1.5)));
+
+        // Nulls are not nested.
+        assert!(matches!(rows[1][2], CValue::None));
+        assert!(matches!(rows[1][3], CValue::None));
+    }
+
+    #[test]
+    fn test_emptied_objects() {
+        let columns = vec!["obj".to_string(), "n".to_string()];
+        let rows = vec![vec![object(vec![("a", object(vec![("b", CValue::I32(1))]))]), CValue::I32(2)]];
+
+        let mappings = vec![("obj.a.b".to_string(), "b".to_string())];
+        let (columns, rows) = apply_column_mappings(columns, rows, &mappings).unwrap();
+        assert_eq!(columns, vec!["obj", "n", "b"]);
+        assert!(matches!(rows[0][..], [CValue::None, CValue::I32(2), CValue::I32(1)]));
+
+        // Nesting into a value that is not an object is an error.
+        let mappings = vec![("b".to_string(), "n.b".to_string())];
+        assert!(apply_column_mappings(columns, rows, &mappings).is_err());
+    }
+
+    #[test]
+    fn test_target() {
+        let sink = MappedSink::new(CrateDB::default())
+            .with_table("users", TableMapping { schema: Some("app".to_string()), ..Default::default() })
+            .with_table("doc.orders", TableMapping { table: Some("sales".to_string()), ..Default::default() });
+
+        assert_eq!(sink.target("doc", "users"), ("app".to_string(), "users".to_string()));
+        assert_eq!(sink.target("doc", "orders"), ("doc".to_string(), "sales".to_string()));
+        assert_eq!(sink.target("other", "orders"), ("other".to_string(), "orders".to_string()));
+    }
+}
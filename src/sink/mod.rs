@@ -1 +1,9 @@
-pub mod cratedb;
\ No newline at end of file
+pub mod cratedb;
+pub mod arrow;
+pub mod postgres;
+pub mod file;
+pub mod evolution;
+pub mod mapping;
+pub mod filter;
+pub mod enforce;
+pub mod transform;
\ No newline at end of file
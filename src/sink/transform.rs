@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::experiment::data::{CDataFrame, CValue, TransformError};
+use crate::source::source::{Sink, SinkError, SinkWrapper};
+use crate::utils::lookup_table;
+
+pub type Transform = Box<dyn Fn(CDataFrame) -> Result<CDataFrame, TransformError> + Send + Sync>;
+
+/// Sink that applies a `CDataFrame` transformation to every batch of a table before sending it to
+/// `sink`, e.g. `|df| Ok(df.rename("_id", "id")?.drop(vec!["__v"]))`. A transformation that
+/// fails, e.g. on a value that can not be cast, fails the batch.
+///
+/// Transformations are looked up by `<schema>.<table>` and then by `<table>`, other tables are sent
+/// as they are.
+pub struct TransformedSink<S: Sink> {
+    pub(crate) sink: S,
+    pub(crate) transforms: HashMap<String, Transform>,
+}
+
+impl<S: Sink> TransformedSink<S> {
+    pub fn new(sink: S) -> Self {
+        Self { sink, transforms: HashMap::new() }
+    }
+
+    pub fn with_transform<F>(mut self, table: &str, transform: F) -> Self
+    where
+        F: Fn(CDataFrame) -> Result<CDataFrame, TransformError> + Send + Sync + 'static,
+    {
+        self.transforms.insert(table.to_string(), Box::new(transform));
+        self
+    }
+
+    fn get_transform(&self, schema: &str, table_name: &str) -> Option<&Transform> {
+        lookup_table(&self.transforms, schema, table_name)
+    }
+}
+
+impl<S: Sink> fmt::Debug for TransformedSink<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TransformedSink({:?}, {} transforms)", self.sink, self.transforms.len())
+    }
+}
+
+#[async_trait]
+impl<S: Sink> SinkWrapper for TransformedSink<S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        &self.sink
+    }
+
+    fn passes_through(&self, schema: &str, table_name: &str) -> bool {
+        self.get_transform(schema, table_name).is_none()
+    }
+
+    async fn wrap_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let Some(transform) = self.get_transform(schema, table_name) else {
+            return self.sink.send_batch(schema, table_name, columns, buffer).await;
+        };
+
+        let (columns, buffer) = transform(CDataFrame::from_rows(columns, buffer))?.into_rows();
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        self.sink.send_batch(schema, table_name, &columns, buffer).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::experiment::data::{CDataFrame, CValue, CValueType};
+    use crate::experiment::predicate::Predicate;
+    use crate::testing::object;
+
+    fn dataframe() -> CDataFrame {
+        let columns = vec!["_id".to_string(), "user".to_string(), "tags".to_string(), "__v".to_string()];
+        let rows = vec![
+            vec![
+                CValue::String("1".to_string()),
+                object(vec![("name", CValue::String("a".to_string())), ("age", CValue::I32(30))]),
+                CValue::VecString(vec!["x".to_string(), "y".to_string()]),
+                CValue::I32(0),
+            ],
+            vec![
+                CValue::String("two".to_string()),
+                object(vec![("name", CValue::String("b".to_string()))]),
+                CValue::VecString(vec![]),
+                CValue::I32(0),
+            ],
+            vec![CValue::String("3".to_string()), CValue::None, CValue::None, CValue::I32(1)],
+        ];
+        CDataFrame::from_rows(&columns, rows)
+    }
+
+    #[test]
+    fn test_transformations() {
+        let dataframe = dataframe()
+            .filter(&Predicate::Ne("_id".to_string(), CValue::String("two".to_string())))
+            .rename("_id", "id").unwrap()
+            .drop(vec!["__v", "missing"])
+            .cast("id", CValueType::I64).unwrap()
+            .with_column("adult", |row| match row.get("user.age") {
+                CValue::I32(age) => CValue::Bool(age >= 18),
+                _ => CValue::None,
+            })
+            .flatten("user");
+
+        let (columns, rows) = dataframe.into_rows();
+        assert_eq!(columns, vec!["id", "user_age", "user_name", "tags", "adult"]);
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(rows[0][0], CValue::I64(1)));
+        assert!(matches!(rows[0][1], CValue::I32(30)));
+        assert!(matches!(&rows[0][2], CValue::String(s) if s == "a"));
+        assert!(matches!(rows[0][4], CValue::Bool(true)));
+        assert!(matches!(rows[1][0], CValue::I64(3)));
+        assert!(matches!(rows[1][1], CValue::None));
+    }
+
+    #[test]
+    fn test_transformation_errors() {
+        let error = dataframe().cast("_id", CValueType::I64).unwrap_err();
+        assert!(error.to_string().contains("Row 1 of column _id"), "{}", error);
+
+        let error = dataframe().rename("_id", "__v").unwrap_err();
+        assert!(error.to_string().contains("already exists"), "{}", error);
+        assert!(dataframe().rename("_id", "_id").is_ok());
+    }
+
+    #[test]
+    fn test_explode() {
+        let (columns, rows) = dataframe().explode("tags").into_rows();
+        assert_eq!(columns.len(), 4);
+        assert_eq!(rows.len(), 4);
+        assert!(matches!(&rows[0][2], CValue::String(s) if s == "x"));
+        assert!(matches!(&rows[1][2], CValue::String(s) if s == "y"));
+        assert!(matches!(&rows[1][0], CValue::String(s) if s == "1"));
+        assert!(matches!(rows[2][2], CValue::None));
+        assert!(matches!(&rows[3][0], CValue::String(s) if s == "3"));
+    }
+}
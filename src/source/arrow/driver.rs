@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::fs::{read_dir, File};
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::datatypes::{DataType, Date32Type, Date64Type, Field, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, TimeUnit, TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::FileReader;
+use arrow::util::display::array_value_to_string;
+use serde_json::Value;
+
+use crate::experiment::data::CValue;
+use crate::experiment::trans::{collapse_vec, json_to_cvalue};
+use crate::metadata::Metadata;
+use crate::sink::arrow::driver::JSON_EXTENSION;
+use crate::source::source::{MigrationError, Sink, Source};
+
+/// Source for Arrow IPC files (Feather v2), `path` is either a file or a directory where every
+/// `.arrow`, `.feather` and `.ipc` file is a table named after the file stem.
+pub struct ArrowIPCSource {
+    pub(crate) path: String,
+}
+
+const EXTENSIONS: [&str; 3] = ["arrow", "feather", "ipc"];
+
+/// Maps an Arrow data type to a CrateDB column type.
+pub fn arrow_type_to_cratedb(field: &Field) -> String {
+    let is_json = field.metadata().get("ARROW:extension:name").is_some_and(|n| n == JSON_EXTENSION);
+    match field.data_type() {
+        DataType::Boolean => "BOOLEAN".to_string(),
+        DataType::Int8 | DataType::Int16 | DataType::UInt8 => "SMALLINT".to_string(),
+        DataType::Int32 | DataType::UInt16 => "INTEGER".to_string(),
+        DataType::Int64 | DataType::UInt32 => "BIGINT".to_string(),
+        // Values above i64::MAX do not fit BIGINT.
+        DataType::UInt64 | DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => "NUMERIC".to_string(),
+        DataType::Float16 | DataType::Float32 => "REAL".to_string(),
+        DataType::Float64 => "DOUBLE PRECISION".to_string(),
+        DataType::Date32 | DataType::Date64 | DataType::Timestamp(_, _) => "TIMESTAMP WITH TIME ZONE".to_string(),
+        DataType::Utf8 | DataType::LargeUtf8 if is_json => "OBJECT(DYNAMIC)".to_string(),
+        DataType::List(item) | DataType::LargeList(item) | DataType::FixedSizeList(item, _) => format!("ARRAY({})", arrow_type_to_cratedb(item)),
+        DataType::Struct(fields) => {
+            let columns: Vec<String> = fields.iter().map(|f| format!(r#""{}" {}"#, f.name(), arrow_type_to_cratedb(f))).collect();
+            format!("OBJECT(DYNAMIC) AS ({})", columns.join(", "))
+        }
+        DataType::Map(_, _) => "OBJECT(DYNAMIC)".to_string(),
+        DataType::Dictionary(_, value) => arrow_type_to_cratedb(&Field::new(field.name(), value.as_ref().clone(), true)),
+        _ => "TEXT".to_string()
+    }
+}
+
+/// Converts the value at `row` of an Arrow array to a CValue.
+pub fn arrow_value_to_cvalue(array: &ArrayRef, field: &Field, row: usize) -> Result<CValue, ArrowError> {
+    if array.is_null(row) {
+        return Ok(CValue::None);
+    }
+
+    let is_json = field.metadata().get("ARROW:extension:name").is_some_and(|n| n == JSON_EXTENSION);
+    let json_or_string = |v: &str| match is_json {
+        true => serde_json::from_str::<Value>(v).map_or(CValue::String(v.to_string()), json_to_cvalue),
+        false => CValue::String(v.to_string())
+    };
+    let list_to_cvalue = |values: ArrayRef, item: &Field| -> Result<CValue, ArrowError> {
+        // Numeric lists without nulls keep their type, e.g. f32 embeddings stay VecF32.
+        if values.null_count() == 0 {
+            match values.data_type() {
+                DataType::Int32 => return Ok(CValue::VecI32(values.as_primitive::<Int32Type>().values().to_vec())),
+                DataType::Int64 => return Ok(CValue::VecI64(values.as_primitive::<Int64Type>().values().to_vec())),
+                DataType::Float32 => return Ok(CValue::VecF32(values.as_primitive::<Float32Type>().values().to_vec())),
+                DataType::Float64 => return Ok(CValue::VecF64(values.as_primitive::<Float64Type>().values().to_vec())),
+                _ => {}
+            }
+        }
+        let elements = (0..values.len()).map(|i| arrow_value_to_cvalue(&values, item, i)).collect::<Result<Vec<CValue>, ArrowError>>()?;
+        Ok(collapse_vec(elements))
+    };
+
+    let value = match field.data_type() {
+        DataType::Boolean => CValue::Bool(array.as_boolean().value(row)),
+        DataType::Int8 => CValue::I16(array.as_primitive::<Int8Type>().value(row) as i16),
+        DataType::Int16 => CValue::I16(array.as_primitive::<Int16Type>().value(row)),
+        DataType::UInt8 => CValue::I16(array.as_primitive::<UInt8Type>().value(row) as i16),
+        DataType::Int32 => CValue::I32(array.as_primitive::<Int32Type>().value(row)),
+        DataType::UInt16 => CValue::I32(array.as_primitive::<UInt16Type>().value(row) as i32),
+        DataType::Int64 => CValue::I64(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt32 => CValue::I64(array.as_primitive::<UInt32Type>().value(row) as i64),
+        DataType::UInt64 => CValue::Decimal(array.as_primitive::<UInt64Type>().value(row).to_string()),
+        DataType::Float32 => CValue::Double32(array.as_primitive::<Float32Type>().value(row)),
+        DataType::Float64 => CValue::Double64(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Date32 => CValue::Date(array.as_primitive::<Date32Type>().value(row)),
+        // Milliseconds, kept as a timestamp since they do not always fit in the days of a Date.
+        DataType::Date64 => CValue::Timestamp(array.as_primitive::<Date64Type>().value(row)),
+        DataType::Timestamp(unit, _) => {
+            let millis = match unit {
+                TimeUnit::Second => {
+                    let seconds = array.as_primitive::<TimestampSecondType>().value(row);
+                    seconds.checked_mul(1_000).ok_or_else(|| ArrowError::ComputeError(format!("The timestamp {}s of {} overflows in milliseconds", seconds, field.name())))?
+                }
+                TimeUnit::Millisecond => array.as_primitive::<TimestampMillisecondType>().value(row),
+                TimeUnit::Microsecond => array.as_primitive::<TimestampMicrosecondType>().value(row) / 1_000,
+                TimeUnit::Nanosecond => array.as_primitive::<TimestampNanosecondType>().value(row) / 1_000_000,
+            };
+            CValue::Timestamp(millis)
+        }
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => CValue::Decimal(array_value_to_string(array, row)?),
+        DataType::Utf8 => json_or_string(array.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => json_or_string(array.as_string::<i64>().value(row)),
+        DataType::List(item) => list_to_cvalue(array.as_list::<i32>().value(row), item)?,
+        DataType::LargeList(item) => list_to_cvalue(array.as_list::<i64>().value(row), item)?,
+        DataType::FixedSizeList(item, _) => list_to_cvalue(array.as_fixed_size_list().value(row), item)?,
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            let object = fields
+                .iter()
+                .zip(array.columns())
+                .map(|(f, column)| Ok((f.name().clone(), arrow_value_to_cvalue(column, f, row)?)))
+                .collect::<Result<HashMap<String, CValue>, ArrowError>>()?;
+            CValue::Object(object)
+        }
+        DataType::Map(entry, _) => {
+            // Entries are a struct of the key and the value, keys are written as their string.
+            let entries = array.as_map().value(row);
+            let DataType::Struct(fields) = entry.data_type() else {
+                return Err(ArrowError::SchemaError(format!("The entries of map {} are not a struct", field.name())));
+            };
+            let object = (0..entries.len())
+                .map(|i| Ok((array_value_to_string(entries.column(0), i)?, arrow_value_to_cvalue(entries.column(1), &fields[1], i)?)))
+                .collect::<Result<HashMap<String, CValue>, ArrowError>>()?;
+            CValue::Object(object)
+        }
+        _ => {
+            // Dictionaries, binaries, durations... are kept as their string representation.
+            CValue::String(array_value_to_string(array, row)?)
+        }
+    };
+    Ok(value)
+}
+
+impl ArrowIPCSource {
+    fn get_files(&self) -> Result<Vec<PathBuf>, ArrowError> {
+        let path = Path::new(&self.path);
+        if path.is_file() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+
+        let mut files: Vec<PathBuf> = read_dir(path)
+            .map_err(|e| ArrowError::IoError(format!("Could not read directory {}", self.path), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| EXTENSIONS.contains(&e)))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Returns the file stem of a table, `<database>.<table>` for the files of `ArrowIPCSink` or
+    /// just `<table>`.
+    fn get_stem(&self, database: &str, table_name: &str) -> Result<Option<String>, ArrowError> {
+        for stem in [format!("{}.{}", database, table_name), table_name.to_string()] {
+            if (!database.is_empty() || stem == table_name) && self.get_file(&stem)?.is_some() {
+                return Ok(Some(stem));
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_file(&self, table_name: &str) -> Result<Option<PathBuf>, ArrowError> {
+        Ok(self.get_files()?
+            .into_iter()
+            .find(|p| p.file_stem().and_then(|s| s.to_str()) == Some(table_name)))
+    }
+
+    fn open_reader(&self, table_name: &str) -> Result<FileReader<File>, ArrowError> {
+        let path = self.get_file(table_name)?
+            .ok_or(ArrowError::InvalidArgumentError(format!("Table {} not found in {}", table_name, self.path)))?;
+        FileReader::try_new(File::open(path)?, None)
+    }
+
+    fn batch_to_rows(&self, batch: &RecordBatch) -> Result<Vec<Vec<CValue>>, ArrowError> {
+        (0..batch.num_rows()).map(|row| self.row_of_batch(batch, row)).collect()
+    }
+
+    fn row_of_batch(&self, batch: &RecordBatch, row: usize) -> Result<Vec<CValue>, ArrowError> {
+        batch.schema()
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(field, column)| arrow_value_to_cvalue(column, field, row))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Source for ArrowIPCSource {
+    type ErrorType = ArrowError;
+    type TableType = String;
+    /// A row of a record batch, by its index.
+    type RowType = (RecordBatch, usize);
+
+    /// Files written by `ArrowIPCSink` are named `<schema>.<table>`, their schemas are the databases.
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        let mut databases: Vec<String> = vec![];
+        for stem in self.get_files()?.iter().filter_map(|p| p.file_stem().and_then(|s| s.to_str())) {
+            if let Some((database, _)) = stem.split_once('.') {
+                if !databases.iter().any(|d| d == database) {
+                    databases.push(database.to_string());
+                }
+            }
+        }
+        Ok(databases)
+    }
+
+    /// Returns the tables of the `<database>.<table>` files, every file stem if `database` is empty.
+    async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        let prefix = format!("{}.", database);
+        Ok(self.get_files()?
+            .iter()
+            .filter_map(|p| p.file_stem().and_then(|s| s.to_str()))
+            .filter_map(|stem| match database.is_empty() {
+                true => Some(stem),
+                false => stem.strip_prefix(&prefix)
+            })
+            .map(String::from)
+            .collect())
+    }
+
+    /// Returns the file stem of the table.
+    async fn get_table(&self, database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        self.get_stem(database, table_name)?
+            .ok_or(ArrowError::InvalidArgumentError(format!("Table {} not found in {}", table_name, self.path)))
+    }
+
+    async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let mut count: i64 = 0;
+        for batch in self.open_reader(&self.get_table(database, table_name).await?)? {
+            count += batch?.num_rows() as i64;
+        }
+        Ok(count)
+    }
+
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        let reader = self.open_reader(table).map_err(MigrationError::Source)?;
+        metadata.print_step(format!("Starting migrating table {} to CrateDB {:?}", table, cratedb).as_str());
+        let mut total_documents_sent = 0;
+
+        let file_schema = reader.schema();
+        let selected: Vec<usize> = file_schema.fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| !ignored_columns.contains(&f.name().as_str()))
+            .map(|(i, _)| i)
+            .collect();
+        let file_schema = file_schema.project(&selected).map_err(MigrationError::Source)?;
+
+        let column_definitions: Vec<(String, String)> = file_schema.fields()
+            .iter()
+            .map(|f| (f.name().clone(), arrow_type_to_cratedb(f)))
+            .collect();
+        let columns: Vec<String> = column_definitions.iter().map(|(name, _)| name.clone()).collect();
+        // `<schema>.<table>` files are migrated to `<table>`, not `<schema>.<schema>.<table>`.
+        let table = table.strip_prefix(&format!("{}.", schema)).unwrap_or(table);
+        cratedb.create_table(schema, table, &column_definitions).await?;
+        metadata.print_step("Created table from Arrow schema");
+
+        for batch in reader {
+            let buffer = batch
+                .and_then(|b| b.project(&selected))
+                .and_then(|b| self.batch_to_rows(&b))
+                .map_err(MigrationError::Source)?;
+            let documents_in_batch = buffer.len();
+            cratedb.send_batch(schema, table, &columns, buffer).await?;
+            total_documents_sent += documents_in_batch;
+            metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
+        }
+
+        metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
+    }
+
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
+        let (batch, index) = row;
+        self.row_of_batch(&batch, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    use std::sync::Arc;
+
+    use arrow::array::{ArrayRef, Decimal128Array, Int32Builder, LargeStringArray, MapBuilder, StringBuilder, TimestampSecondArray, UInt64Array};
+    use arrow::datatypes::{DataType, Field};
+
+    use crate::experiment::data::CValue;
+    use crate::sink::arrow::driver::{ArrowIPCSink, JSON_EXTENSION};
+    use crate::source::arrow::driver::{arrow_type_to_cratedb, arrow_value_to_cvalue, ArrowIPCSource};
+    use crate::source::source::{Sink, Source};
+
+    #[tokio::test]
+    async fn test_arrow_round_trip() {
+        let directory = temp_dir().join("cdctest_arrow_round_trip");
+        let _ = remove_dir_all(&directory);
+        let sink = ArrowIPCSink::new(directory.to_str().unwrap());
+
+        let columns = vec!["id".to_string(), "name".to_string(), "vector".to_string(), "obj".to_string()];
+        let rows = vec![
+            vec![CValue::I32(1), CValue::String("a".to_string()), CValue::VecF32(vec![0.5, 1.5]), CValue::Object(HashMap::from([("k".to_string(), CValue::I64(1))]))],
+            vec![CValue::I32(2), CValue::None, CValue::VecF32(vec![]), CValue::None],
+        ];
+        sink.send_batch("doc", "data", &columns, rows).await.unwrap();
+        sink.finish().unwrap();
+
+        let source = ArrowIPCSource { path: directory.to_str().unwrap().to_string() };
+        assert_eq!(source.list_tables("doc").await.unwrap(), vec!["data"]);
+        assert_eq!(source.list_tables("").await.unwrap(), vec!["doc.data"]);
+        assert_eq!(source.count("doc", "data").await.unwrap(), 2);
+
+        let batch = source.open_reader("doc.data").unwrap().next().unwrap().unwrap();
+        let read = source.batch_to_rows(&batch).unwrap();
+        assert!(matches!(read[0][0], CValue::I32(1)));
+        assert!(matches!(&read[0][1], CValue::String(s) if s == "a"));
+        assert!(matches!(&read[0][2], CValue::VecF32(v) if v == &vec![0.5, 1.5]));
+        assert!(matches!(&read[1][2], CValue::VecF32(v) if v.is_empty()));
+        assert!(matches!(&read[0][3], CValue::Object(o) if matches!(o.get("k"), Some(CValue::I64(1)))));
+        assert!(matches!(read[1][1], CValue::None));
+        assert!(matches!(read[1][3], CValue::None));
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_arrow_types() {
+        let json = Field::new("payload", DataType::LargeUtf8, true)
+            .with_metadata(HashMap::from([("ARROW:extension:name".to_string(), JSON_EXTENSION.to_string())]));
+        let mut map = MapBuilder::new(None, StringBuilder::new(), Int32Builder::new());
+        map.keys().append_value("a");
+        map.values().append_value(1);
+        map.append(true).unwrap();
+        let columns: Vec<(Field, ArrayRef)> = vec![
+            (Field::new("big", DataType::UInt64, true), Arc::new(UInt64Array::from(vec![u64::MAX]))),
+            (Field::new("price", DataType::Decimal128(10, 2), true), Arc::new(Decimal128Array::from(vec![12345]).with_precision_and_scale(10, 2).unwrap())),
+            (json, Arc::new(LargeStringArray::from(vec![r#"{"k": 1}"#]))),
+        ];
+        let map: ArrayRef = Arc::new(map.finish());
+        let map_field = Field::new("tags", map.data_type().clone(), true);
+
+        let ddl: Vec<String> = columns.iter().map(|(f, _)| arrow_type_to_cratedb(f)).collect();
+        assert_eq!(ddl, vec!["NUMERIC", "NUMERIC", "OBJECT(DYNAMIC)"]);
+        assert_eq!(arrow_value_to_cvalue(&columns[0].1, &columns[0].0, 0).unwrap(), CValue::Decimal(u64::MAX.to_string()));
+        assert_eq!(arrow_value_to_cvalue(&columns[1].1, &columns[1].0, 0).unwrap(), CValue::Decimal("123.45".to_string()));
+        assert_eq!(arrow_value_to_cvalue(&columns[2].1, &columns[2].0, 0).unwrap(), CValue::Object(HashMap::from([("k".to_string(), CValue::I64(1))])));
+        assert_eq!(arrow_value_to_cvalue(&map, &map_field, 0).unwrap(), CValue::Object(HashMap::from([("a".to_string(), CValue::I32(1))])));
+
+        // Seconds that overflow in milliseconds are an error, not a wrapped timestamp.
+        let seconds: ArrayRef = Arc::new(TimestampSecondArray::from(vec![i64::MAX / 10]));
+        let field = Field::new("time", seconds.data_type().clone(), true);
+        assert!(arrow_value_to_cvalue(&seconds, &field, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_arrow_sink_types() {
+        let directory = temp_dir().join("cdctest_arrow_sink_types");
+        let _ = remove_dir_all(&directory);
+        let sink = ArrowIPCSink::new(directory.to_str().unwrap());
+        let columns = vec!["n".to_string()];
+
+        // Integers are widened to the type of the column, other values are an error.
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I32(1)], vec![CValue::I64(2)]]).await.unwrap();
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I16(3)]]).await.unwrap();
+        assert!(sink.send_batch("doc", "data", &columns, vec![vec![CValue::String("4".to_string())]]).await.is_err());
+        assert!(sink.send_batch("doc", "data", &columns, vec![vec![CValue::Double64(5.5)]]).await.is_err());
+        // The file is finished when the sink is dropped.
+        drop(sink);
+
+        let source = ArrowIPCSource { path: directory.to_str().unwrap().to_string() };
+        let batch = source.open_reader("doc.data").unwrap().next().unwrap().unwrap();
+        let read: Vec<CValue> = source.batch_to_rows(&batch).unwrap().into_iter().flatten().collect();
+        assert!(matches!(read[..], [CValue::I64(1), CValue::I64(2)]));
+        assert_eq!(source.count("", "doc.data").await.unwrap(), 3);
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_arrow_sink_schema_changes() {
+        let directory = temp_dir().join("cdctest_arrow_sink_schema_changes");
+        let _ = remove_dir_all(&directory);
+        let sink = ArrowIPCSink::new(directory.to_str().unwrap());
+
+        // `note` only has nulls in the first batch and `extra` is only in the second one.
+        sink.send_batch("doc", "data", &["id".to_string(), "note".to_string()], vec![vec![CValue::I32(1), CValue::None]]).await.unwrap();
+        assert_eq!(sink.list_columns("doc", "data").await, vec!["id", "note"]);
+        let columns = vec!["id".to_string(), "note".to_string(), "extra".to_string()];
+        sink.send_batch("doc", "data", &columns, vec![vec![CValue::I32(2), CValue::String("b".to_string()), CValue::I64(3)]]).await.unwrap();
+        sink.finish().unwrap();
+
+        let source = ArrowIPCSource { path: directory.to_str().unwrap().to_string() };
+        let batches: Vec<_> = source.open_reader("doc.data").unwrap().collect::<Result<_, _>>().unwrap();
+        let schema = batches[0].schema();
+        assert_eq!(schema.field_with_name("note").unwrap().data_type(), &DataType::Utf8);
+        assert_eq!(schema.field_with_name("extra").unwrap().data_type(), &DataType::Int64);
+        let read: Vec<Vec<CValue>> = batches.iter().flat_map(|batch| source.batch_to_rows(batch).unwrap()).collect();
+        assert!(matches!(&read[0][..], [CValue::I32(1), CValue::None, CValue::None]));
+        assert!(matches!(&read[1][..], [CValue::I32(2), CValue::String(s), CValue::I64(3)] if s == "b"));
+        remove_dir_all(&directory).unwrap();
+    }
+}
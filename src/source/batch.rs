@@ -0,0 +1,98 @@
+use crate::experiment::data::{CValue, CValueType};
+use crate::sink::cratedb::driver::cvalue_type_to_cratedb;
+
+/// Batch for sources whose rows do not share the same columns, e.g. InfluxDB points or Redis keys.
+///
+/// `columns` only grows, rows are normalized to it with None for missing columns. When a row brings
+/// a new column the pending rows are handed back to be sent, so every batch has the same columns and
+/// the new one can be added before it is sent, by CrateDB as a dynamic column or by `SchemaEvolution`.
+pub struct DynamicBatch {
+    pub columns: Vec<String>,
+    rows: Vec<Vec<CValue>>,
+    batch_size: usize,
+}
+
+impl DynamicBatch {
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            columns: vec![],
+            rows: vec![],
+            batch_size,
+        }
+    }
+
+    /// Adds a row, returns the (columns, rows) that have to be sent before it if the batch is full
+    /// or the row has new columns.
+    pub fn push(&mut self, row: Vec<(String, CValue)>) -> Option<(Vec<String>, Vec<Vec<CValue>>)> {
+        let has_new_columns = row.iter().any(|(name, _)| !self.columns.contains(name));
+
+        let flushed = if !self.rows.is_empty() && (has_new_columns || self.rows.len() >= self.batch_size) {
+            Some((self.columns.clone(), std::mem::take(&mut self.rows)))
+        } else {
+            None
+        };
+
+        for (name, _) in &row {
+            if !self.columns.contains(name) {
+                self.columns.push(name.clone());
+            }
+        }
+
+        let mut normalized_row = vec![CValue::None; self.columns.len()];
+        for (name, value) in row {
+            let i = self.columns.iter().position(|c| c == &name).unwrap();
+            normalized_row[i] = value;
+        }
+        self.rows.push(normalized_row);
+
+        flushed
+    }
+
+    /// Returns the pending (columns, rows), if any.
+    pub fn finish(self) -> Option<(Vec<String>, Vec<Vec<CValue>>)> {
+        if self.rows.is_empty() {
+            return None;
+        }
+        Some((self.columns, self.rows))
+    }
+}
+
+/// Returns the (column, DDL) definitions of a batch, the type of a column is the widest type of its
+/// values over every row, or the first one if they do not widen. Columns that are only None or have
+/// no CrateDB type are left out, they are added when a value is inserted.
+pub fn column_definitions(columns: &[String], rows: &[Vec<CValue>]) -> Vec<(String, String)> {
+    columns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| {
+            let dtype = rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|value| value.get_dtype())
+                .filter(|dtype| !matches!(dtype, CValueType::None | CValueType::Unknown))
+                .reduce(|widest, dtype| widest.widen(dtype).unwrap_or(widest))?;
+            Some((name.clone(), cvalue_type_to_cratedb(&dtype)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::experiment::data::CValue;
+    use crate::source::batch::column_definitions;
+
+    #[test]
+    fn test_column_definitions() {
+        let columns = vec!["time".to_string(), "value".to_string(), "note".to_string()];
+        let rows = vec![
+            vec![CValue::Timestamp(0), CValue::I16(1), CValue::None],
+            vec![CValue::Timestamp(1), CValue::I32(100_000), CValue::None],
+        ];
+
+        // Types come from every row, not the first one; columns without values are left out.
+        assert_eq!(column_definitions(&columns, &rows), vec![
+            ("time".to_string(), "TIMESTAMP WITH TIME ZONE".to_string()),
+            ("value".to_string(), "INTEGER".to_string()),
+        ]);
+    }
+}
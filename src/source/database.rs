@@ -0,0 +1,250 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use glob::{Pattern, PatternError};
+
+use crate::experiment::data::CValue;
+use crate::metadata::Metadata;
+use crate::source::mongodb::driver::{StringRow};
+use crate::source::source::{Sink, SinkError, SinkWrapper, Source};
+
+/// Options of a whole-database migration, see `migrate_database`.
+#[derive(Debug, Clone)]
+pub struct DatabaseMigration {
+    /// Globs of the tables to migrate, e.g. `orders_*`; every table if empty.
+    pub include: Vec<String>,
+    /// Globs of the tables to skip, checked after `include`.
+    pub exclude: Vec<String>,
+    /// Number of tables migrated at the same time.
+    pub workers: usize,
+    pub ignored_columns: Vec<String>,
+}
+
+impl Default for DatabaseMigration {
+    fn default() -> Self {
+        Self {
+            include: vec![],
+            exclude: vec![],
+            workers: 4,
+            ignored_columns: vec![],
+        }
+    }
+}
+
+impl DatabaseMigration {
+    /// Returns the tables matching `include` and not `exclude`, an error if a glob is invalid.
+    pub fn select_tables(&self, tables: Vec<String>) -> Result<Vec<String>, PatternError> {
+        let patterns = |globs: &Vec<String>| -> Result<Vec<Pattern>, PatternError> {
+            globs.iter().map(|g| Pattern::new(g)).collect()
+        };
+        let include = patterns(&self.include)?;
+        let exclude = patterns(&self.exclude)?;
+
+        Ok(tables
+            .into_iter()
+            .filter(|t| include.is_empty() || include.iter().any(|p| p.matches(t)))
+            .filter(|t| !exclude.iter().any(|p| p.matches(t)))
+            .collect())
+    }
+}
+
+/// Error of a database migration before its tables are migrated, listing them or selecting them.
+/// The errors of the tables are in their `TableSummary`.
+#[derive(Debug)]
+pub struct DatabaseMigrationError {
+    message: String,
+}
+
+impl fmt::Display for DatabaseMigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error migrating the database: {}", self.message)
+    }
+}
+
+impl From<PatternError> for DatabaseMigrationError {
+    fn from(e: PatternError) -> Self {
+        DatabaseMigrationError { message: format!("invalid glob pattern - {}", e) }
+    }
+}
+
+/// Outcome of one table of a database migration.
+#[derive(Debug, Clone)]
+pub struct TableSummary {
+    pub table: String,
+    /// Rows handed to the sink.
+    pub rows: usize,
+    pub elapsed: Duration,
+    /// Why the migration failed, None if it succeeded.
+    pub error: Option<String>,
+}
+
+pub fn print_summary(summaries: &Vec<TableSummary>) {
+    println!("\n{:<40} {:>12} {:>12}  status", "table", "rows", "elapsed");
+    for summary in summaries {
+        println!("{:<40} {:>12} {:>12.2?}  {}",
+                 summary.table,
+                 summary.rows,
+                 summary.elapsed,
+                 summary.error.as_deref().unwrap_or("ok"));
+    }
+    let failed = summaries.iter().filter(|s| s.error.is_some()).count();
+    println!("{} tables migrated, {} failed", summaries.len() - failed, failed);
+}
+
+/// Sink shared by the tables of a database migration, counts the rows sent to it for a table.
+struct CountingSink<'a, S: Sink> {
+    sink: &'a S,
+    rows: Arc<AtomicUsize>,
+}
+
+impl<'a, S: Sink> fmt::Debug for CountingSink<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.sink)
+    }
+}
+
+#[async_trait]
+impl<'a, S: Sink> SinkWrapper for CountingSink<'a, S> {
+    type Inner = S;
+
+    fn inner(&self) -> &S {
+        self.sink
+    }
+
+    async fn wrap_batch_http(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<StringRow>>) -> Result<(), SinkError> {
+        let rows = buffer.len();
+        self.sink.send_batch_http(schema, table_name, columns, buffer).await?;
+        self.rows.fetch_add(rows, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn wrap_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        let rows = buffer.len();
+        self.sink.send_batch(schema, table_name, columns, buffer).await?;
+        self.rows.fetch_add(rows, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Migrates every table of `database` matching `options`, `options.workers` tables at a time, and
+/// returns a summary per table sorted by name. A table that fails does not stop the others.
+/// `sink` is borrowed so it can be finished afterwards, e.g. `FileSink::finish`.
+///
+/// `database` is passed as the schema of every table migration, it is the source schema for
+/// Postgres and MySQL and the target schema for the others; use a `MappedSink` to move the tables
+/// to another schema.
+pub async fn migrate_database<T, S>(source: &T, database: &str, options: &DatabaseMigration, sink: &S) -> Result<Vec<TableSummary>, DatabaseMigrationError>
+where
+    T: Source + Sync,
+    T::ErrorType: fmt::Debug,
+    S: Sink,
+{
+    let tables = source.list_tables(database)
+        .await
+        .map_err(|e| DatabaseMigrationError { message: format!("could not list the tables of {} - {:?}", database, e) })?;
+    let tables = options.select_tables(tables)?;
+    println!("Migrating {} tables of {}: {:?}", tables.len(), database, tables);
+
+    let mut summaries: Vec<TableSummary> = stream::iter(tables)
+        .map(|table_name| {
+            let rows = Arc::new(AtomicUsize::new(0));
+            let table_sink = CountingSink { sink, rows: rows.clone() };
+            let ignored_columns: Vec<&str> = options.ignored_columns.iter().map(|c| c.as_str()).collect();
+
+            async move {
+                let start = Instant::now();
+                let mut metadata = Metadata::new();
+                metadata.start();
+
+                let result = match source.get_table(database, &table_name).await {
+                    Ok(table) => source.migrate_table_to_cratedb(database, &table, ignored_columns, table_sink, &mut metadata).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(format!("{:?}", e))
+                };
+
+                TableSummary {
+                    table: table_name,
+                    rows: rows.load(Ordering::SeqCst),
+                    elapsed: start.elapsed(),
+                    error: result.err(),
+                }
+            }
+        })
+        .buffer_unordered(options.workers.max(1))
+        .collect()
+        .await;
+
+    summaries.sort_by(|a, b| a.table.cmp(&b.table));
+    print_summary(&summaries);
+    Ok(summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs;
+
+    use crate::experiment::data::CValue;
+    use crate::sink::arrow::driver::ArrowIPCSink;
+    use crate::source::arrow::driver::ArrowIPCSource;
+    use crate::source::database::{migrate_database, DatabaseMigration};
+    use crate::source::source::{Sink, Source};
+
+    #[test]
+    fn test_select_tables() {
+        let options = DatabaseMigration {
+            include: vec!["orders*".to_string(), "users".to_string()],
+            exclude: vec!["*_tmp".to_string()],
+            ..Default::default()
+        };
+        let tables = vec!["orders", "orders_2024", "orders_tmp", "users", "users_old"].into_iter().map(String::from).collect();
+        assert_eq!(options.select_tables(tables).unwrap(), vec!["orders", "orders_2024", "users"]);
+
+        let invalid = DatabaseMigration { include: vec!["orders[".to_string()], ..Default::default() };
+        assert!(invalid.select_tables(vec!["orders".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_database() {
+        let directory = temp_dir().join("cdctest_database_source");
+        let target = temp_dir().join("cdctest_database_target");
+        let _ = fs::remove_dir_all(&directory);
+        let _ = fs::remove_dir_all(&target);
+
+        let sink = ArrowIPCSink::new(directory.to_str().unwrap());
+        let columns = vec!["id".to_string()];
+        sink.send_batch("doc", "a", &columns, vec![vec![CValue::I32(1)], vec![CValue::I32(2)]]).await.unwrap();
+        sink.send_batch("doc", "b", &columns, vec![vec![CValue::I32(3)]]).await.unwrap();
+        sink.send_batch("doc", "skipped", &columns, vec![vec![CValue::I32(4)]]).await.unwrap();
+        sink.finish().unwrap();
+        fs::write(directory.join("doc.broken.arrow"), b"not arrow").unwrap();
+
+        let source = ArrowIPCSource { path: directory.to_str().unwrap().to_string() };
+        let options = DatabaseMigration {
+            exclude: vec!["skipped".to_string()],
+            workers: 2,
+            ..Default::default()
+        };
+        let target_sink = ArrowIPCSink::new(target.to_str().unwrap());
+        let summaries = migrate_database(&source, "doc", &options, &target_sink).await.unwrap();
+        target_sink.finish().unwrap();
+
+        let tables: Vec<&str> = summaries.iter().map(|s| s.table.as_str()).collect();
+        assert_eq!(tables, vec!["a", "b", "broken"]);
+        assert_eq!(summaries[0].rows, 2);
+        assert!(summaries[0].error.is_none());
+        assert_eq!(summaries[1].rows, 1);
+        // The broken file is not an Arrow file, its migration returns the error of the reader.
+        assert_eq!(summaries[2].rows, 0);
+        assert!(summaries[2].error.as_ref().unwrap().starts_with("Error reading from the source: IoError"));
+
+        let copied = ArrowIPCSource { path: target.to_str().unwrap().to_string() };
+        assert_eq!(copied.list_tables("doc").await.unwrap(), vec!["a", "b"]);
+        assert_eq!(copied.count("doc", "a").await.unwrap(), 2);
+        fs::remove_dir_all(&directory).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+    }
+}
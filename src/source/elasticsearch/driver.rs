@@ -0,0 +1,511 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+
+use crate::experiment::data::CValue;
+use crate::experiment::trans::json_to_cvalue;
+use crate::metadata::Metadata;
+use crate::source::source::{MigrationError, Sink, Source};
+
+/// Source for Elasticsearch and OpenSearch, indices are exposed as tables.
+///
+/// Documents are read with a point-in-time + `search_after`, Elasticsearch and OpenSearch have
+/// different point-in-time APIs and the one of the cluster's distribution is used. If the cluster
+/// does not support point-in-time (Elasticsearch < 7.10, OpenSearch < 2.4) or can not sort on
+/// `_shard_doc`, it falls back to the scroll API.
+pub struct ElasticsearchSource {
+    pub(crate) uri: String,
+}
+
+const KEEP_ALIVE: &str = "1m";
+
+/// How the documents of an index are paged through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pagination {
+    /// `POST /<index>/_pit`, released with `DELETE /_pit`.
+    ElasticsearchPit,
+    /// `POST /<index>/_search/point_in_time`, released with `DELETE /_search/point_in_time`.
+    OpenSearchPit,
+    Scroll,
+}
+
+/// Position in an index, `id` is the point-in-time or scroll id returned by the last page.
+struct Cursor {
+    pagination: Pagination,
+    id: Option<String>,
+    search_after: Option<Value>,
+}
+
+impl Cursor {
+    fn scroll() -> Self {
+        Cursor { pagination: Pagination::Scroll, id: None, search_after: None }
+    }
+
+    /// Whether no page was read with the point-in-time yet, it can still fall back to scroll.
+    fn is_new_point_in_time(&self) -> bool {
+        self.pagination != Pagination::Scroll && self.search_after.is_none()
+    }
+}
+
+/// Maps an Elasticsearch mapping property to a CrateDB column type.
+pub fn es_type_to_cratedb(property: &Value) -> String {
+    let properties = property.get("properties").and_then(|p| p.as_object());
+    let es_type = property.get("type").and_then(|t| t.as_str());
+
+    match (es_type, properties) {
+        (Some("nested"), Some(properties)) => format!("ARRAY({})", es_object_to_cratedb(properties)),
+        (Some("nested"), None) => "ARRAY(OBJECT(DYNAMIC))".to_string(),
+        // Sub-objects do not have a type, only properties.
+        (None, Some(properties)) | (Some("object"), Some(properties)) => es_object_to_cratedb(properties),
+        (Some("object"), None) | (Some("flattened"), None) => "OBJECT(DYNAMIC)".to_string(),
+        (Some(es_type), _) => {
+            match es_type {
+                "keyword" | "text" | "match_only_text" | "constant_keyword" | "wildcard" | "binary" | "version" => "TEXT",
+                "long" => "BIGINT",
+                // Values above i64::MAX do not fit BIGINT.
+                "unsigned_long" => "NUMERIC",
+                "integer" => "INTEGER",
+                "short" | "byte" => "SMALLINT",
+                "double" => "DOUBLE PRECISION",
+                "float" | "half_float" | "scaled_float" => "REAL",
+                "boolean" => "BOOLEAN",
+                "date" | "date_nanos" => "TIMESTAMP WITH TIME ZONE",
+                "ip" => "IP",
+                "geo_point" => "GEO_POINT",
+                "geo_shape" | "shape" => "GEO_SHAPE",
+                "dense_vector" => {
+                    return match property.get("dims").and_then(|d| d.as_u64()) {
+                        Some(dims) => format!("FLOAT_VECTOR({})", dims),
+                        None => "ARRAY(REAL)".to_string()
+                    }
+                }
+                _ => "TEXT"
+            }.to_string()
+        }
+        (None, None) => "TEXT".to_string()
+    }
+}
+
+fn es_object_to_cratedb(properties: &Map<String, Value>) -> String {
+    let columns: Vec<String> = properties
+        .iter()
+        .map(|(name, property)| format!(r#""{}" {}"#, name, es_type_to_cratedb(property)))
+        .collect();
+    format!("OBJECT(DYNAMIC) AS ({})", columns.join(", "))
+}
+
+impl ElasticsearchSource {
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.uri.trim_end_matches('/'), path)
+    }
+
+    /// Returns the columns of an index as pairs of (column_name, cratedb_type).
+    pub async fn get_columns(&self, table_name: &str) -> Result<Vec<(String, String)>, reqwest::Error> {
+        let client = self.get_client().await?;
+        let mapping: Value = client.get(self.url(&format!("{}/_mapping", table_name)))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // The response is keyed by the concrete index name, which might differ from `table_name`
+        // if an alias was used.
+        let properties = mapping
+            .as_object()
+            .and_then(|indices| indices.values().next())
+            .and_then(|index| index.pointer("/mappings/properties"))
+            .and_then(|p| p.as_object());
+
+        match properties {
+            Some(properties) => Ok(properties.iter().map(|(name, property)| (name.clone(), es_type_to_cratedb(property))).collect()),
+            None => Ok(vec![])
+        }
+    }
+
+    /// Whether the cluster is OpenSearch, from the `version.distribution` of its info.
+    async fn is_opensearch(&self, client: &Client) -> Result<bool, reqwest::Error> {
+        let info: Value = client.get(self.url("")).send().await?.error_for_status()?.json().await?;
+        Ok(info.pointer("/version/distribution").and_then(|d| d.as_str()) == Some("opensearch"))
+    }
+
+    /// Opens a point-in-time on the index with the API of the cluster's distribution, or returns a
+    /// scroll cursor if it fails.
+    async fn open_cursor(&self, client: &Client, table_name: &str) -> Result<Cursor, reqwest::Error> {
+        let (pagination, path, id_key) = if self.is_opensearch(client).await? {
+            (Pagination::OpenSearchPit, format!("{}/_search/point_in_time?keep_alive={}", table_name, KEEP_ALIVE), "pit_id")
+        } else {
+            (Pagination::ElasticsearchPit, format!("{}/_pit?keep_alive={}", table_name, KEEP_ALIVE), "id")
+        };
+
+        let res = client.post(self.url(&path)).send().await?;
+        if !res.status().is_success() {
+            return Ok(Cursor::scroll());
+        }
+        let body: Value = res.json().await?;
+        Ok(match body.get(id_key).and_then(|id| id.as_str()) {
+            Some(id) => Cursor { pagination, id: Some(id.to_string()), search_after: None },
+            None => Cursor::scroll()
+        })
+    }
+
+    /// Fetches the next page of hits, the cursor is updated with the id returned by the server and
+    /// the sort values of the last hit.
+    async fn next_page(&self, client: &Client, table_name: &str, cursor: &mut Cursor, batch_size: usize) -> Result<Vec<Value>, reqwest::Error> {
+        let request = match (cursor.pagination, &cursor.id) {
+            (Pagination::Scroll, None) => client.post(self.url(&format!("{}/_search?scroll={}", table_name, KEEP_ALIVE)))
+                .json(&json!({"size": batch_size, "sort": ["_doc"]})),
+            (Pagination::Scroll, Some(scroll_id)) => client.post(self.url("_search/scroll"))
+                .json(&json!({"scroll": KEEP_ALIVE, "scroll_id": scroll_id})),
+            (_, id) => {
+                let mut body = json!({
+                    "size": batch_size,
+                    "pit": {"id": id, "keep_alive": KEEP_ALIVE},
+                    "sort": [{"_shard_doc": "asc"}],
+                    "track_total_hits": false
+                });
+                if let Some(after) = &cursor.search_after {
+                    body["search_after"] = after.clone();
+                }
+                client.post(self.url("_search")).json(&body)
+            }
+        };
+
+        let mut response: Value = request.send().await?.error_for_status()?.json().await?;
+
+        let id_key = if cursor.pagination == Pagination::Scroll { "_scroll_id" } else { "pit_id" };
+        if let Some(id) = response.get(id_key).and_then(|c| c.as_str()) {
+            cursor.id = Some(id.to_string());
+        }
+
+        let hits = match response.pointer_mut("/hits/hits").map(|h| h.take()) {
+            Some(Value::Array(hits)) => hits,
+            _ => vec![]
+        };
+
+        if let Some(last) = hits.last() {
+            cursor.search_after = last.get("sort").cloned();
+        }
+        Ok(hits)
+    }
+
+    /// Releases the point-in-time or scroll context, they expire after `KEEP_ALIVE` anyway.
+    async fn close_cursor(&self, client: &Client, cursor: Cursor) -> Result<(), reqwest::Error> {
+        let Some(id) = cursor.id else { return Ok(()) };
+        let request = match cursor.pagination {
+            Pagination::ElasticsearchPit => client.delete(self.url("_pit")).json(&json!({"id": id})),
+            Pagination::OpenSearchPit => client.delete(self.url("_search/point_in_time")).json(&json!({"pit_id": [id]})),
+            Pagination::Scroll => client.delete(self.url("_search/scroll")).json(&json!({"scroll_id": id})),
+        };
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Normalizes a document's `_source` following the order of `columns`, missing keys are None.
+    fn source_to_row(&self, source: Value, columns: &[String]) -> Vec<CValue> {
+        let mut source = match source {
+            Value::Object(map) => map,
+            _ => Map::new()
+        };
+        columns
+            .iter()
+            .map(|column| source.remove(column).map_or(CValue::None, json_to_cvalue))
+            .collect()
+    }
+
+    pub(crate) async fn get_client(&self) -> Result<Client, reqwest::Error> {
+        Client::builder().build()
+    }
+}
+
+#[async_trait]
+impl Source for ElasticsearchSource {
+    type ErrorType = reqwest::Error;
+    type TableType = String;
+    type RowType = Value;
+
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        // Elasticsearch does not have databases, we return the cluster name instead.
+        let client = self.get_client().await?;
+        let info: Value = client.get(self.url("")).send().await?.error_for_status()?.json().await?;
+        match info.get("cluster_name").and_then(|n| n.as_str()) {
+            Some(name) => Ok(vec![name.to_string()]),
+            None => Ok(vec![])
+        }
+    }
+
+    async fn list_tables(&self, _database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        let client = self.get_client().await?;
+        let indices: Vec<Value> = client.get(self.url("_cat/indices?format=json&h=index"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Hidden and system indices start with a dot, e.g. `.kibana`.
+        let tables: Vec<String> = indices
+            .iter()
+            .filter_map(|x| x.get("index").and_then(|i| i.as_str()))
+            .filter(|x| !x.starts_with('.'))
+            .map(String::from)
+            .collect();
+        Ok(tables)
+    }
+
+    async fn get_table(&self, _database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        let client = self.get_client().await?;
+        client.head(self.url(table_name)).send().await?.error_for_status()?;
+        Ok(table_name.to_string())
+    }
+
+    async fn count(&self, _database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let client = self.get_client().await?;
+        let count: Value = client.get(self.url(&format!("{}/_count", table_name)))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(count.get("count").and_then(|c| c.as_i64()).unwrap_or(0))
+    }
+
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        let client = self.get_client().await.map_err(MigrationError::Source)?;
+        let batch_size: usize = 1000;
+        let mut total_documents_sent = 0;
+
+        let column_definitions: Vec<(String, String)> = self.get_columns(table)
+            .await
+            .map_err(MigrationError::Source)?
+            .into_iter()
+            .filter(|(name, _)| !ignored_columns.contains(&name.as_str()))
+            .collect();
+        let columns: Vec<String> = column_definitions.iter().map(|(name, _)| name.clone()).collect();
+
+        cratedb.create_table(schema, table, &column_definitions).await?;
+        metadata.print_step("Created table from index mapping");
+
+        let mut cursor = self.open_cursor(&client, table).await.map_err(MigrationError::Source)?;
+        metadata.print_step(format!("Reading index {} with {:?}", table, cursor.pagination).as_str());
+
+        loop {
+            let hits = match self.next_page(&client, table, &mut cursor, batch_size).await {
+                // E.g. OpenSearch versions that can not sort a point-in-time on `_shard_doc`.
+                Err(e) if cursor.is_new_point_in_time() && e.status().is_some_and(|s| s.is_client_error()) => {
+                    metadata.print_step(format!("Could not search with {:?}, falling back to scroll: {}", cursor.pagination, e).as_str());
+                    if let Err(e) = self.close_cursor(&client, cursor).await {
+                        metadata.print_step(format!("Could not release the point-in-time: {}", e).as_str());
+                    }
+                    cursor = Cursor::scroll();
+                    continue;
+                }
+                hits => hits.map_err(MigrationError::Source)?
+            };
+            if hits.is_empty() {
+                break;
+            }
+
+            let mut buffer: Vec<Vec<CValue>> = vec![];
+            for mut hit in hits {
+                let source = hit.get_mut("_source").map(|s| s.take()).unwrap_or(Value::Null);
+                buffer.push(self.source_to_row(source, &columns));
+            }
+
+            let documents_in_batch = buffer.len();
+            cratedb.send_batch(schema, table, &columns, buffer).await?;
+            total_documents_sent += documents_in_batch;
+            metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
+        }
+
+        if let Err(e) = self.close_cursor(&client, cursor).await {
+            metadata.print_step(format!("Could not release the search context: {}", e).as_str());
+        }
+
+        metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
+    }
+
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
+        Ok(match row {
+            Value::Object(map) => map.into_iter().map(|(_, v)| json_to_cvalue(v)).collect(),
+            _ => vec![json_to_cvalue(row)]
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::experiment::data::CValue;
+    use crate::metadata::Metadata;
+    use crate::source::elasticsearch::driver::{es_type_to_cratedb, ElasticsearchSource};
+    use crate::source::source::Source;
+    use crate::testing::MemorySink;
+
+    fn hits(messages: &[(&str, i64)]) -> Value {
+        let hits: Vec<Value> = messages
+            .iter()
+            .map(|(message, sort)| json!({"_source": {"message": message, "id": 18446744073709551615u64}, "sort": [sort]}))
+            .collect();
+        json!({"pit_id": "pit-1", "_scroll_id": "scroll-1", "hits": {"hits": hits}})
+    }
+
+    async fn mock_index(server: &MockServer, distribution: &str) {
+        Mock::given(method("GET")).and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"version": {"distribution": distribution}})))
+            .mount(server).await;
+        Mock::given(method("GET")).and(path("/logs/_mapping"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "logs": {"mappings": {"properties": {"message": {"type": "text"}, "id": {"type": "unsigned_long"}}}}
+            })))
+            .mount(server).await;
+    }
+
+    /// Returns the `message` column of the rows sent to the sink.
+    fn messages(sink: &MemorySink) -> Vec<String> {
+        let batches = sink.batches.lock().unwrap();
+        let index = batches[0].0.iter().position(|c| c == "message").unwrap();
+        batches
+            .iter()
+            .flat_map(|(_, rows)| rows.iter())
+            .map(|row| match &row[index] { CValue::String(s) => s.clone(), other => panic!("{:?}", other) })
+            .collect()
+    }
+
+    #[test]
+    fn test_mapping_to_cratedb_types() {
+        assert_eq!(es_type_to_cratedb(&json!({"type": "keyword"})), "TEXT");
+        assert_eq!(es_type_to_cratedb(&json!({"type": "date"})), "TIMESTAMP WITH TIME ZONE");
+        assert_eq!(es_type_to_cratedb(&json!({"type": "unsigned_long"})), "NUMERIC");
+        assert_eq!(es_type_to_cratedb(&json!({"type": "dense_vector", "dims": 3})), "FLOAT_VECTOR(3)");
+        assert_eq!(
+            es_type_to_cratedb(&json!({"properties": {"city": {"type": "text"}, "zip": {"type": "integer"}}})),
+            r#"OBJECT(DYNAMIC) AS ("city" TEXT, "zip" INTEGER)"#
+        );
+        assert_eq!(
+            es_type_to_cratedb(&json!({"type": "nested", "properties": {"n": {"type": "long"}}})),
+            r#"ARRAY(OBJECT(DYNAMIC) AS ("n" BIGINT))"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_mocked_elasticsearch() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET")).and(path("/_cat/indices"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"index": "logs"}, {"index": ".kibana"}])))
+            .mount(&server).await;
+        Mock::given(method("GET")).and(path("/logs/_count"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"count": 2})))
+            .mount(&server).await;
+        Mock::given(method("GET")).and(path("/logs/_mapping"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "logs-000001": {"mappings": {"properties": {
+                    "message": {"type": "text"},
+                    "host": {"properties": {"name": {"type": "keyword"}}}
+                }}}
+            })))
+            .mount(&server).await;
+
+        let elasticsearch = ElasticsearchSource { uri: server.uri() };
+
+        assert_eq!(elasticsearch.list_tables("").await.unwrap(), vec!["logs".to_string()]);
+        assert_eq!(elasticsearch.count("", "logs").await.unwrap(), 2);
+
+        let columns = elasticsearch.get_columns("logs").await.unwrap();
+        assert!(columns.contains(&("message".to_string(), "TEXT".to_string())));
+        assert!(columns.contains(&("host".to_string(), r#"OBJECT(DYNAMIC) AS ("name" TEXT)"#.to_string())));
+
+        let row = elasticsearch.source_to_row(
+            json!({"host": {"name": "web-1"}, "extra": 1}),
+            &["message".to_string(), "host".to_string()],
+        );
+        assert!(matches!(row[0], CValue::None));
+        match &row[1] {
+            CValue::Object(obj) => assert!(matches!(obj.get("name"), Some(CValue::String(s)) if s == "web-1")),
+            other => panic!("Expected an object, got {:?}", other)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_point_in_time_pagination() {
+        let server = MockServer::start().await;
+        mock_index(&server, "elasticsearch").await;
+
+        Mock::given(method("POST")).and(path("/logs/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "pit-0"})))
+            .expect(1)
+            .mount(&server).await;
+        // Pages are matched on the `search_after` of the previous page's last hit.
+        Mock::given(method("POST")).and(path("/_search")).and(body_partial_json(json!({"search_after": [2]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(hits(&[])))
+            .mount(&server).await;
+        Mock::given(method("POST")).and(path("/_search")).and(body_partial_json(json!({"pit": {"id": "pit-1"}, "search_after": [1]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(hits(&[("c", 2)])))
+            .mount(&server).await;
+        Mock::given(method("POST")).and(path("/_search")).and(body_partial_json(json!({"pit": {"id": "pit-0"}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(hits(&[("a", 0), ("b", 1)])))
+            .mount(&server).await;
+        Mock::given(method("DELETE")).and(path("/_pit")).and(body_partial_json(json!({"id": "pit-1"})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server).await;
+
+        let elasticsearch = ElasticsearchSource { uri: server.uri() };
+        let mut metadata = Metadata::new();
+        metadata.start();
+        let sink = MemorySink::default();
+        elasticsearch.migrate_table_to_cratedb("doc", &"logs".to_string(), vec![], sink.clone(), &mut metadata).await.unwrap();
+
+        assert_eq!(messages(&sink), vec!["a", "b", "c"]);
+        let batches = sink.batches.lock().unwrap();
+        let id = batches[0].0.iter().position(|c| c == "id").unwrap();
+        assert!(matches!(&batches[0].1[0][id], CValue::Decimal(s) if s == "18446744073709551615"));
+    }
+
+    #[tokio::test]
+    async fn test_scroll_fallback() {
+        let server = MockServer::start().await;
+        mock_index(&server, "opensearch").await;
+
+        Mock::given(method("POST")).and(path("/logs/_search/point_in_time"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"pit_id": "pit-0"})))
+            .expect(1)
+            .mount(&server).await;
+        // The point-in-time can not be sorted on `_shard_doc`, so it is released for a scroll.
+        Mock::given(method("POST")).and(path("/_search"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server).await;
+        Mock::given(method("DELETE")).and(path("/_search/point_in_time")).and(body_partial_json(json!({"pit_id": ["pit-0"]})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server).await;
+        Mock::given(method("POST")).and(path("/logs/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(hits(&[("a", 0), ("b", 1)])))
+            .expect(1)
+            .mount(&server).await;
+        Mock::given(method("POST")).and(path("/_search/scroll")).and(body_partial_json(json!({"scroll_id": "scroll-1"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(hits(&[])))
+            .expect(1)
+            .mount(&server).await;
+        Mock::given(method("DELETE")).and(path("/_search/scroll"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server).await;
+
+        let opensearch = ElasticsearchSource { uri: server.uri() };
+        let mut metadata = Metadata::new();
+        metadata.start();
+        let sink = MemorySink::default();
+        opensearch.migrate_table_to_cratedb("doc", &"logs".to_string(), vec![], sink.clone(), &mut metadata).await.unwrap();
+
+        assert_eq!(messages(&sink), vec!["a", "b"]);
+    }
+}
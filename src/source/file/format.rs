@@ -0,0 +1,196 @@
+use std::fmt;
+use arrow::array::RecordBatch;
+use arrow::error::ArrowError;
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde_json::Value;
+
+use crate::experiment::data::CValue;
+use crate::experiment::trans::json_to_cvalue;
+use crate::source::arrow::driver::arrow_value_to_cvalue;
+
+/// A row read from a file as (column, value) pairs, files do not guarantee that every row
+/// has the same columns.
+pub type FileRow = Vec<(String, CValue)>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Json,
+    JsonLines,
+    Csv,
+    Parquet,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadFileError {
+    message: String,
+}
+
+impl fmt::Display for ReadFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error reading file: {}", self.message)
+    }
+}
+
+impl std::error::Error for ReadFileError {}
+
+impl FileFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &str) -> Option<FileFormat> {
+        let extension = path.rsplit_once('.').map(|(_, e)| e.to_lowercase())?;
+        match extension.as_str() {
+            "json" => Some(FileFormat::Json),
+            "jsonl" | "ndjson" => Some(FileFormat::JsonLines),
+            "csv" => Some(FileFormat::Csv),
+            "parquet" => Some(FileFormat::Parquet),
+            _ => None
+        }
+    }
+
+    /// Whether the format can be read record by record while it is downloaded, JSON documents and
+    /// Parquet files (its metadata is at the end) have to be read whole.
+    pub fn is_streamable(&self) -> bool {
+        matches!(self, FileFormat::JsonLines | FileFormat::Csv)
+    }
+}
+
+fn json_to_row(value: Value) -> Option<FileRow> {
+    match value {
+        Value::Object(map) => Some(map.into_iter().map(|(k, v)| (k, json_to_cvalue(v))).collect()),
+        _ => None
+    }
+}
+
+/// Reads a JSON document, either an array of objects or a single object.
+pub fn read_json(bytes: &[u8]) -> Result<Vec<FileRow>, ReadFileError> {
+    let document: Value = serde_json::from_slice(bytes).map_err(|e| ReadFileError { message: e.to_string() })?;
+    match document {
+        Value::Array(values) => Ok(values.into_iter().filter_map(json_to_row).collect()),
+        Value::Object(_) => Ok(json_to_row(document).into_iter().collect()),
+        _ => Err(ReadFileError { message: "JSON document is not an object or an array of objects".to_string() })
+    }
+}
+
+/// Converts a record batch into rows, used for Parquet.
+pub fn record_batch_to_rows(batch: &RecordBatch) -> Result<Vec<FileRow>, ArrowError> {
+    let schema = batch.schema();
+    (0..batch.num_rows())
+        .map(|row| {
+            schema.fields()
+                .iter()
+                .zip(batch.columns())
+                .map(|(field, column)| Ok((field.name().clone(), arrow_value_to_cvalue(column, field, row)?)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Reads a whole Parquet file.
+pub fn read_parquet(bytes: Bytes) -> Result<Vec<FileRow>, ReadFileError> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(bytes)
+        .and_then(|builder| builder.build())
+        .map_err(|e| ReadFileError { message: e.to_string() })?;
+
+    let mut rows = vec![];
+    for batch in reader {
+        let batch = batch.map_err(|e| ReadFileError { message: e.to_string() })?;
+        rows.extend(record_batch_to_rows(&batch).map_err(|e| ReadFileError { message: e.to_string() })?);
+    }
+    Ok(rows)
+}
+
+/// Incremental reader for record oriented formats (JSON Lines and CSV), chunks of bytes are fed
+/// as they are downloaded and complete records are returned; the incomplete tail is kept.
+///
+/// CSV values are Strings and empty values are None, the first record is the header.
+pub struct RecordReader {
+    format: FileFormat,
+    pending: Vec<u8>,
+    headers: Option<Vec<String>>,
+}
+
+impl RecordReader {
+    pub fn new(format: FileFormat) -> Self {
+        Self {
+            format,
+            pending: vec![],
+            headers: None,
+        }
+    }
+
+    /// Returns the position of the first newline that ends a record, CSV values can have newlines
+    /// inside quotes; escaped quotes are doubled so the count of quotes stays even.
+    fn record_end(&self) -> Option<usize> {
+        let mut quoted = false;
+        for (i, b) in self.pending.iter().enumerate() {
+            match b {
+                b'"' if self.format == FileFormat::Csv => quoted = !quoted,
+                b'\n' if !quoted => return Some(i),
+                _ => ()
+            }
+        }
+        None
+    }
+
+    fn parse_record(&mut self, record: &[u8]) -> Option<FileRow> {
+        let record = record.strip_suffix(b"\r").unwrap_or(record);
+        if record.iter().all(|b| b.is_ascii_whitespace()) {
+            return None;
+        }
+
+        match self.format {
+            FileFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new().has_headers(false).from_reader(record);
+                let values: Vec<String> = match reader.records().next() {
+                    Some(Ok(r)) => r.iter().map(String::from).collect(),
+                    _ => {
+                        eprintln!("Skipping CSV record - {}", String::from_utf8_lossy(record));
+                        return None;
+                    }
+                };
+
+                match &self.headers {
+                    None => {
+                        self.headers = Some(values);
+                        None
+                    }
+                    Some(headers) => Some(
+                        headers
+                            .iter()
+                            .zip(values)
+                            .map(|(h, v)| (h.clone(), if v.is_empty() { CValue::None } else { CValue::String(v) }))
+                            .collect()
+                    )
+                }
+            }
+            _ => {
+                match serde_json::from_slice::<Value>(record) {
+                    Ok(value) => json_to_row(value),
+                    Err(e) => {
+                        eprintln!("Skipping JSON line - {}", e);
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Feeds a chunk of bytes and returns the records completed by it.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<FileRow> {
+        self.pending.extend_from_slice(chunk);
+        let mut rows = vec![];
+        while let Some(end) = self.record_end() {
+            let record: Vec<u8> = self.pending.drain(..=end).collect();
+            if let Some(row) = self.parse_record(&record[..record.len() - 1]) {
+                rows.push(row);
+            }
+        }
+        rows
+    }
+
+    /// Returns the last record if the file does not end with a newline.
+    pub fn finish(&mut self) -> Vec<FileRow> {
+        let record = std::mem::take(&mut self.pending);
+        self.parse_record(&record).into_iter().collect()
+    }
+}
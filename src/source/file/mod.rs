@@ -0,0 +1 @@
+pub mod format;
\ No newline at end of file
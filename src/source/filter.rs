@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use mongodb::bson::{doc, Bson, DateTime, Document};
+
+use crate::experiment::data::CValue;
+use crate::experiment::predicate::Predicate;
+use crate::experiment::trans::cvalue_to_json;
+use crate::utils::{lookup_table, quote_identifier};
+
+/// Filters of a source, looked up by `<schema>.<table>` and then by `<table>`.
+pub type Filters = HashMap<String, Predicate>;
+
+pub fn get_filter<'a>(filters: &'a Filters, schema: &str, table_name: &str) -> Option<&'a Predicate> {
+    lookup_table(filters, schema, table_name)
+}
+
+fn sql_literal(value: &CValue, dialect: Dialect) -> String {
+    match value {
+        CValue::Bool(v) => v.to_string().to_uppercase(),
+        CValue::I16(v) => v.to_string(),
+        CValue::I32(v) => v.to_string(),
+        CValue::I64(v) => v.to_string(),
+        CValue::Double32(v) => v.to_string(),
+        CValue::Double64(v) => v.to_string(),
+        CValue::String(v) => format!("'{}'", v.replace('\'', "''")),
+        CValue::Decimal(v) => v.clone(),
+        // MySQL and ClickHouse do not read the `T` and `Z` of ISO 8601, their timestamps are UTC.
+        CValue::Timestamp(ms) if matches!(dialect, Dialect::MySql | Dialect::ClickHouse) => {
+            let timestamp = chrono::DateTime::from_timestamp_millis(*ms).unwrap_or_default();
+            format!("'{}'", timestamp.format("%Y-%m-%d %H:%M:%S%.3f"))
+        }
+        CValue::Timestamp(_) | CValue::Date(_) | CValue::Ip(_) => format!("'{}'", value),
+        CValue::None | CValue::Unknown => "NULL".to_string(),
+        other => format!("'{}'", cvalue_to_json(other).to_string().replace('\'', "''")),
+    }
+}
+
+/// SQL dialect of a source, for the identifier quotes and the way object fields are read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Postgres,
+    CrateDB,
+    MySql,
+    ClickHouse,
+}
+
+impl Dialect {
+    /// Quotes an identifier, quotes in it are doubled.
+    fn quote_identifier(self, name: &str) -> String {
+        match self {
+            Dialect::Postgres | Dialect::CrateDB => quote_identifier(name),
+            Dialect::MySql | Dialect::ClickHouse => format!("`{}`", name.replace('`', "``")),
+        }
+    }
+}
+
+/// Returns the column of a condition on `value`. Dotted paths are read with subscripts in CrateDB,
+/// the JSON operators in Postgres and MySQL and as subcolumns in ClickHouse.
+fn sql_column(column: &str, dialect: Dialect, value: Option<&CValue>) -> String {
+    let mut parts = column.split('.');
+    let first = dialect.quote_identifier(parts.next().unwrap_or_default());
+    let fields: Vec<&str> = parts.collect();
+    let Some((last, parents)) = fields.split_last() else { return first };
+    let literal = |field: &str| format!("'{}'", field.replace('\'', "''"));
+
+    match dialect {
+        Dialect::CrateDB => format!("{}{}", first, fields.iter().map(|f| format!("[{}]", literal(f))).collect::<String>()),
+        Dialect::Postgres => {
+            let path = format!("{}{}->>{}", first, parents.iter().map(|f| format!("->{}", literal(f))).collect::<String>(), literal(last));
+            // `->>` returns text, it is cast to compare numbers and booleans.
+            match value {
+                Some(CValue::Bool(_)) => format!("({})::BOOLEAN", path),
+                Some(CValue::I16(_) | CValue::I32(_) | CValue::I64(_) | CValue::Double32(_) | CValue::Double64(_)) => format!("({})::NUMERIC", path),
+                _ => path
+            }
+        }
+        Dialect::MySql => format!("{}->>{}", first, literal(&format!("$.{}", fields.join(".")))),
+        Dialect::ClickHouse => format!("{}.{}", first, fields.iter().map(|f| dialect.quote_identifier(f)).collect::<Vec<String>>().join(".")),
+    }
+}
+
+fn cvalue_to_bson(value: &CValue) -> Bson {
+    match value {
+        CValue::Bool(v) => Bson::Boolean(*v),
+        CValue::I16(v) => Bson::Int32(*v as i32),
+        CValue::I32(v) => Bson::Int32(*v),
+        CValue::I64(v) => Bson::Int64(*v),
+        CValue::Double32(v) => Bson::Double(*v as f64),
+        CValue::Double64(v) => Bson::Double(*v),
+        CValue::String(v) => Bson::String(v.clone()),
+        CValue::Timestamp(v) => Bson::DateTime(DateTime::from_millis(*v)),
+        CValue::None | CValue::Unknown => Bson::Null,
+        other => Bson::try_from(cvalue_to_json(other)).unwrap_or(Bson::Null),
+    }
+}
+
+/// Pushing predicates down to the sources.
+impl Predicate {
+    /// Returns the `WHERE` condition in the source's dialect.
+    pub fn to_sql(&self, dialect: Dialect) -> String {
+        let comparison = |column: &str, operator: &str, value: &CValue| {
+            format!("{} {} {}", sql_column(column, dialect, Some(value)), operator, sql_literal(value, dialect))
+        };
+        match self {
+            Predicate::Eq(c, CValue::None) => format!("{} IS NULL", sql_column(c, dialect, None)),
+            Predicate::Ne(c, CValue::None) => format!("{} IS NOT NULL", sql_column(c, dialect, None)),
+            Predicate::Eq(c, v) => comparison(c, "=", v),
+            Predicate::Ne(c, v) => comparison(c, "<>", v),
+            Predicate::Gt(c, v) => comparison(c, ">", v),
+            Predicate::Gte(c, v) => comparison(c, ">=", v),
+            Predicate::Lt(c, v) => comparison(c, "<", v),
+            Predicate::Lte(c, v) => comparison(c, "<=", v),
+            Predicate::In(c, values) => format!("{} IN ({})", sql_column(c, dialect, values.first()), values.iter().map(|v| sql_literal(v, dialect)).collect::<Vec<String>>().join(", ")),
+            Predicate::IsNull(c) => format!("{} IS NULL", sql_column(c, dialect, None)),
+            Predicate::IsNotNull(c) => format!("{} IS NOT NULL", sql_column(c, dialect, None)),
+            Predicate::And(predicates) if predicates.is_empty() => "TRUE".to_string(),
+            Predicate::Or(predicates) if predicates.is_empty() => "FALSE".to_string(),
+            Predicate::And(predicates) => format!("({})", predicates.iter().map(|p| p.to_sql(dialect)).collect::<Vec<String>>().join(" AND ")),
+            Predicate::Or(predicates) => format!("({})", predicates.iter().map(|p| p.to_sql(dialect)).collect::<Vec<String>>().join(" OR ")),
+            Predicate::Not(predicate) => format!("NOT ({})", predicate.to_sql(dialect)),
+            Predicate::Sql(condition) => format!("({})", condition),
+        }
+    }
+
+    /// Returns the MongoDB query document, None if the predicate has raw SQL.
+    pub fn to_mongo(&self) -> Option<Document> {
+        let field = |c: &str, operator: &str, v: &CValue| {
+            let mut condition = Document::new();
+            condition.insert(operator, cvalue_to_bson(v));
+            let mut query = Document::new();
+            query.insert(c, condition);
+            query
+        };
+        let many = |predicates: &Vec<Predicate>| predicates.iter().map(|p| p.to_mongo().map(Bson::Document)).collect::<Option<Vec<Bson>>>();
+
+        Some(match self {
+            Predicate::Eq(c, v) => field(c, "$eq", v),
+            Predicate::Ne(c, v) => field(c, "$ne", v),
+            Predicate::Gt(c, v) => field(c, "$gt", v),
+            Predicate::Gte(c, v) => field(c, "$gte", v),
+            Predicate::Lt(c, v) => field(c, "$lt", v),
+            Predicate::Lte(c, v) => field(c, "$lte", v),
+            Predicate::In(c, values) => {
+                let mut query = Document::new();
+                query.insert(c, doc! { "$in": values.iter().map(cvalue_to_bson).collect::<Vec<Bson>>() });
+                query
+            }
+            // `{field: null}` also matches documents without the field, like a missing column.
+            Predicate::IsNull(c) => field(c, "$eq", &CValue::None),
+            Predicate::IsNotNull(c) => field(c, "$ne", &CValue::None),
+            Predicate::And(predicates) if predicates.is_empty() => Document::new(),
+            Predicate::And(predicates) => doc! { "$and": many(predicates)? },
+            Predicate::Or(predicates) => doc! { "$or": many(predicates)? },
+            Predicate::Not(predicate) => doc! { "$nor": [predicate.to_mongo()?] },
+            Predicate::Sql(_) => return None,
+        })
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use crate::experiment::data::CValue;
+    use crate::experiment::predicate::Predicate;
+    use crate::source::filter::Dialect;
+
+    fn predicate() -> Predicate {
+        Predicate::And(vec![
+            Predicate::Eq("tenant.id".to_string(), CValue::String("o'neil".to_string())),
+            Predicate::Or(vec![
+                Predicate::Gte("created".to_string(), CValue::I64(100)),
+                Predicate::IsNull("created".to_string()),
+            ]),
+        ])
+    }
+
+    #[test]
+    fn test_pushdown() {
+        assert_eq!(predicate().to_sql(Dialect::CrateDB), r#"("tenant"['id'] = 'o''neil' AND ("created" >= 100 OR "created" IS NULL))"#);
+        assert_eq!(predicate().to_sql(Dialect::Postgres), r#"("tenant"->>'id' = 'o''neil' AND ("created" >= 100 OR "created" IS NULL))"#);
+        assert_eq!(Predicate::Gt("a.b.c".to_string(), CValue::I32(1)).to_sql(Dialect::Postgres), r#"("a"->'b'->>'c')::NUMERIC > 1"#);
+        assert_eq!(Predicate::IsNull("tenant.id".to_string()).to_sql(Dialect::MySql), "`tenant`->>'$.id' IS NULL");
+        assert_eq!(Predicate::In("id".to_string(), vec![CValue::I32(1), CValue::I32(2)]).to_sql(Dialect::ClickHouse), "`id` IN (1, 2)");
+        let since = Predicate::Gte("created".to_string(), CValue::Timestamp(1704164645000));
+        assert_eq!(since.to_sql(Dialect::Postgres), r#""created" >= '2024-01-02T03:04:05.000Z'"#);
+        assert_eq!(since.to_sql(Dialect::MySql), "`created` >= '2024-01-02 03:04:05.000'");
+
+        // Quotes in column names are escaped.
+        assert_eq!(Predicate::IsNull(r#"a"b"#.to_string()).to_sql(Dialect::Postgres), r#""a""b" IS NULL"#);
+        assert_eq!(Predicate::IsNull("a`b.c`d".to_string()).to_sql(Dialect::ClickHouse), "`a``b`.`c``d` IS NULL");
+
+        assert_eq!(predicate().to_mongo().unwrap(), doc! {
+            "$and": [
+                { "tenant.id": { "$eq": "o'neil" } },
+                { "$or": [{ "created": { "$gte": 100_i64 } }, { "created": { "$eq": null } }] },
+            ]
+        });
+        assert!(Predicate::Not(Box::new(Predicate::Sql("a > 1".to_string()))).to_mongo().is_none());
+    }
+}
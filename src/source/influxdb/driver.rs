@@ -0,0 +1,438 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::fs::File;
+use tokio::io::{stdin, AsyncBufRead, AsyncBufReadExt, BufReader, Lines};
+
+use crate::experiment::data::CValue;
+use crate::metadata::Metadata;
+use crate::source::batch::{column_definitions, DynamicBatch};
+use crate::source::source::{MigrationError, Sink, SinkError, Source};
+
+/// Name of the column the point's timestamp is written to.
+pub const TIME_COLUMN: &str = "time";
+
+/// Name of the column tags are written to when `tags_as_object` is set.
+pub const TAGS_COLUMN: &str = "tags";
+
+/// Source for files in InfluxDB line protocol, every measurement is exposed as a table.
+///
+/// `path` can be `-` to read from stdin, in that case the input can only be read once, so
+/// `migrate_all_to_cratedb` should be used instead of migrating measurements one by one.
+pub struct InfluxDBSource {
+    pub(crate) path: String,
+    pub(crate) precision: Precision,
+    pub(crate) tags_as_object: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Precision {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+}
+
+impl Precision {
+    fn to_millis(self, timestamp: i64) -> Result<i64, ParseLineError> {
+        match self {
+            Precision::Nanoseconds => Ok(timestamp / 1_000_000),
+            Precision::Microseconds => Ok(timestamp / 1_000),
+            Precision::Milliseconds => Ok(timestamp),
+            Precision::Seconds => timestamp
+                .checked_mul(1_000)
+                .ok_or_else(|| ParseLineError { message: format!("the timestamp {}s overflows in milliseconds", timestamp) }),
+        }
+    }
+}
+
+impl FromStr for Precision {
+    type Err = ParseLineError;
+
+    fn from_str(input: &str) -> Result<Precision, ParseLineError> {
+        match input.to_lowercase().as_str() {
+            "ns" => Ok(Precision::Nanoseconds),
+            "us" => Ok(Precision::Microseconds),
+            "ms" => Ok(Precision::Milliseconds),
+            "s" => Ok(Precision::Seconds),
+            _ => Err(ParseLineError { message: format!("'{}' is not a valid precision", input) }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseLineError {
+    message: String,
+}
+
+impl fmt::Display for ParseLineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error parsing line protocol: {}", self.message)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Point {
+    pub measurement: String,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, CValue)>,
+    /// Timestamp in the unit of the source's `Precision`.
+    pub timestamp: Option<i64>,
+}
+
+/// Splits `input` by `separator` unless it is escaped with a backslash, if `track_quotes` is
+/// set separators inside double-quoted strings are kept too. Escapes are preserved.
+fn split_unescaped(input: &str, separator: char, track_quotes: bool) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quoted = false;
+
+    for (i, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '"' if track_quotes => quoted = !quoted,
+            _ if c == separator && !quoted => {
+                parts.push(&input[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => ()
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn unescape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(',' | '=' | ' ' | '"' | '\\')) => output.push(chars.next().unwrap()),
+            _ => output.push(c)
+        }
+    }
+    output
+}
+
+fn parse_key_value(input: &str) -> Result<(&str, &str), ParseLineError> {
+    let parts = split_unescaped(input, '=', true);
+    match parts.as_slice() {
+        [key, value] if !key.is_empty() => Ok((key, value)),
+        _ => Err(ParseLineError { message: format!("'{}' is not a valid key=value pair", input) })
+    }
+}
+
+fn parse_field_value(input: &str) -> Result<CValue, ParseLineError> {
+    if input.len() >= 2 && input.starts_with('"') && input.ends_with('"') {
+        return Ok(CValue::String(unescape(&input[1..input.len() - 1])));
+    }
+
+    match input {
+        "t" | "T" | "true" | "True" | "TRUE" => return Ok(CValue::Bool(true)),
+        "f" | "F" | "false" | "False" | "FALSE" => return Ok(CValue::Bool(false)),
+        _ => ()
+    }
+
+    if let Some(integer) = input.strip_suffix('i') {
+        if let Ok(v) = integer.parse::<i64>() {
+            return Ok(CValue::I64(v));
+        }
+    }
+
+    if let Some(unsigned) = input.strip_suffix('u') {
+        if let Ok(v) = unsigned.parse::<u64>() {
+            // CrateDB does not have unsigned types, values that do not fit in a BIGINT are kept as strings.
+            return Ok(i64::try_from(v).map_or(CValue::String(v.to_string()), CValue::I64));
+        }
+    }
+
+    match input.parse::<f64>() {
+        Ok(v) => Ok(CValue::Double64(v)),
+        Err(_) => Err(ParseLineError { message: format!("'{}' is not a valid field value", input) })
+    }
+}
+
+/// Parses one line of InfluxDB line protocol, e.g.:
+///
+/// `weather,location=us-midwest temperature=82,humidity=71i 1465839830100400200`
+pub fn parse_line(line: &str) -> Result<Point, ParseLineError> {
+    let sections = split_unescaped(line.trim(), ' ', false);
+    let (series, rest) = match sections.split_first() {
+        Some((series, rest)) if !rest.is_empty() => (*series, rest.join(" ")),
+        _ => return Err(ParseLineError { message: format!("'{}' does not have fields", line) })
+    };
+
+    let mut series_parts = split_unescaped(series, ',', false).into_iter();
+    let measurement = unescape(series_parts.next().unwrap_or(""));
+    if measurement.is_empty() {
+        return Err(ParseLineError { message: format!("'{}' does not have a measurement", line) });
+    }
+
+    let mut tags = vec![];
+    for tag in series_parts {
+        let (key, value) = parse_key_value(tag)?;
+        tags.push((unescape(key), unescape(value)));
+    }
+
+    let rest_parts = split_unescaped(&rest, ' ', true);
+    let mut fields = vec![];
+    for field in split_unescaped(rest_parts[0], ',', true) {
+        let (key, value) = parse_key_value(field)?;
+        fields.push((unescape(key), parse_field_value(value)?));
+    }
+
+    let timestamp = match rest_parts.get(1) {
+        Some(t) if !t.is_empty() => Some(t.parse::<i64>().map_err(|_| ParseLineError { message: format!("'{}' is not a valid timestamp", t) })?),
+        _ => None
+    };
+
+    Ok(Point { measurement, tags, fields, timestamp })
+}
+
+/// Reads the points of the input line by line, empty lines and comments are skipped.
+struct PointReader {
+    lines: Lines<Box<dyn AsyncBufRead + Unpin + Send>>,
+    line_number: usize,
+}
+
+impl PointReader {
+    /// Returns the next point, or an error with the line number if the line can not be parsed.
+    async fn next_point(&mut self) -> Result<Option<Point>, io::Error> {
+        while let Some(line) = self.lines.next_line().await? {
+            self.line_number += 1;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            return parse_line(&line)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Line {}: {}", self.line_number, e)));
+        }
+        Ok(None)
+    }
+}
+
+impl InfluxDBSource {
+    async fn get_reader(&self) -> Result<PointReader, io::Error> {
+        let reader: Box<dyn AsyncBufRead + Unpin + Send> = match self.path.as_str() {
+            "-" => Box::new(BufReader::new(stdin())),
+            path => Box::new(BufReader::new(File::open(path).await?))
+        };
+        Ok(PointReader { lines: reader.lines(), line_number: 0 })
+    }
+
+    /// Sends a batch of a measurement, its table is created with the types of the first batch.
+    async fn send_rows<S: Sink>(&self, schema: &str, measurement: &str, columns: Vec<String>, rows: Vec<Vec<CValue>>, created: &mut HashSet<String>, cratedb: &S) -> Result<usize, SinkError> {
+        if created.insert(measurement.to_string()) {
+            cratedb.create_table(schema, measurement, &column_definitions(&columns, &rows)).await?;
+        }
+        let documents_in_batch = rows.len();
+        cratedb.send_batch(schema, measurement, &columns, rows).await?;
+        Ok(documents_in_batch)
+    }
+
+    /// Migrates every measurement found in the input to its own table, reading the input once.
+    pub async fn migrate_all_to_cratedb<S: Sink>(&self, schema: &str, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<io::Error>> {
+        self.migrate_measurements(schema, None, ignored_columns, cratedb, metadata).await
+    }
+
+    async fn migrate_measurements<S: Sink>(&self, schema: &str, measurement: Option<&str>, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<io::Error>> {
+        let batch_size: usize = 5000;
+        let mut total_documents_sent = 0;
+        let mut batches: HashMap<String, DynamicBatch> = HashMap::new();
+        let mut created: HashSet<String> = HashSet::new();
+
+        metadata.print_step(format!("Starting reading line protocol from {}", self.path).as_str());
+
+        let mut reader = self.get_reader().await.map_err(MigrationError::Source)?;
+        while let Some(point) = reader.next_point().await.map_err(MigrationError::Source)? {
+            if measurement.is_some_and(|m| m != point.measurement) {
+                continue;
+            }
+
+            let table = point.measurement.clone();
+            let row: Vec<(String, CValue)> = self.point_to_values(point)
+                .map_err(|e| MigrationError::Source(io::Error::new(io::ErrorKind::InvalidData, format!("Line {}: {}", reader.line_number, e))))?
+                .into_iter()
+                .filter(|(name, _)| !ignored_columns.contains(&name.as_str()))
+                .collect();
+
+            let batch = batches.entry(table.clone()).or_insert_with(|| DynamicBatch::new(batch_size));
+            if let Some((columns, rows)) = batch.push(row) {
+                let documents_in_batch = self.send_rows(schema, &table, columns, rows, &mut created, &cratedb).await?;
+                total_documents_sent += documents_in_batch;
+                metadata.print_step(format!("Sent batch of {:?} to {}", &documents_in_batch, table).as_str());
+            }
+        }
+
+        // Send the remaining rows of every measurement.
+        for (measurement, batch) in batches {
+            if let Some((columns, rows)) = batch.finish() {
+                let documents_in_batch = self.send_rows(schema, &measurement, columns, rows, &mut created, &cratedb).await?;
+                total_documents_sent += documents_in_batch;
+                metadata.print_step(format!("Sent batch of {:?} to {}", &documents_in_batch, measurement).as_str());
+            }
+        }
+
+        metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
+    }
+
+    /// Returns the (column, value) pairs of a point: its timestamp, tags and fields.
+    fn point_to_values(&self, point: Point) -> Result<Vec<(String, CValue)>, ParseLineError> {
+        let timestamp = match point.timestamp {
+            Some(t) => self.precision.to_millis(t)?,
+            None => Utc::now().timestamp_millis()
+        };
+
+        let mut values: Vec<(String, CValue)> = vec![(TIME_COLUMN.to_string(), CValue::Timestamp(timestamp))];
+
+        if self.tags_as_object {
+            let tags = point.tags.into_iter().map(|(k, v)| (k, CValue::String(v))).collect();
+            values.push((TAGS_COLUMN.to_string(), CValue::Object(tags)));
+        } else {
+            values.extend(point.tags.into_iter().map(|(k, v)| (k, CValue::String(v))));
+        }
+        values.extend(point.fields);
+        Ok(values)
+    }
+}
+
+#[async_trait]
+impl Source for InfluxDBSource {
+    type ErrorType = io::Error;
+    type TableType = String;
+    type RowType = Point;
+
+    /// A line protocol export holds a single database, it is named after the file.
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        let name = match self.path.as_str() {
+            "-" => "stdin",
+            path => std::path::Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or(path)
+        };
+        Ok(vec![name.to_string()])
+    }
+
+    async fn list_tables(&self, _database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        let mut measurements: Vec<String> = vec![];
+        let mut reader = self.get_reader().await?;
+        while let Some(point) = reader.next_point().await? {
+            if !measurements.contains(&point.measurement) {
+                measurements.push(point.measurement);
+            }
+        }
+        Ok(measurements)
+    }
+
+    async fn get_table(&self, _database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        Ok(table_name.to_string())
+    }
+
+    async fn count(&self, _database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let mut count = 0;
+        let mut reader = self.get_reader().await?;
+        while let Some(point) = reader.next_point().await? {
+            if point.measurement == table_name {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        self.migrate_measurements(schema, Some(table), ignored_columns, cratedb, metadata).await
+    }
+
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
+        let values = self.point_to_values(row).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(values.into_iter().map(|(_, v)| v).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs;
+
+    use crate::experiment::data::CValue;
+    use crate::metadata::Metadata;
+    use crate::source::influxdb::driver::{parse_line, InfluxDBSource, Precision, TIME_COLUMN};
+    use crate::source::source::{MigrationError, Source};
+    use crate::testing::MemorySink;
+
+    #[test]
+    fn test_parse_line() {
+        let point = parse_line("weather,location=us-midwest,season=summer temperature=82,humidity=71i,raining=f,note=\"hot, dry\" 1465839830100400200").unwrap();
+
+        assert_eq!(point.measurement, "weather");
+        assert_eq!(point.tags, vec![
+            ("location".to_string(), "us-midwest".to_string()),
+            ("season".to_string(), "summer".to_string()),
+        ]);
+        assert!(matches!(point.fields[0], (ref k, CValue::Double64(v)) if k == "temperature" && v == 82.0));
+        assert!(matches!(point.fields[1], (ref k, CValue::I64(71)) if k == "humidity"));
+        assert!(matches!(point.fields[2], (ref k, CValue::Bool(false)) if k == "raining"));
+        assert!(matches!(point.fields[3], (ref k, CValue::String(ref v)) if k == "note" && v == "hot, dry"));
+        assert_eq!(point.timestamp, Some(1465839830100400200));
+    }
+
+    #[test]
+    fn test_parse_line_escapes_and_missing_timestamp() {
+        let point = parse_line(r#"my\ measurement,tag\,key=a\=b value="say \"hi\"""#).unwrap();
+
+        assert_eq!(point.measurement, "my measurement");
+        assert_eq!(point.tags, vec![("tag,key".to_string(), "a=b".to_string())]);
+        assert!(matches!(point.fields[0], (_, CValue::String(ref v)) if v == r#"say "hi""#));
+        assert_eq!(point.timestamp, None);
+    }
+
+    #[test]
+    fn test_parse_invalid_lines() {
+        assert!(parse_line("only_measurement").is_err());
+        assert!(parse_line("m field=notanumber").is_err());
+        assert!(parse_line("m field=1 notatimestamp").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_measurement() {
+        let path = temp_dir().join("cdctest_influxdb.lp");
+        fs::write(&path, "# exported\ncpu,host=a usage=0.5 1700000000\n\nmem,host=a used=10i 1700000000\ncpu,host=b usage=0.25 1700000001\n").unwrap();
+        let influxdb = InfluxDBSource { path: path.to_str().unwrap().to_string(), precision: Precision::Seconds, tags_as_object: false };
+
+        assert_eq!(influxdb.list_tables("").await.unwrap(), vec!["cpu", "mem"]);
+        assert_eq!(influxdb.count("", "cpu").await.unwrap(), 2);
+
+        let mut metadata = Metadata::new();
+        metadata.start();
+        let sink = MemorySink::default();
+        influxdb.migrate_table_to_cratedb("doc", &"cpu".to_string(), vec![], sink.clone(), &mut metadata).await.unwrap();
+
+        assert_eq!(*sink.columns.lock().unwrap(), vec![TIME_COLUMN, "host", "usage"]);
+        {
+            let batches = sink.batches.lock().unwrap();
+            let (columns, rows) = &batches[0];
+            assert_eq!(columns, &vec![TIME_COLUMN, "host", "usage"]);
+            assert_eq!(rows.len(), 2);
+            assert!(matches!(rows[0][0], CValue::Timestamp(1_700_000_000_000)));
+            assert!(matches!(&rows[1][1], CValue::String(host) if host == "b"));
+        }
+
+        // A timestamp in seconds that does not fit in milliseconds is an error, not a wrapped value.
+        fs::write(&path, format!("cpu,host=a usage=0.5 {}\n", i64::MAX / 10)).unwrap();
+        let result = influxdb.migrate_table_to_cratedb("doc", &"cpu".to_string(), vec![], MemorySink::default(), &mut metadata).await;
+        assert!(matches!(result, Err(MigrationError::Source(e)) if e.to_string().contains("Line 1")));
+
+        fs::write(&path, "cpu,host=a usage=0.5\ncpu usage=\n").unwrap();
+        assert!(influxdb.count("", "cpu").await.is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
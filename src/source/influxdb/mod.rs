@@ -0,0 +1 @@
+pub mod driver;
\ No newline at end of file
@@ -1,5 +1,16 @@
 pub mod mongodb;
 pub mod postgres;
+#[allow(clippy::module_inception)]
 pub mod source;
+pub mod batch;
 pub mod mysql;
-
+pub mod elasticsearch;
+pub mod influxdb;
+pub mod clickhouse;
+pub mod redis;
+pub mod arrow;
+pub mod file;
+pub mod objectstore;
+pub mod filter;
+pub mod watermark;
+pub mod database;
\ No newline at end of file
@@ -1,20 +1,108 @@
-use std::collections::HashMap;
-use std::fmt;
-use std::str::FromStr;
+use std::cmp::Ordering;
 use async_trait::async_trait;
 
 use mongodb::bson::{Bson, doc, Document};
+use mongodb::error::ErrorKind;
 use mongodb::{Client, Collection, Database};
 
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::Serialize;
 use crate::experiment::data::CValue;
+use crate::experiment::predicate::Predicate;
 use crate::metadata::Metadata;
-use crate::sink::cratedb::driver::CrateDB;
-use crate::source::source::{Sink, Source};
+use crate::source::batch::DynamicBatch;
+use crate::source::filter::{get_filter, Filters};
+use crate::source::source::{MigrationError, Sink, SinkError, Source};
+use crate::source::watermark::{Incremental, Watermark};
 
 #[derive(Debug)]
 pub struct MongoDBSource {
     pub(crate) uri: String,
+    pub(crate) filters: Filters,
+    pub(crate) incremental: Incremental,
+}
+
+impl MongoDBSource {
+    pub fn new(uri: &str) -> Self {
+        Self { uri: uri.to_string(), filters: Filters::new(), incremental: Incremental::default() }
+    }
+
+    /// Only migrates the documents of `table` (`<database>.<collection>` or `<collection>`) matching
+    /// `predicate`. It is sent as the query document, so it can not have raw SQL: reading the
+    /// collection returns an error.
+    pub fn with_filter(mut self, table: &str, predicate: Predicate) -> Self {
+        self.filters.insert(table.to_string(), predicate);
+        self
+    }
+
+    /// Loads collections incrementally, see `Incremental`. Watermarks are saved as extended JSON so
+    /// dates and ObjectIds keep their type.
+    pub fn with_incremental(mut self, incremental: Incremental) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Returns the query document of a collection, its filter and the documents from its watermark on.
+    fn get_query(&self, table: &Collection<Document>) -> Result<Document, mongodb::error::Error> {
+        let database = table.namespace().db;
+        let mut conditions: Vec<Document> = vec![];
+
+        if let Some(predicate) = get_filter(&self.filters, &database, table.name()) {
+            match predicate.to_mongo() {
+                Some(query) => conditions.push(query),
+                None => return Err(ErrorKind::from(mongodb::bson::de::Error::custom(format!("Filter of {} has raw SQL, it can not be pushed down to MongoDB", table.name()))).into())
+            }
+        }
+
+        if let Some(watermark) = self.incremental.get_watermark(&database, table.name()) {
+            match Bson::try_from(watermark.value) {
+                Ok(value) => conditions.push(doc! { watermark.column: { "$gte": value } }),
+                Err(e) => return Err(ErrorKind::from(mongodb::bson::de::Error::custom(format!("Could not read the watermark of {} - {}", table.name(), e))).into())
+            }
+        }
+
+        Ok(match conditions.len() {
+            0 => doc! {},
+            1 => conditions.remove(0),
+            _ => doc! { "$and": conditions }
+        })
+    }
+
+    /// Keeps the largest value of the watermark column, `max` is None until a document has one.
+    fn track_watermark(max: &mut Option<Bson>, document: &Document, column: &str) {
+        let Some(value) = document.get(column) else { return };
+        let is_greater = match max {
+            Some(max) => bson_cmp(value, max) == Some(Ordering::Greater),
+            None => !matches!(value, Bson::Null)
+        };
+        if is_greater {
+            *max = Some(value.clone());
+        }
+    }
+
+    fn save_watermark(&self, table: &Collection<Document>, column: &str, max: Option<Bson>, metadata: &mut Metadata) -> Result<(), SinkError> {
+        let watermark = max.map(|max| Watermark { column: column.to_string(), value: max.into_canonical_extjson() });
+        self.incremental.finish_table(&table.namespace().db, table.name(), watermark, metadata)
+    }
+}
+
+/// Orders the values a watermark column can have: dates, timestamps, numbers, strings and ObjectIds.
+fn bson_cmp(a: &Bson, b: &Bson) -> Option<Ordering> {
+    let as_f64 = |v: &Bson| match v {
+        Bson::Int32(v) => Some(*v as f64),
+        Bson::Int64(v) => Some(*v as f64),
+        Bson::Double(v) => Some(*v),
+        _ => None
+    };
+
+    match (a, b) {
+        (Bson::DateTime(a), Bson::DateTime(b)) => Some(a.cmp(b)),
+        (Bson::Timestamp(a), Bson::Timestamp(b)) => Some((a.time, a.increment).cmp(&(b.time, b.increment))),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => Some(a.bytes().cmp(&b.bytes())),
+        (Bson::String(a), Bson::String(b)) => Some(a.cmp(b)),
+        (Bson::Int64(a), Bson::Int64(b)) => Some(a.cmp(b)),
+        _ => as_f64(a)?.partial_cmp(&as_f64(b)?)
+    }
 }
 
 
@@ -25,17 +113,28 @@ pub struct MongoDBSource {
 
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
-pub(crate) enum StringRow {
+pub enum StringRow {
     String(String),
     I32(i32),
-    U32(u32),
     I64(i64),
     Vec(Vec<String>),
-    VecI32(Vec<i32>),
     VecF64(Vec<f64>),
     None,
 }
 
+impl From<StringRow> for CValue {
+    fn from(value: StringRow) -> Self {
+        match value {
+            StringRow::String(v) => CValue::String(v),
+            StringRow::I32(v) => CValue::I32(v),
+            StringRow::I64(v) => CValue::I64(v),
+            StringRow::Vec(v) => CValue::VecString(v),
+            StringRow::VecF64(v) => CValue::VecF64(v),
+            StringRow::None => CValue::None,
+        }
+    }
+}
+
 
 fn bson_to_normalized_row(row: Bson) -> CValue {
     match row {
@@ -43,7 +142,8 @@ fn bson_to_normalized_row(row: Bson) -> CValue {
         Bson::String(v) => CValue::String(v),
         Bson::Int32(v) => CValue::I32(v),
         Bson::Int64(v) => CValue::I64(v),
-        Bson::Decimal128(v) => CValue::Double64(v.to_string().parse().unwrap()),
+        // Kept exact, a f64 would round it.
+        Bson::Decimal128(v) => CValue::Decimal(v.to_string()),
         Bson::ObjectId(v) => CValue::String(v.to_string()),
         Bson::Null => CValue::None,
         Bson::Array(v) => {
@@ -59,9 +159,9 @@ fn bson_to_normalized_row(row: Bson) -> CValue {
                 // Array has contents
                 Some(first_element) => {
                     match first_element {
-                        Bson::Double(_) => CValue::VecF64(v.into_iter().map(|x| x.as_f64().expect(format!("Could not unwrap value of type '{:?}' to f64, is your array of mixed types?", x.element_type()).as_str())).collect::<Vec<f64>>()),
-                        Bson::Int32(_) => CValue::VecI32(v.into_iter().map(|x| x.as_i32().expect(format!("Could not unwrap value of type '{:?}' to i32, is your array of mixed types?", x.element_type()).as_str())).collect::<Vec<i32>>()),
-                        Bson::Int64(_) => CValue::VecI64(v.into_iter().map(|x| x.as_i64().expect(format!("Could not unwrap value of type '{:?}' to i64, is your array of mixed types?", x.element_type()).as_str())).collect::<Vec<i64>>()),
+                        Bson::Double(_) => CValue::VecF64(v.into_iter().map(|x| x.as_f64().unwrap_or_else(|| panic!("Could not unwrap value of type '{:?}' to f64, is your array of mixed types?", x.element_type()))).collect::<Vec<f64>>()),
+                        Bson::Int32(_) => CValue::VecI32(v.into_iter().map(|x| x.as_i32().unwrap_or_else(|| panic!("Could not unwrap value of type '{:?}' to i32, is your array of mixed types?", x.element_type()))).collect::<Vec<i32>>()),
+                        Bson::Int64(_) => CValue::VecI64(v.into_iter().map(|x| x.as_i64().unwrap_or_else(|| panic!("Could not unwrap value of type '{:?}' to i64, is your array of mixed types?", x.element_type()))).collect::<Vec<i64>>()),
                         _ => CValue::VecString(v.into_iter().map(|x| {
                             match x {
                                 Bson::String(s) => s,
@@ -72,7 +172,7 @@ fn bson_to_normalized_row(row: Bson) -> CValue {
                 }
             }
         }
-        Bson::DateTime(v) => CValue::I64(v.timestamp_millis()),
+        Bson::DateTime(v) => CValue::Timestamp(v.timestamp_millis()),
         // Bson::Array(v) => v.into_iter().map(|x| bson_to_normalized_row(x)).collect(),
         _ => {
             CValue::String(row.to_string())
@@ -117,117 +217,104 @@ fn bson_to_string(row: Bson) -> StringRow {
     }
 }
 
-#[async_trait]
-impl Source for MongoDBSource {
-    type PoolType = (); // Placeholder type, replace it with an actual MongoDB pool type
-    type ErrorType = ();
-    type ClientType = Client;
-    type DatabaseType = Database;
-    type TableType = Collection<Document>;
-    type RowType = Document;
-
-    async fn get_pool(&self) -> Result<Self::PoolType, Self::ErrorType> {
-        todo!() // MongoDB handles pooling internally.
-    }
-    async fn get_client(&self) -> Result<Self::ClientType, Self::ErrorType> {
-        let client = Client::with_uri_str(&self.uri).await;
-        Ok(client.unwrap())
-    }
-    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
-        let client = self.get_client().await;
-        let databases = client.unwrap().list_database_names().await;
-        Ok(databases.unwrap())
-    }
-    async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType> {
-        let client = self.get_client().await;
-        let collections = client.unwrap().database(&database).list_collection_names().await;
-        Ok(collections.unwrap())
-    }
-    async fn get_database(&self, database: &str) -> Result<Self::DatabaseType, Self::ErrorType> {
-        let client = self.get_client().await;
-        let database = client?.database(&database);
-        Ok(database)
-    }
-    async fn get_table(&self, database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
-        Ok(self.get_database(&database).await?.collection(&table_name))
+impl MongoDBSource {
+    pub(crate) async fn get_client(&self) -> Result<Client, mongodb::error::Error> {
+        Client::with_uri_str(&self.uri).await
     }
 
-    async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
-        todo!()
+    pub(crate) async fn get_database(&self, database: &str) -> Result<Database, mongodb::error::Error> {
+        Ok(self.get_client().await?.database(database))
     }
 
-    async fn migrate_table_to_cratedb_pg(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata) {
-        println!("Starting migrating table {:?} to CrateDB {:?}", table.name(), cratedb);
+    /// Migrates a collection like `migrate_table_to_cratedb` but sends `CValue`s through
+    /// `send_batch`, so the sink gets typed values instead of `StringRow`s.
+    pub async fn migrate_table_to_cratedb_pg<S: Sink>(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<mongodb::error::Error>> {
+        metadata.print_step(format!("Starting migrating table {:?} to CrateDB {:?}", table.name(), cratedb).as_str());
         let mut total_documents_sent = 0;
         let batch_size: usize = 3000;
-        let mut cursor = table.find(doc! {}).batch_size(batch_size as u32).await.expect("Could not create a cursor");
+        let mut cursor = table.find(self.get_query(table).map_err(MigrationError::Source)?).batch_size(batch_size as u32).await.map_err(MigrationError::Source)?;
 
-        let mut buffer: Vec<Vec<CValue>> = vec![];
-        let mut columns: Vec<String> = vec![];
-        let mut last_columns: Vec<String> = vec![];
+        // Documents do not have to share fields, a batch is flushed when a document brings a new
+        // one so the sink can evolve the table (see `SchemaEvolution`) before receiving it.
+        let mut batch = DynamicBatch::new(batch_size);
 
-        let mut batch_document_len: Option<usize> = None;
+        let watermark_column = self.incremental.get_column(&table.namespace().db, table.name()).cloned();
+        let mut watermark: Option<Bson> = None;
 
         metadata.print_step("Starting connection to MongoDB");
 
-        while cursor.advance().await.expect("Could not advance cursor; maybe connection was lost") {
+        while cursor.advance().await.map_err(MigrationError::Source)? {
             let mut document = cursor.deserialize_current().unwrap();
 
+            if let Some(column) = &watermark_column {
+                Self::track_watermark(&mut watermark, &document, column);
+            }
             for column in &ignored_columns {
                 document.remove(column);
             }
 
-            match batch_document_len {
-                Some(_) => (),
-                None => batch_document_len = Some(document.len())
-            }
-
-            let has_doc_len_changed = &batch_document_len.unwrap() != &document.len().clone();
-
-            // column is empty on new batches.
-            if columns.is_empty() {
-                columns = document.keys().map(|x| String::from(x)).collect();
-            }
-
-            if has_doc_len_changed || last_columns.is_empty() {
-                // This fixes getting the columns for the last batch.
-                last_columns = document.keys().map(|x| String::from(x)).collect();
-            }
+            let row: Vec<(String, CValue)> = document.into_iter().map(|(k, v)| (k, bson_to_normalized_row(v))).collect();
 
-            let normalized_row = self.row_to_normalized_row(document).await;
-
-            if has_doc_len_changed || &buffer.len() == &batch_size {
-                let documents_in_batch: usize = buffer.len();
-                cratedb.send_batch(&schema, &table.name(), &columns, buffer).await;
+            if let Some((columns, rows)) = batch.push(row) {
+                let documents_in_batch: usize = rows.len();
+                cratedb.send_batch(schema, table.name(), &columns, rows).await?;
 
                 total_documents_sent += &documents_in_batch;
                 metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
-
-                // Clear up all temporal containers for the next iteration.
-                buffer = vec![];
-                batch_document_len = None;
-                columns.clear();
             }
-            buffer.push(normalized_row);
         }
 
         // There is still a last batch.
-        // Rows of the last batch are guaranteed to be of the same length.
-        if !buffer.is_empty() {
-            let documents_in_batch: usize = buffer.len();
+        if let Some((columns, rows)) = batch.finish() {
+            let documents_in_batch: usize = rows.len();
             total_documents_sent += &documents_in_batch;
-            cratedb.send_batch(&schema, &table.name(), &last_columns, buffer).await
+            cratedb.send_batch(schema, table.name(), &columns, rows).await?;
+            metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
+        }
+
+        if let Some(column) = &watermark_column {
+            self.save_watermark(table, column, watermark, metadata)?;
         }
 
         metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
-        metadata.print_step(format!("Rows per seconds: {}", (total_documents_sent as u128) / metadata.elapsed().as_millis() * 1000).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
     }
+    pub(crate) fn row_to_vec_str(&self, row: Document) -> Vec<StringRow> {
+        let mut rows: Vec<StringRow> = Vec::new();
+        for (_, value) in row {
+            rows.push(bson_to_string(value))
+        }
+        rows
+    }
+}
 
-    async fn migrate_table_to_cratedb(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata) {
-        println!("Starting migrating table {:?} to CrateDB {:?}", table.name(), cratedb);
+#[async_trait]
+impl Source for MongoDBSource {
+    type ErrorType = mongodb::error::Error;
+    type TableType = Collection<Document>;
+    type RowType = Document;
+
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        self.get_client().await?.list_database_names().await
+    }
+    async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        self.get_database(database).await?.list_collection_names().await
+    }
+    async fn get_table(&self, database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        Ok(self.get_database(database).await?.collection(table_name))
+    }
+
+    async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let table = self.get_table(database, table_name).await?;
+        Ok(table.count_documents(self.get_query(&table)?).await? as i64)
+    }
+
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        metadata.print_step(format!("Starting migrating table {:?} to CrateDB {:?}", table.name(), cratedb).as_str());
         let batch_size: usize = 1;
         let mut total_documents_sent = 0;
-        let mut cursor = table.find(doc! {}).batch_size(batch_size as u32).await.expect("Could not create a cursor");
+        let mut cursor = table.find(self.get_query(table).map_err(MigrationError::Source)?).batch_size(batch_size as u32).await.map_err(MigrationError::Source)?;
 
         let mut buffer: Vec<Vec<StringRow>> = vec![];
         let mut current_batch_columns: Vec<String> = vec![];
@@ -235,11 +322,15 @@ impl Source for MongoDBSource {
 
         let mut batch_document_len: Option<usize> = None;
 
+        let watermark_column = self.incremental.get_column(&table.namespace().db, table.name()).cloned();
+        let mut watermark: Option<Bson> = None;
+
         metadata.print_step("Starting connection to MongoDB");
-        println!("??Asdf");
-        while cursor.advance().await.expect("Could not advance cursor; maybe connection was lost") {
+        while cursor.advance().await.map_err(MigrationError::Source)? {
             let mut document = cursor.deserialize_current().unwrap();
-            println!("{:?} focking doc mate", document);
+            if let Some(column) = &watermark_column {
+                Self::track_watermark(&mut watermark, &document, column);
+            }
             for column in &ignored_columns {
                 document.remove(column);
             }
@@ -249,23 +340,23 @@ impl Source for MongoDBSource {
                 None => batch_document_len = Some(document.len())
             }
 
-            let has_doc_len_changed = &batch_document_len.unwrap() != &document.len().clone();
+            let has_doc_len_changed = batch_document_len.unwrap() != document.len();
 
             // column is empty on new batches.
             if current_batch_columns.is_empty() {
-                current_batch_columns = document.keys().map(|x| String::from(x)).collect();
+                current_batch_columns = document.keys().map(String::from).collect();
             }
 
             if has_doc_len_changed || last_columns.is_empty() {
                 // This fixes getting the columns for the last batch.
-                last_columns = document.keys().map(|x| String::from(x)).collect();
+                last_columns = document.keys().map(String::from).collect();
             }
 
             let normalized_row = self.row_to_vec_str(document);
 
-            if has_doc_len_changed || &buffer.len() == &batch_size {
+            if has_doc_len_changed || buffer.len() == batch_size {
                 let documents_in_batch: usize = buffer.len();
-                cratedb.send_batch_http(&schema, &table.name(), &current_batch_columns, buffer).await;
+                cratedb.send_batch_http(schema, table.name(), &current_batch_columns, buffer).await?;
 
                 total_documents_sent += &documents_in_batch;
                 metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
@@ -283,28 +374,73 @@ impl Source for MongoDBSource {
         if !buffer.is_empty() {
             let documents_in_batch: usize = buffer.len();
             total_documents_sent += &documents_in_batch;
-            cratedb.send_batch_http(&schema, &table.name(), &last_columns, buffer).await;
+            cratedb.send_batch_http(schema, table.name(), &last_columns, buffer).await?;
             total_documents_sent += &documents_in_batch;
             metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
         }
 
+        if let Some(column) = &watermark_column {
+            self.save_watermark(table, column, watermark, metadata)?;
+        }
+
         metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
-        metadata.print_step(format!("Rows per seconds: {}", (total_documents_sent as u128) / metadata.elapsed().as_millis() * 1000).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
     }
 
-    async fn row_to_normalized_row(&self, row: Self::RowType) -> Vec<CValue> {
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
         let mut rows: Vec<CValue> = Vec::new();
         for (_, value) in row {
             rows.push(bson_to_normalized_row(value))
         }
-        return rows;
+        Ok(rows)
     }
+}
 
-    fn row_to_vec_str(&self, row: Self::RowType) -> Vec<StringRow> {
-        let mut rows: Vec<StringRow> = Vec::new();
-        for (_, value) in row {
-            rows.push(bson_to_string(value))
-        }
-        rows
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use mongodb::bson::{doc, oid::ObjectId, Bson, DateTime, Document};
+    use mongodb::Client;
+
+    use crate::experiment::data::CValue;
+    use crate::experiment::predicate::Predicate;
+    use crate::source::mongodb::driver::{bson_cmp, MongoDBSource};
+
+    #[test]
+    fn test_watermark_round_trip() {
+        let earlier = Bson::DateTime(DateTime::from_millis(1_700_000_000_000));
+        let later = Bson::DateTime(DateTime::from_millis(1_700_000_000_001));
+        assert_eq!(bson_cmp(&later, &earlier), Some(Ordering::Greater));
+        assert_eq!(bson_cmp(&Bson::Int32(2), &Bson::Double(1.5)), Some(Ordering::Greater));
+        assert_eq!(bson_cmp(&Bson::String("a".to_string()), &Bson::Int32(1)), None);
+
+        let mut max = None;
+        MongoDBSource::track_watermark(&mut max, &doc! { "updated_at": earlier.clone() }, "updated_at");
+        MongoDBSource::track_watermark(&mut max, &doc! { "updated_at": Bson::Null }, "updated_at");
+        MongoDBSource::track_watermark(&mut max, &doc! { "updated_at": later.clone() }, "updated_at");
+        MongoDBSource::track_watermark(&mut max, &doc! { "other": 1 }, "updated_at");
+        assert_eq!(max, Some(later.clone()));
+
+        // Watermarks are saved as extended JSON so they are read back with their type.
+        assert_eq!(Bson::try_from(later.clone().into_canonical_extjson()).unwrap(), later);
+        let id = Bson::ObjectId(ObjectId::new());
+        assert_eq!(Bson::try_from(id.clone().into_canonical_extjson()).unwrap(), id);
+    }
+
+    #[tokio::test]
+    async fn test_query_with_sql_filter() {
+        // The client does not connect until it is used.
+        let client = Client::with_uri_str("mongodb://localhost").await.unwrap();
+        let collection = client.database("testdb").collection::<Document>("users");
+
+        let mongodb = MongoDBSource::new("mongodb://localhost")
+            .with_filter("users", Predicate::Eq("tenant".to_string(), CValue::I32(1)));
+        assert_eq!(mongodb.get_query(&collection).unwrap(), doc! { "tenant": { "$eq": 1 } });
+
+        // Raw SQL is an error when the collection is read, not a panic.
+        let mongodb = mongodb.with_filter("users", Predicate::Sql("tenant = 1".to_string()));
+        assert!(mongodb.get_query(&collection).unwrap_err().to_string().contains("raw SQL"));
     }
 }
@@ -1,20 +1,18 @@
-use std::collections::HashSet;
 use std::net::IpAddr;
 use std::time::Duration;
 use async_trait::async_trait;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use futures::StreamExt;
-use mongodb::bson::Document;
-use mongodb::Collection;
 use serde_json::Value;
 
 use sqlx::mysql::{MySqlPoolOptions, MySqlRow};
 use sqlx::{Column, MySqlPool, Row, TypeInfo};
-use crate::experiment::data::CValue;
+use crate::experiment::data::{date_to_epoch_days, CValue};
+use crate::experiment::predicate::Predicate;
 use crate::metadata::Metadata;
-use crate::sink::cratedb::driver::CrateDB;
-use crate::source::mongodb::driver::{StringRow};
-use crate::source::source::{Sink, Source};
+use crate::source::filter::{get_filter, Dialect, Filters};
+use crate::source::source::{MigrationError, Sink, Source};
+use crate::source::watermark::{Incremental, WatermarkTracker};
 macro_rules! mysql2normalized {
     ($row:expr, $name:expr, $type_name:ty, $result_type:expr) => {
         {
@@ -51,110 +49,184 @@ macro_rules! mysql2normalized {
 }
 pub struct MySqlSource {
     pub uri: String,
+    pub(crate) filters: Filters,
+    pub(crate) incremental: Incremental,
 }
 
-#[async_trait]
-impl Source for MySqlSource {
-    type PoolType = MySqlPool;
-    type ErrorType = sqlx::Error;
-    type ClientType = ();
-    type DatabaseType = ();
-    type TableType = String;
-    type RowType = MySqlRow;
+impl MySqlSource {
+    pub fn new(uri: &str) -> Self {
+        Self { uri: uri.to_string(), filters: Filters::new(), incremental: Incremental::default() }
+    }
 
-    async fn get_pool(&self) -> Result<Self::PoolType, Self::ErrorType> {
+    /// Only migrates the rows of `table` (`<schema>.<table>` or `<table>`) matching `predicate`. It is
+    /// added to the `WHERE` clause of the incremental watermark; dotted columns are JSON paths, `$.a.b`.
+    pub fn with_filter(mut self, table: &str, predicate: Predicate) -> Self {
+        self.filters.insert(table.to_string(), predicate);
+        self
+    }
+
+    /// Loads tables incrementally, see `Incremental`.
+    pub fn with_incremental(mut self, incremental: Incremental) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    pub(crate) async fn get_pool(&self) -> Result<MySqlPool, sqlx::Error> {
         let pool = MySqlPoolOptions::new()
             .max_connections(5)
             .acquire_timeout(Duration::new(5, 0))
-            .connect(&*self.uri)
+            .connect(&self.uri)
             .await?;
         Ok(pool)
     }
 
-    async fn get_client(&self) -> Result<Self::ClientType, Self::ErrorType> {
-        todo!()
+    /// Returns the columns of a table in order with their CrateDB type, None for the JSON ones, and
+    /// `PRIMARY KEY` appended to the columns of the primary key.
+    async fn get_columns(&self, pool: &MySqlPool, schema: &str, table_name: &str) -> Result<Vec<(String, Option<String>)>, sqlx::Error> {
+        let rows: Vec<(String, String, String, String)> = sqlx::query_as(
+            "SELECT CAST(column_name AS CHAR), CAST(data_type AS CHAR), CAST(column_type AS CHAR), CAST(column_key AS CHAR) \
+             FROM information_schema.columns WHERE table_schema = ? AND table_name = ? ORDER BY ordinal_position"
+        )
+            .bind(schema)
+            .bind(table_name)
+            .fetch_all(pool)
+            .await?;
+        Ok(rows.into_iter().map(|(name, data_type, column_type, column_key)| {
+            let ddl = mysql_type_to_cratedb(&data_type, &column_type).map(|ddl| match column_key.as_str() {
+                "PRI" => format!("{} PRIMARY KEY", ddl),
+                _ => ddl
+            });
+            (name, ddl)
+        }).collect())
     }
+}
+
+/// Quotes a MySQL identifier with backticks.
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Maps the `data_type` of a MySQL column to a CrateDB type, `column_type` tells booleans,
+/// `tinyint(1)`, and unsigned integers apart. JSON is None: it holds objects or arrays, CrateDB
+/// infers its type on the first insert.
+fn mysql_type_to_cratedb(data_type: &str, column_type: &str) -> Option<String> {
+    let ddl = match data_type {
+        "tinyint" if column_type == "tinyint(1)" => "BOOLEAN",
+        "tinyint" | "smallint" => "SMALLINT",
+        "mediumint" | "int" => "INTEGER",
+        "bigint" if column_type.ends_with("unsigned") => "NUMERIC",
+        "bigint" => "BIGINT",
+        "float" => "REAL",
+        "double" => "DOUBLE PRECISION",
+        "decimal" => "NUMERIC",
+        "datetime" => "TIMESTAMP WITHOUT TIME ZONE",
+        "timestamp" | "date" => "TIMESTAMP WITH TIME ZONE",
+        "json" => return None,
+        _ => "TEXT"
+    };
+    Some(ddl.to_string())
+}
+
+#[async_trait]
+impl Source for MySqlSource {
+    type ErrorType = sqlx::Error;
+    type TableType = String;
+    type RowType = MySqlRow;
 
     async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
-        todo!()
+        let pool = self.get_pool().await?;
+        sqlx::query_scalar(
+            "SELECT CAST(schema_name AS CHAR) FROM information_schema.schemata \
+             WHERE schema_name NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys') ORDER BY schema_name"
+        )
+            .fetch_all(&pool)
+            .await
     }
 
     async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType> {
-        todo!()
-    }
-
-    async fn get_database(&self, database: &str) -> Result<Self::DatabaseType, Self::ErrorType> {
-        todo!()
+        let pool = self.get_pool().await?;
+        sqlx::query_scalar(
+            "SELECT CAST(table_name AS CHAR) FROM information_schema.tables \
+             WHERE table_schema = ? AND table_type = 'BASE TABLE' ORDER BY table_name"
+        )
+            .bind(database)
+            .fetch_all(&pool)
+            .await
     }
 
-    async fn get_table(&self, database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
-        todo!()
+    async fn get_table(&self, _database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        Ok(table_name.to_string())
     }
 
     async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
-        todo!()
+        let pool = self.get_pool().await?;
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}.{}", quote_identifier(database), quote_identifier(table_name))).fetch_one(&pool).await
     }
 
-    async fn migrate_table_to_cratedb_pg(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata) {
-        todo!()
-    }
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        let pool = self.get_pool().await.map_err(MigrationError::Source)?;
+        let mut total_documents_sent = 0;
+        let batch_size = 1000;
 
-    async fn migrate_table_to_cratedb(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata) {
-        let pool = self.get_pool().await.unwrap();
-        if !ignored_columns.is_empty() {
-            let mut columns: Vec<String> = vec![];
-            let query_limit = format!("SELECT * FROM {}.{} LIMIT 1", schema, table);
-            let one_row = sqlx::query(&*query_limit).fetch_one(&pool).await;
-            let mut total_documents_sent = 0;
-            let batch_size = 1000;
-
-            let mut buffer: Vec<Vec<CValue>> = vec![];
-
-            match one_row {
-                Ok(row) => {
-                    let one_row_columns: Vec<String> = row.columns().iter().map(|x| String::from(x.name())).collect();
-                    let set1: HashSet<String> = one_row_columns.into_iter().collect();
-                    let set2: HashSet<String> = ignored_columns.into_iter().map(|i| String::from(i)).collect();
-
-                    let difference: HashSet<String> = set1.difference(&set2).cloned().collect();
-                    columns = difference.into_iter().collect();
-                    println!("{:?}", columns)
-                }
-                Err(e) => panic!("{}", format!("{} - Could not get a LIMIT one row to ignore columns. Is there data in the table?", e))
-            }
-            let query_1 = format!("SELECT {} FROM {}.{}", &columns.join(","), &schema, &table);
-            let mut rows_stream = sqlx::query(&query_1).fetch(&pool);
+        let mut buffer: Vec<Vec<CValue>> = vec![];
 
-            metadata.print_step("Starting connections");
+        // The target table gets the source's primary key, rows read again after the watermark are
+        // upserted instead of inserted twice.
+        let source_columns: Vec<(String, Option<String>)> = self.get_columns(&pool, schema, table)
+            .await
+            .map_err(MigrationError::Source)?
+            .into_iter()
+            .filter(|(name, _)| !ignored_columns.contains(&name.as_str()))
+            .collect();
+        let definitions: Vec<(String, String)> = source_columns
+            .iter()
+            .filter_map(|(name, ddl)| Some((name.clone(), ddl.clone()?)))
+            .collect();
+        cratedb.create_table(schema, table, &definitions).await?;
 
-            while let Some(row) = rows_stream.next().await {
-                let mut normalized_row: Vec<CValue> = vec![];
-                let mut columns: Vec<String> = vec![];
+        let columns: Vec<String> = source_columns.into_iter().map(|(name, _)| name).collect();
+        let selected: Vec<String> = columns.iter().map(|name| quote_identifier(name)).collect();
+        let mut query_1 = format!("SELECT {} FROM {}.{}", selected.join(", "), quote_identifier(schema), quote_identifier(table));
+        if let Some(predicate) = self.incremental.predicate(schema, table, get_filter(&self.filters, schema, table)) {
+            query_1 = format!("{} WHERE {}", query_1, predicate.to_sql(Dialect::MySql));
+        }
+        let mut watermark = self.incremental.get_column(schema, table).map(|column| WatermarkTracker::new(column));
+        let mut rows_stream = sqlx::query(&query_1).fetch(&pool);
 
-                match row {
-                    Ok(row_obj) => {
-                        columns = row_obj.columns().iter().map(|x| String::from(x.name())).collect();
-                        normalized_row = self.row_to_normalized_row(row_obj).await;
-                    }
-                    Err(e) => {}
-                }
+        metadata.print_step("Starting connections");
 
-                let buffer_len = &buffer.len();
+        while let Some(row) = rows_stream.next().await {
+            let row_obj = row.map_err(MigrationError::Source)?;
+            let normalized_row = self.row_to_normalized_row(row_obj).await.map_err(MigrationError::Source)?;
+            if let Some(watermark) = &mut watermark {
+                watermark.observe(&columns, &normalized_row);
+            }
 
-                if buffer_len == &batch_size {
-                    cratedb.send_batch("doc", &table, &columns, buffer).await;
-                    metadata.print_step(format!("Sent batch of {}", buffer_len).as_str());
-                    total_documents_sent += buffer_len;
-                    buffer = vec![];
-                }
-                buffer.push(normalized_row);
+            let buffer_len = &buffer.len();
+
+            if buffer_len == &batch_size {
+                cratedb.send_batch(schema, table, &columns, buffer).await?;
+                metadata.print_step(format!("Sent batch of {}", buffer_len).as_str());
+                total_documents_sent += buffer_len;
+                buffer = vec![];
             }
-            metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
-            metadata.print_step(format!("Rows per seconds: {}", (total_documents_sent as u128) / metadata.elapsed().as_millis() * 1000).as_str());
+            buffer.push(normalized_row);
+        }
+
+        if !buffer.is_empty() {
+            let buffer_len = &buffer.len();
+            cratedb.send_batch(schema, table, &columns, buffer).await?;
+            metadata.print_step(format!("Sent batch of {}", &buffer_len).as_str());
+            total_documents_sent += buffer_len;
         }
+
+        self.incremental.finish_table(schema, table, watermark.and_then(|w| w.finish()), metadata)?;
+        metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
     }
 
-    async fn row_to_normalized_row(&self, row: Self::RowType) -> Vec<CValue> {
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
         let mut new_row = vec![];
         for col in row.columns() {
             let name = col.name();
@@ -166,7 +238,7 @@ impl Source for MySqlSource {
                     new_row.push(mysql2normalized!(row, name, String, CValue::String))
                 }
                 "INET" => {
-                    new_row.push(mysql2normalized!(row, name, IpAddr, CValue::String, |v:IpAddr|v.to_string()))
+                    new_row.push(mysql2normalized!(row, name, IpAddr, CValue::Ip))
                 }
                 "SMALLINT" => {
                     new_row.push(mysql2normalized!(row, name, i16, CValue::I16))
@@ -181,10 +253,16 @@ impl Source for MySqlSource {
                     new_row.push(mysql2normalized!(row, name, bool, CValue::Bool))
                 }
                 "DATETIME" => {
-                    new_row.push(mysql2normalized!(row, name, NaiveDateTime, CValue::String, |v: NaiveDateTime|v.to_string()));
+                    new_row.push(mysql2normalized!(row, name, NaiveDateTime, CValue::Timestamp, |v: NaiveDateTime|v.and_utc().timestamp_millis()));
+                }
+                "TIMESTAMP" => {
+                    new_row.push(mysql2normalized!(row, name, DateTime<Utc>, CValue::Timestamp, |v: DateTime<Utc>|v.timestamp_millis()));
+                }
+                "DATE" => {
+                    new_row.push(mysql2normalized!(row, name, NaiveDate, CValue::Date, date_to_epoch_days));
                 }
                 "JSON" => {
-                    let val: Value = row.try_get(name).unwrap();
+                    let val: Value = row.try_get(name)?;
                     match val {
                         Value::Array(arr) => {
                             let first = arr.first();
@@ -221,18 +299,10 @@ impl Source for MySqlSource {
                 }
 
                 _ => {
-                    let val = row.try_get(name);
-                    match val {
-                        Ok(v) => new_row.push(CValue::String(v)),
-                        Err(e) => panic!("Datatype is {} - {} ", type_name, e)
-                    }
+                    new_row.push(CValue::String(row.try_get(name)?));
                 }
             };
         };
-        return new_row;
-    }
-
-    fn row_to_vec_str(&self, row: Self::RowType) -> Vec<StringRow> {
-        todo!()
+        Ok(new_row)
     }
 }
\ No newline at end of file
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use glob::Pattern;
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path;
+use object_store::{GetResult, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
+
+use crate::experiment::data::CValue;
+use crate::metadata::Metadata;
+use crate::source::batch::{column_definitions, DynamicBatch};
+use crate::source::file::format::{read_json, read_parquet, FileFormat, FileRow, RecordReader};
+use crate::source::source::{MigrationError, Sink, Source};
+
+/// Source for files in an object storage bucket, e.g. S3, MinIO or any S3-compatible service.
+///
+/// Objects under `prefix` are grouped into tables by their parent "directory", so
+/// `exports/users/2024-01.jsonl` belongs to the table `users` when the prefix is `exports`.
+/// Objects directly under the prefix belong to the table named after the prefix's last segment.
+///
+/// Supported formats are JSON, JSON Lines, CSV and Parquet, detected by extension. If
+/// `checkpoint_path` is set, the objects already migrated are saved there and skipped on the
+/// next run unless they changed; for an object that failed midway the rows already sent are saved
+/// and skipped. Objects that can not be read are an error and are not saved.
+pub struct ObjectStoreSource {
+    pub(crate) store: Arc<dyn ObjectStore>,
+    pub(crate) prefix: String,
+    /// Glob matched against the object's key relative to `prefix`, e.g. `**/*.parquet`.
+    pub(crate) glob: Option<String>,
+    pub(crate) checkpoint_path: Option<String>,
+}
+
+/// Objects already migrated, key -> e_tag (or last modified date if the store has no e_tags), and
+/// the progress of objects whose migration stopped midway.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub objects: HashMap<String, String>,
+    #[serde(default)]
+    pub partial: HashMap<String, ObjectProgress>,
+}
+
+/// Rows of an object already sent, they are skipped when the same version of it is read again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectProgress {
+    pub version: String,
+    pub rows: usize,
+}
+
+fn object_version(meta: &ObjectMeta) -> String {
+    meta.e_tag.clone().unwrap_or(meta.last_modified.to_rfc3339())
+}
+
+fn generic_error(source: impl Into<Box<dyn Error + Send + Sync>>) -> object_store::Error {
+    object_store::Error::Generic { store: "ObjectStore", source: source.into() }
+}
+
+impl ObjectStoreSource {
+    /// Builds a source for an S3-compatible bucket, credentials and region are read from the
+    /// usual `AWS_*` environment variables.
+    pub fn s3(endpoint: &str, bucket: &str, prefix: &str) -> Result<Self, object_store::Error> {
+        let store = AmazonS3Builder::from_env()
+            .with_endpoint(endpoint)
+            .with_bucket_name(bucket)
+            .with_allow_http(endpoint.starts_with("http://"))
+            .build()?;
+
+        Ok(Self {
+            store: Arc::new(store),
+            prefix: prefix.trim_matches('/').to_string(),
+            glob: None,
+            checkpoint_path: None,
+        })
+    }
+
+    fn relative_key<'a>(&self, location: &'a Path) -> &'a str {
+        location.as_ref().strip_prefix(&self.prefix).unwrap_or(location.as_ref()).trim_start_matches('/')
+    }
+
+    fn table_of(&self, location: &Path) -> String {
+        match self.relative_key(location).rsplit_once('/') {
+            Some((directory, _)) => directory.rsplit('/').next().unwrap_or(directory).to_string(),
+            None => self.prefix.rsplit('/').next().unwrap_or("").to_string()
+        }
+    }
+
+    /// Lists the objects matching the glob with a supported format.
+    async fn list_objects(&self) -> Result<Vec<ObjectMeta>, object_store::Error> {
+        let pattern = self.glob.as_deref().map(Pattern::new).transpose().map_err(generic_error)?;
+        let prefix = Path::from(self.prefix.as_str());
+
+        let mut objects = vec![];
+        let mut listing = self.store.list(Some(&prefix));
+        while let Some(meta) = listing.next().await {
+            let meta = meta?;
+            let key = self.relative_key(&meta.location);
+            if FileFormat::from_path(key).is_some() && pattern.as_ref().is_none_or(|p| p.matches(key)) {
+                objects.push(meta);
+            }
+        }
+        objects.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(objects)
+    }
+
+    fn load_checkpoint(&self) -> Checkpoint {
+        self.checkpoint_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the checkpoint, an error fails the migration: objects it does not record would be
+    /// sent again by the next run.
+    fn save_checkpoint(&self, checkpoint: &Checkpoint) -> Result<(), object_store::Error> {
+        if let Some(path) = &self.checkpoint_path {
+            let content = serde_json::to_string_pretty(checkpoint).map_err(generic_error)?;
+            fs::write(path, content).map_err(|e| generic_error(format!("Could not write checkpoint {} - {}", path, e)))?;
+        }
+        Ok(())
+    }
+
+    /// Saves that the first `rows` rows of an object were sent.
+    fn save_progress(&self, checkpoint: &mut Checkpoint, key: &str, version: &str, rows: usize) -> Result<(), object_store::Error> {
+        checkpoint.partial.insert(key.to_string(), ObjectProgress { version: version.to_string(), rows });
+        self.save_checkpoint(checkpoint)
+    }
+
+    /// Opens an object for reading, see `ObjectReader`.
+    pub async fn open_object(&self, meta: &ObjectMeta) -> Result<ObjectReader, object_store::Error> {
+        let format = FileFormat::from_path(meta.location.as_ref())
+            .ok_or_else(|| generic_error(format!("{} is not a supported format", meta.location)))?;
+        let result = self.store.get(&meta.location).await?;
+
+        Ok(ObjectReader {
+            format,
+            reader: RecordReader::new(format),
+            result: Some(result),
+            stream: None,
+        })
+    }
+}
+
+/// Reads the rows of an object, streamable formats are read chunk by chunk as they are downloaded
+/// so objects do not have to fit in memory; JSON and Parquet objects are read whole.
+pub struct ObjectReader {
+    format: FileFormat,
+    reader: RecordReader,
+    result: Option<GetResult>,
+    stream: Option<BoxStream<'static, Result<Bytes, object_store::Error>>>,
+}
+
+impl ObjectReader {
+    /// Returns the next rows of the object, None once it has been read completely.
+    pub async fn next_rows(&mut self) -> Option<Result<Vec<FileRow>, object_store::Error>> {
+        if let Some(result) = self.result.take() {
+            if self.format.is_streamable() {
+                self.stream = Some(result.into_stream());
+            } else {
+                let bytes = match result.bytes().await {
+                    Ok(bytes) => bytes,
+                    Err(e) => return Some(Err(e))
+                };
+                let rows = match self.format {
+                    FileFormat::Parquet => read_parquet(bytes),
+                    _ => read_json(&bytes),
+                };
+                return Some(rows.map_err(generic_error));
+            }
+        }
+
+        let stream = self.stream.as_mut()?;
+        match stream.next().await {
+            Some(Ok(chunk)) => Some(Ok(self.reader.feed(&chunk))),
+            Some(Err(e)) => Some(Err(e)),
+            None => {
+                self.stream = None;
+                Some(Ok(self.reader.finish()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Source for ObjectStoreSource {
+    type ErrorType = object_store::Error;
+    type TableType = String;
+    type RowType = FileRow;
+
+    /// A bucket has no databases, the prefix is listed as the only one.
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        Ok(vec![self.prefix.clone()])
+    }
+
+    async fn list_tables(&self, _database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        let mut tables: Vec<String> = vec![];
+        for meta in self.list_objects().await? {
+            let table = self.table_of(&meta.location);
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+        Ok(tables)
+    }
+
+    async fn get_table(&self, _database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        Ok(table_name.to_string())
+    }
+
+    async fn count(&self, _database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let mut count: i64 = 0;
+        for meta in self.list_objects().await? {
+            if self.table_of(&meta.location) == table_name {
+                let mut reader = self.open_object(&meta).await?;
+                while let Some(rows) = reader.next_rows().await {
+                    count += rows?.len() as i64;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        metadata.print_step(format!("Starting migrating objects of table {:?} to CrateDB {:?}", table, cratedb).as_str());
+        let batch_size: usize = 3000;
+        let mut total_documents_sent = 0;
+        let mut checkpoint = self.load_checkpoint();
+        let mut table_created = false;
+
+        let objects: Vec<ObjectMeta> = self.list_objects()
+            .await
+            .map_err(MigrationError::Source)?
+            .into_iter()
+            .filter(|meta| &self.table_of(&meta.location) == table)
+            .collect();
+        metadata.print_step(format!("Found {} objects", objects.len()).as_str());
+
+        for meta in objects {
+            let key = meta.location.to_string();
+            let version = object_version(&meta);
+            if checkpoint.objects.get(&key) == Some(&version) {
+                metadata.print_step(format!("Skipping object {}, already migrated", key).as_str());
+                continue;
+            }
+
+            // Rows sent by a run that stopped midway through the object are not sent again.
+            let skipped_rows = checkpoint.partial.get(&key).filter(|p| p.version == version).map_or(0, |p| p.rows);
+            let mut batch = DynamicBatch::new(batch_size);
+            let mut rows_read = 0;
+            let mut object_documents_sent = 0;
+            let mut read_error = None;
+
+            let mut reader = self.open_object(&meta).await.map_err(MigrationError::Source)?;
+
+            loop {
+                let rows = match reader.next_rows().await {
+                    Some(Ok(rows)) => rows,
+                    Some(Err(e)) => {
+                        read_error = Some(e);
+                        break;
+                    }
+                    None => break
+                };
+
+                for row in rows {
+                    rows_read += 1;
+                    if rows_read <= skipped_rows {
+                        continue;
+                    }
+                    let row: FileRow = row.into_iter().filter(|(name, _)| !ignored_columns.contains(&name.as_str())).collect();
+
+                    if let Some((columns, rows)) = batch.push(row) {
+                        // The table gets the types of the whole first batch.
+                        if !table_created {
+                            cratedb.create_table(schema, table, &column_definitions(&columns, &rows)).await?;
+                            table_created = true;
+                        }
+                        let documents_in_batch = rows.len();
+                        cratedb.send_batch(schema, table, &columns, rows).await?;
+                        object_documents_sent += documents_in_batch;
+                        self.save_progress(&mut checkpoint, &key, &version, skipped_rows + object_documents_sent)
+                            .map_err(MigrationError::Source)?;
+                    }
+                }
+            }
+
+            // Batches do not span objects, an object is only marked as migrated once all of its rows
+            // are sent; until then the rows sent so far are saved as its progress.
+            if let Some((columns, rows)) = batch.finish() {
+                if !table_created {
+                    cratedb.create_table(schema, table, &column_definitions(&columns, &rows)).await?;
+                    table_created = true;
+                }
+                let documents_in_batch = rows.len();
+                cratedb.send_batch(schema, table, &columns, rows).await?;
+                object_documents_sent += documents_in_batch;
+            }
+            total_documents_sent += object_documents_sent;
+
+            if let Some(e) = read_error {
+                self.save_progress(&mut checkpoint, &key, &version, skipped_rows + object_documents_sent)
+                    .map_err(MigrationError::Source)?;
+                return Err(MigrationError::Source(e));
+            }
+            checkpoint.partial.remove(&key);
+            checkpoint.objects.insert(key.clone(), version);
+            self.save_checkpoint(&checkpoint).map_err(MigrationError::Source)?;
+            metadata.print_step(format!("Sent {:?} rows from {}", &object_documents_sent, key).as_str());
+        }
+
+        metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
+    }
+
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
+        Ok(row.into_iter().map(|(_, v)| v).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::memory::InMemory;
+    use object_store::PutPayload;
+    use super::*;
+    use crate::testing::MemorySink;
+
+    async fn source_with_objects() -> ObjectStoreSource {
+        let store = InMemory::new();
+        let objects = vec![
+            ("exports/users/2024-01.jsonl", "{\"id\": 1, \"name\": \"a\"}\n{\"id\": 2, \"name\": \"b\"}"),
+            ("exports/users/2024-02.csv", "id,name\n3,\"multi\nline\"\n4,\n"),
+            ("exports/orders/all.json", "[{\"id\": 1}, {\"id\": 2}, {\"id\": 3}]"),
+            ("exports/orders/readme.txt", "not a data file"),
+        ];
+        for (key, content) in objects {
+            store.put(&Path::from(key), PutPayload::from(content)).await.unwrap();
+        }
+
+        ObjectStoreSource {
+            store: Arc::new(store),
+            prefix: "exports".to_string(),
+            glob: None,
+            checkpoint_path: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_count() {
+        let source = source_with_objects().await;
+
+        assert_eq!(source.list_tables("").await.unwrap(), vec!["orders", "users"]);
+        assert_eq!(source.count("", "users").await.unwrap(), 4);
+        assert_eq!(source.count("", "orders").await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_glob() {
+        let mut source = source_with_objects().await;
+        source.glob = Some("users/*.csv".to_string());
+
+        assert_eq!(source.list_tables("").await.unwrap(), vec!["users"]);
+        assert_eq!(source.count("", "users").await.unwrap(), 2);
+
+        source.glob = Some("users/[".to_string());
+        assert!(source.list_tables("").await.is_err());
+    }
+
+    #[test]
+    fn test_record_reader_chunks() {
+        let mut reader = RecordReader::new(FileFormat::Csv);
+        let mut rows = reader.feed(b"id,na");
+        rows.extend(reader.feed(b"me\n1,\"a\nb\""));
+        assert!(rows.is_empty());
+        rows.extend(reader.feed(b"\n2,c"));
+        rows.extend(reader.finish());
+
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(&rows[0][1], (k, CValue::String(v)) if k == "name" && v == "a\nb"));
+        assert!(matches!(&rows[1][0], (k, CValue::String(v)) if k == "id" && v == "2"));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint() {
+        let path = std::env::temp_dir().join("cdctest_objectstore_checkpoint.json");
+        let _ = fs::remove_file(&path);
+        let mut source = source_with_objects().await;
+        source.checkpoint_path = Some(path.to_str().unwrap().to_string());
+
+        // A run that stopped after the first row of the object only sends the second one.
+        let objects = source.list_objects().await.unwrap();
+        let meta = objects.iter().find(|m| m.location.as_ref().ends_with("2024-01.jsonl")).unwrap();
+        let mut checkpoint = Checkpoint::default();
+        source.save_progress(&mut checkpoint, meta.location.as_ref(), &object_version(meta), 1).unwrap();
+
+        let mut metadata = Metadata::new();
+        metadata.start();
+        let sink = MemorySink::default();
+        source.migrate_table_to_cratedb("doc", &"users".to_string(), vec![], sink.clone(), &mut metadata).await.unwrap();
+        let sent: usize = sink.batches.lock().unwrap().iter().map(|(_, rows)| rows.len()).sum();
+        assert_eq!(sent, 3);
+        let checkpoint = source.load_checkpoint();
+        assert_eq!(checkpoint.objects.len(), 2);
+        assert!(checkpoint.partial.is_empty());
+
+        // An object that can not be parsed is an error and is not marked as migrated.
+        source.store.put(&Path::from("exports/orders/broken.json"), PutPayload::from("[{\"id\": ")).await.unwrap();
+        let result = source.migrate_table_to_cratedb("doc", &"orders".to_string(), vec![], MemorySink::default(), &mut metadata).await;
+        assert!(matches!(result, Err(MigrationError::Source(_))));
+        assert!(!source.load_checkpoint().objects.contains_key("exports/orders/broken.json"));
+        fs::remove_file(&path).unwrap();
+
+        // A checkpoint that can not be written fails the migration instead of panicking.
+        source.checkpoint_path = Some(std::env::temp_dir().join("cdctest_missing_dir").join("checkpoint.json").to_str().unwrap().to_string());
+        let result = source.migrate_table_to_cratedb("doc", &"users".to_string(), vec![], MemorySink::default(), &mut metadata).await;
+        assert!(matches!(result, Err(MigrationError::Source(e)) if e.to_string().contains("Could not write checkpoint")));
+    }
+}
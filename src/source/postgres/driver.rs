@@ -1,18 +1,17 @@
-use std::collections::HashSet;
 use std::net::IpAddr;
 use async_trait::async_trait;
-use chrono::{NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use futures::StreamExt;
-use mongodb::bson::Document;
-use mongodb::Collection;
-use sqlx::{Column, Error, Pool, Postgres, Row, TypeInfo};
+use sqlx::{Column, Error, Pool, Postgres, Row, TypeInfo, ValueRef};
 
-use sqlx::postgres::{PgPoolOptions, PgRow};
-use crate::experiment::data::CValue;
+use sqlx::postgres::{PgPoolOptions, PgRow, PgValueFormat};
+use crate::experiment::data::{date_to_epoch_days, CValue};
+use crate::experiment::predicate::Predicate;
 use crate::metadata::Metadata;
-use crate::sink::cratedb::driver::CrateDB;
-use crate::source::mongodb::driver::{StringRow};
-use crate::source::source::{Sink, Source};
+use crate::source::filter::{get_filter, Dialect, Filters};
+use crate::source::source::{MigrationError, Sink, Source};
+use crate::source::watermark::{Incremental, WatermarkTracker};
+use crate::utils::quote_identifier;
 
 macro_rules! pg2normalized {
     ($row:expr, $name:expr, $type_name:ty, $result_type:expr) => {
@@ -47,111 +46,192 @@ macro_rules! pg2normalized {
 
 pub struct PostgresSource {
     pub(crate) uri: String,
+    pub(crate) filters: Filters,
+    pub(crate) incremental: Incremental,
 }
 
-#[async_trait]
-impl Source for PostgresSource {
-    type PoolType = Pool<Postgres>;
-    type ErrorType = sqlx::Error;
-    type ClientType = ();
-    type DatabaseType = ();
-    type TableType = String;
-    type RowType = PgRow;
+impl PostgresSource {
+    pub fn new(uri: &str) -> Self {
+        Self { uri: uri.to_string(), filters: Filters::new(), incremental: Incremental::default() }
+    }
 
-    async fn get_pool(&self) -> Result<Self::PoolType, Self::ErrorType> {
+    /// Only migrates the rows of `table` (`<schema>.<table>` or `<table>`) matching `predicate`. It is
+    /// added to the `WHERE` clause of the incremental watermark; dotted columns read JSON with `->>`.
+    pub fn with_filter(mut self, table: &str, predicate: Predicate) -> Self {
+        self.filters.insert(table.to_string(), predicate);
+        self
+    }
+
+    /// Loads tables incrementally, see `Incremental`.
+    pub fn with_incremental(mut self, incremental: Incremental) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    pub(crate) async fn get_pool(&self) -> Result<Pool<Postgres>, sqlx::Error> {
         let pool = PgPoolOptions::new()
             .max_connections(5)
-            .connect(&*self.uri)
+            .connect(&self.uri)
             .await?;
         Ok(pool)
     }
 
-    async fn get_client(&self) -> Result<Self::ClientType, Self::ErrorType> {
-        todo!()
+    /// Returns the columns of a table in order with their CrateDB type, None for the JSON ones, and
+    /// `PRIMARY KEY` appended to the columns of the primary key.
+    async fn get_columns(&self, pool: &Pool<Postgres>, schema: &str, table_name: &str) -> Result<Vec<(String, Option<String>)>, sqlx::Error> {
+        let rows: Vec<(String, String, bool)> = sqlx::query_as(
+            "SELECT c.column_name::TEXT, c.udt_name::TEXT, EXISTS ( \
+                 SELECT 1 FROM information_schema.table_constraints t \
+                 JOIN information_schema.key_column_usage k \
+                   ON k.constraint_name = t.constraint_name AND k.table_schema = t.table_schema AND k.table_name = t.table_name \
+                 WHERE t.constraint_type = 'PRIMARY KEY' AND t.table_schema = c.table_schema \
+                   AND t.table_name = c.table_name AND k.column_name = c.column_name) \
+             FROM information_schema.columns c \
+             WHERE c.table_schema = $1 AND c.table_name = $2 ORDER BY c.ordinal_position"
+        )
+            .bind(schema)
+            .bind(table_name)
+            .fetch_all(pool)
+            .await?;
+        Ok(rows.into_iter().map(|(name, udt_name, is_primary_key)| {
+            let ddl = udt_to_cratedb(&udt_name).map(|ddl| match is_primary_key {
+                true => format!("{} PRIMARY KEY", ddl),
+                false => ddl
+            });
+            (name, ddl)
+        }).collect())
     }
-    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
-        println!("Listing databases");
+}
 
-        let pool: Self::PoolType = self.get_pool().await.unwrap();
-        let result = sqlx::query("SELECT datname as name FROM pg_database WHERE datistemplate = false").fetch_all(&pool).await;
+/// Decodes a NUMERIC in the binary format to its text, e.g. `-12.50`. The format has the digits in
+/// base 10000, the weight of the first one, the sign and the number of decimal places.
+fn decode_numeric(bytes: &[u8]) -> Option<String> {
+    let field = |i: usize| bytes.get(i * 2..i * 2 + 2).map(|b| i16::from_be_bytes([b[0], b[1]]));
+    let (ndigits, weight, sign, scale) = (field(0)?, field(1)?, field(2)? as u16, field(3)?);
+    let digits: Vec<i16> = (0..ndigits.max(0) as usize).map(|i| field(4 + i)).collect::<Option<_>>()?;
+    match sign {
+        0xC000 => return Some("NaN".to_string()),
+        0xD000 => return Some("Infinity".to_string()),
+        0xF000 => return Some("-Infinity".to_string()),
+        _ => {}
+    }
 
-        match result { 
-            Ok(result) => {
-                let tables: Vec<String> = result.iter().map(|row| row.try_get("name").expect("Column not found, does the query return a column called name")).collect();
-                Ok(tables)
-            }
-            Err(e) => {
-                println!("Error - {:?}", e);
-                Err(e)
-            }
+    // The digit of weight `w` is at `weight - w`, the groups that are not stored are zeros.
+    let group = |w: i32| usize::try_from(weight as i32 - w).ok().and_then(|i| digits.get(i)).copied().unwrap_or(0);
+    let mut text = if sign == 0x4000 { "-".to_string() } else { String::new() };
+    match weight < 0 {
+        true => text.push('0'),
+        false => {
+            text.push_str(&group(weight as i32).to_string());
+            (0..weight as i32).rev().for_each(|w| text.push_str(&format!("{:04}", group(w))));
         }
     }
-    async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType> {
-        todo!()
+    if scale > 0 {
+        let fraction: String = (1..=(scale as i32 + 3) / 4).map(|w| format!("{:04}", group(-w))).collect();
+        text.push('.');
+        text.push_str(&fraction[..scale as usize]);
     }
+    Some(text)
+}
 
-    async fn get_database(&self, database: &str) -> Result<Self::DatabaseType, Self::ErrorType> {
-        todo!()
+/// Maps the `udt_name` of a Postgres column to a CrateDB type, arrays are the element's name after
+/// an underscore, e.g. `_int4`. JSON is None: it holds objects or arrays, CrateDB infers its type
+/// on the first insert.
+fn udt_to_cratedb(udt_name: &str) -> Option<String> {
+    if let Some(element) = udt_name.strip_prefix('_') {
+        return udt_to_cratedb(element).map(|ddl| format!("ARRAY({})", ddl));
     }
+    let ddl = match udt_name {
+        "int2" => "SMALLINT",
+        "int4" => "INTEGER",
+        "int8" => "BIGINT",
+        "float4" => "REAL",
+        "float8" => "DOUBLE PRECISION",
+        "numeric" => "NUMERIC",
+        "bool" => "BOOLEAN",
+        "timestamp" => "TIMESTAMP WITHOUT TIME ZONE",
+        "timestamptz" | "date" => "TIMESTAMP WITH TIME ZONE",
+        "inet" => "IP",
+        "json" | "jsonb" => return None,
+        _ => "TEXT"
+    };
+    Some(ddl.to_string())
+}
 
-    async fn get_table(&self, database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
-        todo!()
-    }
+#[async_trait]
+impl Source for PostgresSource {
+    type ErrorType = sqlx::Error;
+    type TableType = String;
+    type RowType = PgRow;
 
-    async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
-        todo!()
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        let pool = self.get_pool().await?;
+        let result = sqlx::query("SELECT datname as name FROM pg_database WHERE datistemplate = false").fetch_all(&pool).await?;
+        result.iter().map(|row| row.try_get("name")).collect()
+    }
+    // The pool is connected to one database, `database` is the schema like in the migrations.
+    async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        let pool = self.get_pool().await?;
+        sqlx::query_scalar(
+            "SELECT table_name::TEXT FROM information_schema.tables \
+             WHERE table_schema = $1 AND table_type = 'BASE TABLE' ORDER BY table_name"
+        )
+            .bind(database)
+            .fetch_all(&pool)
+            .await
     }
 
-    async fn migrate_table_to_cratedb_pg(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata) {
-        todo!()
+    async fn get_table(&self, _database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        Ok(table_name.to_string())
     }
 
+    async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let pool = self.get_pool().await?;
+        sqlx::query_scalar(&format!(r#"SELECT COUNT(*) FROM "{}"."{}""#, database, table_name)).fetch_one(&pool).await
+    }
 
-    async fn migrate_table_to_cratedb(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata) {
-        let pool = self.get_pool().await.unwrap();
-        let mut columns: Vec<String> = vec![];
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        let pool = self.get_pool().await.map_err(MigrationError::Source)?;
         let mut buffer: Vec<Vec<CValue>> = vec![];
         let batch_size = 1;
         let mut total_documents_sent = 0;
 
-        // Compute the difference between the table's column and `ignored_columns`, we only want to
-        // select the actual data the user wants.
-        if !ignored_columns.is_empty() {
-            let one_row_query = format!("SELECT * FROM {}.{} LIMIT 1", &schema, &table);
-            let one_row = sqlx::query(&*one_row_query).fetch_one(&pool).await;
-            match one_row {
-                Ok(row) => {
-                    let one_row_columns: Vec<String> = row.columns().iter().map(|x| String::from(x.name())).collect();
-                    let set1: HashSet<String> = one_row_columns.into_iter().collect();
-                    let set2: HashSet<String> = ignored_columns.into_iter().map(|i| String::from(i)).collect();
-
-                    let difference: HashSet<String> = set1.difference(&set2).cloned().collect();
-                    columns = difference.into_iter().collect();
-                }
-                Err(e) => panic!("{}", format!("{} - Could not get a LIMIT one row to ignore columns. Is there data in the table?", e))
-            }
+        // The target table gets the source's primary key, rows read again after the watermark are
+        // upserted instead of inserted twice.
+        let source_columns: Vec<(String, Option<String>)> = self.get_columns(&pool, schema, table)
+            .await
+            .map_err(MigrationError::Source)?
+            .into_iter()
+            .filter(|(name, _)| !ignored_columns.contains(&name.as_str()))
+            .collect();
+        let definitions: Vec<(String, String)> = source_columns
+            .iter()
+            .filter_map(|(name, ddl)| Some((name.clone(), ddl.clone()?)))
+            .collect();
+        cratedb.create_table(schema, table, &definitions).await?;
+
+        let columns: Vec<String> = source_columns.into_iter().map(|(name, _)| name).collect();
+        let selected: Vec<String> = columns.iter().map(|name| quote_identifier(name)).collect();
+        let mut query_1 = format!("SELECT {} FROM {}.{}", selected.join(", "), quote_identifier(schema), quote_identifier(table));
+        if let Some(predicate) = self.incremental.predicate(schema, table, get_filter(&self.filters, schema, table)) {
+            query_1 = format!("{} WHERE {}", query_1, predicate.to_sql(Dialect::Postgres));
         }
-        let query_1 = format!("SELECT {} FROM {}.{}", &columns.join(","), &schema, &table);
+        let mut watermark = self.incremental.get_column(schema, table).map(|column| WatermarkTracker::new(column));
         let mut rows_stream = sqlx::query(&query_1).fetch(&pool);
 
         metadata.print_step("Starting connections");
 
         while let Some(row) = rows_stream.next().await {
-            let mut normalized_row: Vec<CValue> = vec![];
-            let mut columns: Vec<String> = vec![];
-
-            match row {
-                Ok(row_obj) => {
-                    columns = row_obj.columns().iter().map(|x| String::from(x.name())).collect();
-                    normalized_row = self.row_to_normalized_row(row_obj).await;
-                }
-                Err(e) => {}
+            let row_obj = row.map_err(MigrationError::Source)?;
+            let normalized_row = self.row_to_normalized_row(row_obj).await.map_err(MigrationError::Source)?;
+            if let Some(watermark) = &mut watermark {
+                watermark.observe(&columns, &normalized_row);
             }
 
             let buffer_len = &buffer.len();
 
             if buffer_len == &batch_size {
-                cratedb.send_batch("doc", &table, &columns, buffer).await;
+                cratedb.send_batch(schema, table, &columns, buffer).await?;
                 metadata.print_step(format!("Sent batch of {}", buffer_len).as_str());
                 total_documents_sent += buffer_len;
                 buffer = vec![];
@@ -161,16 +241,18 @@ impl Source for PostgresSource {
 
         if !buffer.is_empty() {
             let buffer_len = &buffer.len();
-            cratedb.send_batch("doc", &table, &columns, buffer).await;
+            cratedb.send_batch(schema, table, &columns, buffer).await?;
             metadata.print_step(format!("Sent batch of {}", &buffer_len).as_str());
             total_documents_sent += buffer_len;
-;
         }
+
+        self.incremental.finish_table(schema, table, watermark.and_then(|w| w.finish()), metadata)?;
         metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
-        metadata.print_step(format!("Rows per seconds: {}", (total_documents_sent as u128) / metadata.elapsed().as_millis() * 1000).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
     }
 
-    async fn row_to_normalized_row(&self, row: Self::RowType) -> Vec<CValue> {
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
         let mut new_row: Vec<CValue> = vec![];
         for col in row.columns() {
             let name = col.name();
@@ -182,7 +264,7 @@ impl Source for PostgresSource {
                     new_row.push(pg2normalized!(row, name, String, CValue::String))
                 }
                 "INET" => {
-                    new_row.push(pg2normalized!(row, name, IpAddr, CValue::String, |v:IpAddr|v.to_string()))
+                    new_row.push(pg2normalized!(row, name, IpAddr, CValue::Ip))
                 }
                 "INT2" => {
                     new_row.push(pg2normalized!(row, name, i16, CValue::I16))
@@ -190,17 +272,37 @@ impl Source for PostgresSource {
                 "INT4" => {
                     new_row.push(pg2normalized!(row, name, i32, CValue::I32))
                 }
+                "INT8" => {
+                    new_row.push(pg2normalized!(row, name, i64, CValue::I64))
+                }
                 "FLOAT4" => {
                     new_row.push(pg2normalized!(row, name, f32, CValue::Double32))
                 }
                 "FLOAT8" => {
                     new_row.push(pg2normalized!(row, name, f64, CValue::Double64))
                 }
+                "NUMERIC" => {
+                    let raw = row.try_get_raw(name)?;
+                    new_row.push(match (raw.is_null(), raw.format()) {
+                        (true, _) => CValue::None,
+                        (false, PgValueFormat::Text) => CValue::Decimal(raw.as_str().map_err(Error::Decode)?.to_string()),
+                        (false, PgValueFormat::Binary) => {
+                            let numeric = decode_numeric(raw.as_bytes().map_err(Error::Decode)?);
+                            CValue::Decimal(numeric.ok_or_else(|| Error::Decode(format!("Invalid NUMERIC value in {}", name).into()))?)
+                        }
+                    });
+                }
                 "BOOL" => {
                     new_row.push(pg2normalized!(row, name, bool, CValue::Bool))
                 }
                 "TIMESTAMP" => {
-                    new_row.push(pg2normalized!(row, name, NaiveDateTime, CValue::String, |v: NaiveDateTime|v.to_string()));
+                    new_row.push(pg2normalized!(row, name, NaiveDateTime, CValue::Timestamp, |v: NaiveDateTime|v.and_utc().timestamp_millis()));
+                }
+                "TIMESTAMPTZ" => {
+                    new_row.push(pg2normalized!(row, name, DateTime<Utc>, CValue::Timestamp, |v: DateTime<Utc>|v.timestamp_millis()));
+                }
+                "DATE" => {
+                    new_row.push(pg2normalized!(row, name, NaiveDate, CValue::Date, date_to_epoch_days));
                 }
                 "TEXT[]" => {
                     new_row.push(pg2normalized!(row, name, Vec<String>, CValue::VecString));
@@ -218,19 +320,11 @@ impl Source for PostgresSource {
                     new_row.push(pg2normalized!(row, name, Vec<f64>, CValue::VecF64))
                 }
                 _ => {
-                    let val = row.try_get(name);
-                    match val {
-                        Ok(v) => new_row.push(CValue::String(v)),
-                        Err(e) => panic!("Datatype is {} - {} ", type_name, e)
-                    }
+                    new_row.push(CValue::String(row.try_get(name)?));
                 }
             };
         };
-        return new_row;
-    }
-
-    fn row_to_vec_str(&self, row: Self::RowType) -> Vec<StringRow> {
-        todo!()
+        Ok(new_row)
     }
 }
 
@@ -243,8 +337,10 @@ mod tests {
 
     use crate::metadata::Metadata;
     use crate::sink::cratedb::driver::CrateDB;
-    use crate::source::postgres::driver::PostgresSource;
+    use crate::source::filter::Filters;
+    use crate::source::postgres::driver::{decode_numeric, udt_to_cratedb, PostgresSource};
     use crate::source::source::Source;
+    use crate::source::watermark::Incremental;
 
     #[tokio::test]
     async fn test_with_postgres() {
@@ -263,8 +359,7 @@ mod tests {
             host_port
         );
 
-        println!("{:?}", connection_string);
-        let pool = PgPool::connect(&connection_string).await.expect("TODO: panic message");
+        let pool = PgPool::connect(&connection_string).await.expect("Could not connect to the Postgres container");
         pool.execute(r#"
         CREATE TABLE IF NOT EXISTS data (
            id TEXT,
@@ -306,9 +401,11 @@ values  ('27c7719a-0f63-4612-9f2f-846f6dc745d9', 'Lorem ipsum dolor sit amet, co
         ('27c7719a-0f63-4612-9f2f-846f6dc745d9', 'Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor', '127.198.0.1', -32767, 32767, -2147483647, 2147483647, -2147483647, -0.21474813, 0.1844674407370955, true, null, '{-123456,-654321}', '{123456,654321}', '{January,February,March,April,May,June,July,August,September,October,November,December}', null, '{}', null, '2024-08-19 08:46:44.672056', '2024-08-19 06:46:44.672058 +00:00', '{"one": "two", "three": 4, "five": [6], "seven": {"eight": 9}}', '{0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000,0.23423423000000000}', '{0.10000000000000000}');"#;
         pool.execute(insert_data).await.expect("as");
         let postgres = PostgresSource {
-            uri: connection_string.to_string()
+            uri: connection_string.to_string(),
+            filters: Filters::new(),
+            incremental: Incremental::default(),
         };
-        let cratedb = CrateDB {};
+        let cratedb = CrateDB::default();
         let mut metadata = Metadata::new();
         metadata.start();
         let ignored_columns = vec![
@@ -321,13 +418,36 @@ values  ('27c7719a-0f63-4612-9f2f-846f6dc745d9', 'Lorem ipsum dolor sit amet, co
             "empty_nested_array_5",
             "datetime_2"
         ];
-        let curr_count = cratedb.count("doc", "data").await.unwrap();
-        postgres.migrate_table_to_cratedb("public", &String::from("data"), ignored_columns, cratedb.clone(), &mut metadata).await;
+        // The table is created in the source's schema.
+        let curr_count = cratedb.count("public", "data").await.unwrap_or(0);
+        postgres.migrate_table_to_cratedb("public", &String::from("data"), ignored_columns, cratedb.clone(), &mut metadata).await.unwrap();
 
-        let tables = cratedb.list_tables("doc").await.unwrap();
+        let tables = cratedb.list_tables("public").await.unwrap();
         assert!(!tables.is_empty());
         assert!(tables.contains(&"data".to_string()));
         sleep(Duration::from_secs(1)).await;
-        assert_eq!(cratedb.count("doc", "data").await.unwrap() , curr_count + 10)
+        assert_eq!(cratedb.count("public", "data").await.unwrap() , curr_count + 10)
+    }
+
+    #[test]
+    fn test_decode_numeric() {
+        // ndigits, weight, sign, dscale and the digits in base 10000.
+        let numeric = |fields: &[i16]| fields.iter().flat_map(|f| f.to_be_bytes()).collect::<Vec<u8>>();
+        assert_eq!(decode_numeric(&numeric(&[2, 0, 0x4000, 2, 12, 5000])).unwrap(), "-12.50");
+        assert_eq!(decode_numeric(&numeric(&[5, 4, 0, 0, 1844, 6744, 737, 955, 1615])).unwrap(), "18446744073709551615");
+        assert_eq!(decode_numeric(&numeric(&[1, 1, 0, 0, 1])).unwrap(), "10000");
+        assert_eq!(decode_numeric(&numeric(&[1, -1, 0, 4, 1])).unwrap(), "0.0001");
+        assert_eq!(decode_numeric(&numeric(&[0, 0, 0, 2])).unwrap(), "0.00");
+        assert_eq!(decode_numeric(&numeric(&[0, 0, 0xC000u16 as i16, 0])).unwrap(), "NaN");
+        assert!(decode_numeric(&numeric(&[2, 0, 0])).is_none());
+    }
+
+    #[test]
+    fn test_udt_to_cratedb() {
+        assert_eq!(udt_to_cratedb("int4").unwrap(), "INTEGER");
+        assert_eq!(udt_to_cratedb("_float8").unwrap(), "ARRAY(DOUBLE PRECISION)");
+        assert_eq!(udt_to_cratedb("timestamptz").unwrap(), "TIMESTAMP WITH TIME ZONE");
+        assert_eq!(udt_to_cratedb("uuid").unwrap(), "TEXT");
+        assert!(udt_to_cratedb("jsonb").is_none());
     }
 }
@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use redis::{cmd, Client, ErrorKind, RedisError};
+use serde_json::Value;
+
+use crate::experiment::data::CValue;
+use crate::experiment::trans::json_to_cvalue;
+use crate::metadata::Metadata;
+use crate::source::batch::{column_definitions, DynamicBatch};
+use crate::source::source::{MigrationError, Sink, Source};
+
+/// Source for a Redis keyspace, keys are grouped into tables by their prefix, e.g. the
+/// key `user:1000` belongs to the table `user`; a key without the delimiter, e.g. `version`, is
+/// its own table.
+///
+/// Every key becomes one or more rows depending on its type:
+/// * hash: one row, fields are columns.
+/// * string: one row with a `value` column, JSON strings are objects.
+/// * sorted set: one row per `(member, score)`.
+/// * stream: one row per entry in time order, with its `id`, `time` and fields as columns.
+/// * list and set: one row per element with a `value` column.
+///
+/// All rows have a `key` column with the full key.
+#[derive(Clone)]
+pub struct RedisSource {
+    pub(crate) uri: String,
+    /// Pattern passed to `SCAN MATCH`, e.g. `*` or `user:*`.
+    pub(crate) pattern: String,
+    /// Separator between the key prefix and the rest of the key.
+    pub(crate) delimiter: String,
+}
+
+const SCAN_COUNT: usize = 1000;
+
+/// Interprets a Redis string, JSON objects and arrays are parsed, everything else is a String.
+fn redis_string_to_cvalue(value: String) -> CValue {
+    if value.starts_with('{') || value.starts_with('[') {
+        if let Ok(json) = serde_json::from_str::<Value>(&value) {
+            return json_to_cvalue(json);
+        }
+    }
+    CValue::String(value)
+}
+
+/// Turns a flat `[field, value, field, value...]` reply into (column, value) pairs.
+fn field_pairs(values: Vec<String>) -> Vec<(String, CValue)> {
+    values
+        .chunks_exact(2)
+        .map(|pair| (pair[0].clone(), redis_string_to_cvalue(pair[1].clone())))
+        .collect()
+}
+
+impl RedisSource {
+    async fn get_connection(&self) -> Result<MultiplexedConnection, RedisError> {
+        self.get_client().await?.get_multiplexed_async_connection().await
+    }
+
+    fn key_prefix<'a>(&self, key: &'a str) -> &'a str {
+        key.split_once(self.delimiter.as_str()).map_or(key, |(prefix, _)| prefix)
+    }
+
+    /// Returns all the keys matching `pattern`, using SCAN so the server is not blocked.
+    async fn scan_keys(&self, connection: &mut MultiplexedConnection, pattern: &str) -> Result<Vec<String>, RedisError> {
+        let mut keys: Vec<String> = vec![];
+        let mut cursor: u64 = 0;
+        loop {
+            let (next_cursor, batch): (u64, Vec<String>) = cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(connection)
+                .await?;
+            keys.extend(batch);
+            cursor = next_cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        // SCAN can return the same key more than once.
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    /// Returns the keys matching `pattern` whose prefix is the table, the same keys `list_tables`
+    /// groups into it. The prefix is compared instead of matched as a glob, so tables with `*`, `?`
+    /// or `[` in their name only get their own keys.
+    async fn table_keys(&self, connection: &mut MultiplexedConnection, table_name: &str) -> Result<Vec<String>, RedisError> {
+        let keys = self.scan_keys(connection, &self.pattern).await?;
+        Ok(keys.into_iter().filter(|key| self.key_prefix(key) == table_name).collect())
+    }
+
+    /// Reads a key and turns it into rows of (column, value) pairs depending on its type.
+    pub async fn key_to_rows(&self, connection: &mut MultiplexedConnection, key: &str) -> Result<Vec<Vec<(String, CValue)>>, RedisError> {
+        let key_type: String = cmd("TYPE").arg(key).query_async(connection).await?;
+        let key_column = ("key".to_string(), CValue::String(key.to_string()));
+
+        let rows = match key_type.as_str() {
+            "hash" => {
+                let fields: Vec<String> = cmd("HGETALL").arg(key).query_async(connection).await?;
+                let mut row = vec![key_column];
+                row.extend(field_pairs(fields));
+                vec![row]
+            }
+            "string" => {
+                let value: Vec<u8> = cmd("GET").arg(key).query_async(connection).await?;
+                let value = String::from_utf8_lossy(&value).to_string();
+                vec![vec![key_column, ("value".to_string(), redis_string_to_cvalue(value))]]
+            }
+            "zset" => {
+                let members: Vec<(String, f64)> = cmd("ZRANGE").arg(key).arg(0).arg(-1).arg("WITHSCORES").query_async(connection).await?;
+                members
+                    .into_iter()
+                    .map(|(member, score)| vec![
+                        key_column.clone(),
+                        ("member".to_string(), CValue::String(member)),
+                        ("score".to_string(), CValue::Double64(score)),
+                    ])
+                    .collect()
+            }
+            "stream" => {
+                let mut rows = vec![];
+                let mut start = "-".to_string();
+                loop {
+                    let entries: Vec<(String, Vec<String>)> = cmd("XRANGE").arg(key).arg(&start).arg("+").arg("COUNT").arg(SCAN_COUNT).query_async(connection).await?;
+                    let Some((last_id, _)) = entries.last() else { break };
+                    // Exclusive range, so the last entry is not read twice.
+                    start = format!("({}", last_id);
+                    let entries_len = entries.len();
+
+                    for (id, fields) in entries {
+                        // Stream ids are `<milliseconds>-<sequence>`.
+                        let time = id.split_once('-').and_then(|(ms, _)| ms.parse::<i64>().ok());
+                        let mut row = vec![
+                            key_column.clone(),
+                            ("id".to_string(), CValue::String(id)),
+                            ("time".to_string(), time.map_or(CValue::None, CValue::Timestamp)),
+                        ];
+                        row.extend(field_pairs(fields));
+                        rows.push(row);
+                    }
+                    if entries_len < SCAN_COUNT {
+                        break;
+                    }
+                }
+                rows
+            }
+            "list" | "set" => {
+                let values: Vec<String> = if key_type == "list" {
+                    cmd("LRANGE").arg(key).arg(0).arg(-1).query_async(connection).await?
+                } else {
+                    cmd("SMEMBERS").arg(key).query_async(connection).await?
+                };
+                values
+                    .into_iter()
+                    .map(|v| vec![key_column.clone(), ("value".to_string(), redis_string_to_cvalue(v))])
+                    .collect()
+            }
+            // The key expired between SCAN and reading it.
+            "none" => vec![],
+            _ => return Err(RedisError::from((ErrorKind::TypeError, "Unsupported key type", format!("{} is a {}", key, key_type))))
+        };
+        Ok(rows)
+    }
+
+    pub(crate) async fn get_client(&self) -> Result<Client, RedisError> {
+        Client::open(self.uri.as_str())
+    }
+}
+
+#[async_trait]
+impl Source for RedisSource {
+    type ErrorType = RedisError;
+    type TableType = String;
+    type RowType = Vec<(String, CValue)>;
+
+    /// Only the database selected in the uri is read, e.g. `redis://localhost/2`.
+    async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType> {
+        Ok(vec![self.get_client().await?.get_connection_info().redis.db.to_string()])
+    }
+
+    async fn list_tables(&self, _database: &str) -> Result<Vec<String>, Self::ErrorType> {
+        let mut connection = self.get_connection().await?;
+        let mut tables: Vec<String> = vec![];
+        for key in self.scan_keys(&mut connection, &self.pattern).await? {
+            let prefix = self.key_prefix(&key).to_string();
+            if !tables.contains(&prefix) {
+                tables.push(prefix);
+            }
+        }
+        Ok(tables)
+    }
+
+    async fn get_table(&self, _database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType> {
+        Ok(table_name.to_string())
+    }
+
+    /// Number of keys in the table, not rows; a sorted set or stream key yields many rows.
+    async fn count(&self, _database: &str, table_name: &str) -> Result<i64, Self::ErrorType> {
+        let mut connection = self.get_connection().await?;
+        Ok(self.table_keys(&mut connection, table_name).await?.len() as i64)
+    }
+
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>> {
+        metadata.print_step(format!("Starting migrating keys {}{}* to CrateDB {:?}", table, self.delimiter, cratedb).as_str());
+        let mut connection = self.get_connection().await.map_err(MigrationError::Source)?;
+        let mut total_documents_sent = 0;
+        let mut batch = DynamicBatch::new(3000);
+        let mut table_created = false;
+
+        let keys = self.table_keys(&mut connection, table).await.map_err(MigrationError::Source)?;
+        metadata.print_step(format!("Found {} keys", keys.len()).as_str());
+
+        for key in keys {
+            let rows = self.key_to_rows(&mut connection, &key).await.map_err(MigrationError::Source)?;
+
+            for row in rows {
+                let row: Vec<(String, CValue)> = row
+                    .into_iter()
+                    .filter(|(name, _)| !ignored_columns.contains(&name.as_str()))
+                    .collect();
+
+                if let Some((columns, rows)) = batch.push(row) {
+                    // The table gets the types of the whole first batch.
+                    if !table_created {
+                        cratedb.create_table(schema, table, &column_definitions(&columns, &rows)).await?;
+                        table_created = true;
+                    }
+                    let documents_in_batch = rows.len();
+                    cratedb.send_batch(schema, table, &columns, rows).await?;
+                    total_documents_sent += documents_in_batch;
+                    metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
+                }
+            }
+        }
+
+        if let Some((columns, rows)) = batch.finish() {
+            if !table_created {
+                cratedb.create_table(schema, table, &column_definitions(&columns, &rows)).await?;
+            }
+            let documents_in_batch = rows.len();
+            cratedb.send_batch(schema, table, &columns, rows).await?;
+            total_documents_sent += documents_in_batch;
+            metadata.print_step(format!("Sent batch of {:?}", &documents_in_batch).as_str());
+        }
+
+        metadata.print_step(format!("Total records sent: {}", total_documents_sent).as_str());
+        metadata.print_rows_per_second(total_documents_sent);
+        Ok(())
+    }
+
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType> {
+        Ok(row.into_iter().map(|(_, v)| v).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use redis::cmd;
+    use testcontainers_modules::{redis::Redis, testcontainers::runners::AsyncRunner};
+
+    use crate::experiment::data::CValue;
+    use crate::metadata::Metadata;
+    use crate::source::redis::driver::RedisSource;
+    use crate::source::source::Source;
+    use crate::testing::MemorySink;
+
+    #[tokio::test]
+    async fn test_with_redis() {
+        let container = Redis::default().start().await.expect("Redis Started");
+        let host_ip = container.get_host().await.unwrap();
+        let host_port = container.get_host_port_ipv4(6379).await.unwrap();
+
+        let redis = RedisSource {
+            uri: format!("redis://{}:{}", host_ip, host_port),
+            pattern: "*".to_string(),
+            delimiter: ":".to_string(),
+        };
+        let mut connection = redis.get_connection().await.unwrap();
+
+        let _: () = cmd("HSET").arg("user:1").arg("name").arg("ana").arg("age").arg("31").query_async(&mut connection).await.unwrap();
+        let _: () = cmd("SET").arg("user:2").arg(r#"{"name": "bob"}"#).query_async(&mut connection).await.unwrap();
+        let _: () = cmd("ZADD").arg("scores:game").arg(10).arg("ana").arg(20).arg("bob").query_async(&mut connection).await.unwrap();
+        let _: () = cmd("XADD").arg("events:clicks").arg("1700000000000-0").arg("page").arg("/").query_async(&mut connection).await.unwrap();
+        let _: () = cmd("SET").arg("version").arg("3").query_async(&mut connection).await.unwrap();
+
+        let mut tables = redis.list_tables("").await.unwrap();
+        tables.sort();
+        assert_eq!(tables, vec!["events", "scores", "user", "version"]);
+        assert_eq!(redis.count("", "user").await.unwrap(), 2);
+        // Keys without the delimiter are a table of one key.
+        assert_eq!(redis.count("", "version").await.unwrap(), 1);
+
+        // Table names are not globs, `a*` does not get the keys of `ab`.
+        let _: () = cmd("SET").arg("a*:1").arg("x").query_async(&mut connection).await.unwrap();
+        let _: () = cmd("SET").arg("ab:1").arg("y").query_async(&mut connection).await.unwrap();
+        assert_eq!(redis.count("", "a*").await.unwrap(), 1);
+
+        // Only the keys matching the pattern are read.
+        let users = RedisSource { pattern: "user:*".to_string(), ..redis.clone() };
+        assert_eq!(users.count("", "version").await.unwrap(), 0);
+        assert_eq!(users.count("", "user").await.unwrap(), 2);
+
+        let hash = redis.key_to_rows(&mut connection, "user:1").await.unwrap();
+        assert_eq!(hash.len(), 1);
+        assert!(hash[0].iter().any(|(k, v)| k == "name" && matches!(v, CValue::String(s) if s == "ana")));
+
+        let json = redis.key_to_rows(&mut connection, "user:2").await.unwrap();
+        assert!(matches!(&json[0][1], (k, CValue::Object(_)) if k == "value"));
+
+        let zset = redis.key_to_rows(&mut connection, "scores:game").await.unwrap();
+        assert_eq!(zset.len(), 2);
+        assert!(matches!(&zset[1][2], (_, CValue::Double64(score)) if *score == 20.0));
+
+        let stream = redis.key_to_rows(&mut connection, "events:clicks").await.unwrap();
+        assert!(matches!(&stream[0][2], (k, CValue::Timestamp(1700000000000)) if k == "time"));
+
+        let mut metadata = Metadata::new();
+        metadata.start();
+        let sink = MemorySink::default();
+        users.migrate_table_to_cratedb("doc", &"user".to_string(), vec![], sink.clone(), &mut metadata).await.unwrap();
+        // The table is created from the first batch, the hash; `value` is added with the second one.
+        assert_eq!(*sink.columns.lock().unwrap(), vec!["key", "name", "age"]);
+        assert_eq!(sink.batches.lock().unwrap().len(), 2);
+    }
+}
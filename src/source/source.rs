@@ -1,39 +1,136 @@
+use std::fmt;
 use async_trait::async_trait;
-use mongodb::bson::{Document};
-use mongodb::Collection;
-use sqlx::{Postgres, QueryBuilder};
 use crate::experiment::data::CValue;
 use crate::metadata::Metadata;
-use crate::sink::cratedb::driver::CrateDB;
+use crate::experiment::data::TransformError;
+use crate::sink::file::driver::WriteFileError;
+use crate::sink::postgres::driver::EncodeError;
 use crate::source::mongodb::driver::{StringRow};
 
 #[async_trait]
 pub trait Source {
-    type PoolType;
     type ErrorType;
-    type ClientType;
-    type DatabaseType;
     type TableType;
     type RowType;
 
-    async fn get_pool(&self) -> Result<Self::PoolType, Self::ErrorType>;
-    async fn get_client(&self) -> Result<Self::ClientType, Self::ErrorType>;
     async fn list_databases(&self) -> Result<Vec<String>, Self::ErrorType>;
     async fn list_tables(&self, database: &str) -> Result<Vec<String>, Self::ErrorType>;
-    async fn get_database(&self, database: &str) -> Result<Self::DatabaseType, Self::ErrorType>;
     async fn get_table(&self, database: &str, table_name: &str) -> Result<Self::TableType, Self::ErrorType>;
 
     async fn count(&self, database: &str, table_name: &str) -> Result<i64, Self::ErrorType>;
-    async fn migrate_table_to_cratedb_pg(&self, schema: &str, table: &Collection<Document>, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata);
-    async fn migrate_table_to_cratedb(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: CrateDB, metadata: &mut Metadata);
-    async fn row_to_normalized_row(&self, row: Self::RowType) -> Vec<CValue>;
-    fn row_to_vec_str(&self, row: Self::RowType) -> Vec<StringRow>;
+    // Migrations write to any `Sink`, CrateDB is the usual one but e.g. `PostgresSink` can be used
+    // for reverse migrations.
+    async fn migrate_table_to_cratedb<S: Sink>(&self, schema: &str, table: &Self::TableType, ignored_columns: Vec<&str>, cratedb: S, metadata: &mut Metadata) -> Result<(), MigrationError<Self::ErrorType>>;
+    async fn row_to_normalized_row(&self, row: Self::RowType) -> Result<Vec<CValue>, Self::ErrorType>;
 }
 
+#[derive(Debug)]
+pub struct SinkError {
+    message: String,
+}
+
+impl SinkError {
+    pub fn new(message: impl Into<String>) -> Self {
+        SinkError { message: message.into() }
+    }
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error writing to the sink: {}", self.message)
+    }
+}
+
+macro_rules! impl_from_error {
+    ($($error:ty),+) => {
+        $(impl From<$error> for SinkError {
+            fn from(e: $error) -> Self {
+                SinkError { message: e.to_string() }
+            }
+        })+
+    };
+}
+
+impl_from_error!(sqlx::Error, reqwest::Error, std::io::Error, arrow::error::ArrowError, WriteFileError, EncodeError, TransformError);
+
+/// Error of a migration, either reading from the source or writing to the sink.
+#[derive(Debug)]
+pub enum MigrationError<E> {
+    Source(E),
+    Sink(SinkError),
+}
+
+impl<E> From<SinkError> for MigrationError<E> {
+    fn from(e: SinkError) -> Self {
+        MigrationError::Sink(e)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for MigrationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::Source(e) => write!(f, "Error reading from the source: {:?}", e),
+            MigrationError::Sink(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+#[async_trait]
+pub trait Sink: fmt::Debug + Send + Sync {
+    async fn create_table(&self, schema: &str, table_name: &str, columns: &[(String, String)]) -> Result<(), SinkError>;
+    /// Returns the columns of a table, empty if it does not exist or the sink has no schema.
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String>;
+    /// Adds a column, `dtype` is a CrateDB type like in `create_table`.
+    async fn add_column(&self, schema: &str, table_name: &str, column: &str, dtype: &str) -> Result<(), SinkError>;
+    /// Sends rows read as `StringRow`s, by default they are converted to CValues and sent with
+    /// `send_batch`.
+    async fn send_batch_http(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<StringRow>>) -> Result<(), SinkError> {
+        let rows = buffer.into_iter().map(|row| row.into_iter().map(CValue::from).collect()).collect();
+        self.send_batch(schema, table_name, columns, rows).await
+    }
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError>;
+}
+
+/// Sink that wraps another one, e.g. to filter or transform batches before sending them to `inner`.
+/// Tables and columns are created on `inner` as they are.
 #[async_trait]
-pub trait Sink {
-    fn build_insert_values_statement(&self, schema: &str, table_name: &str, columns: &Vec<String>) -> QueryBuilder<Postgres>;
-    fn get_bulk_args_query(&self, schema: &str, table_name: &str, columns: &Vec<String>) -> String;
-    async fn send_batch_http(&self, schema: &str, table_name: &str, columns: &Vec<String>, buffer: Vec<Vec<StringRow>>);
-    async fn send_batch(&self, schema: &str, table_name: &str, columns: &Vec<String>,buffer: Vec<Vec<CValue>>);
+pub trait SinkWrapper: fmt::Debug + Send + Sync {
+    type Inner: Sink;
+
+    fn inner(&self) -> &Self::Inner;
+    /// Whether the batches of a table go to `inner` untouched, then `StringRow`s are not converted.
+    fn passes_through(&self, _schema: &str, _table_name: &str) -> bool {
+        false
+    }
+    async fn wrap_batch_http(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<StringRow>>) -> Result<(), SinkError> {
+        if self.passes_through(schema, table_name) {
+            return self.inner().send_batch_http(schema, table_name, columns, buffer).await;
+        }
+        let rows = buffer.into_iter().map(|row| row.into_iter().map(CValue::from).collect()).collect();
+        self.wrap_batch(schema, table_name, columns, rows).await
+    }
+    async fn wrap_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError>;
 }
+
+#[async_trait]
+impl<W: SinkWrapper> Sink for W {
+    async fn create_table(&self, schema: &str, table_name: &str, columns: &[(String, String)]) -> Result<(), SinkError> {
+        self.inner().create_table(schema, table_name, columns).await
+    }
+
+    async fn list_columns(&self, schema: &str, table_name: &str) -> Vec<String> {
+        self.inner().list_columns(schema, table_name).await
+    }
+
+    async fn add_column(&self, schema: &str, table_name: &str, column: &str, dtype: &str) -> Result<(), SinkError> {
+        self.inner().add_column(schema, table_name, column, dtype).await
+    }
+
+    async fn send_batch_http(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<StringRow>>) -> Result<(), SinkError> {
+        self.wrap_batch_http(schema, table_name, columns, buffer).await
+    }
+
+    async fn send_batch(&self, schema: &str, table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        self.wrap_batch(schema, table_name, columns, buffer).await
+    }
+}
\ No newline at end of file
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::experiment::data::CValue;
+use crate::experiment::predicate::{compare, lookup, Predicate};
+use crate::metadata::Metadata;
+use crate::source::source::SinkError;
+
+/// Largest value of a table's watermark column copied so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watermark {
+    pub column: String,
+    /// A serialized `CValue` for SQL sources, canonical extended JSON for MongoDB.
+    pub value: Value,
+}
+
+/// Watermarks saved between runs, `<schema>.<table>` -> watermark.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Watermarks {
+    pub tables: HashMap<String, Watermark>,
+}
+
+/// Incremental loads for tables without CDC: every run only reads the rows whose watermark column,
+/// e.g. `updated_at` or an increasing id, is at least the largest value the previous run copied.
+/// Rows committed later with that same value are read too; the rows read twice are upserted, so
+/// they replace their old version, see `CrateDB::with_upserts`. The SQL sources create the target
+/// table with the source's primary key for that.
+///
+/// The watermark is saved to `path` once the whole table is sent, a failed run reads the same rows
+/// again on the next one.
+#[derive(Debug, Clone, Default)]
+pub struct Incremental {
+    /// `<schema>.<table>` or `<table>` -> watermark column.
+    pub(crate) columns: HashMap<String, String>,
+    pub(crate) path: Option<String>,
+}
+
+impl Incremental {
+    pub fn new(path: &str) -> Self {
+        Self { columns: HashMap::new(), path: Some(path.to_string()) }
+    }
+
+    pub fn with_table(mut self, table: &str, column: &str) -> Self {
+        self.columns.insert(table.to_string(), column.to_string());
+        self
+    }
+
+    /// Returns the watermark column of a table, None if it is not loaded incrementally.
+    pub fn get_column(&self, schema: &str, table_name: &str) -> Option<&String> {
+        self.columns.get(&format!("{}.{}", schema, table_name)).or(self.columns.get(table_name))
+    }
+
+    fn load(&self) -> Watermarks {
+        self.path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns the saved watermark of a table, None on the first run or if its column changed.
+    pub fn get_watermark(&self, schema: &str, table_name: &str) -> Option<Watermark> {
+        let column = self.get_column(schema, table_name)?;
+        self.load()
+            .tables
+            .remove(&format!("{}.{}", schema, table_name))
+            .filter(|watermark| &watermark.column == column)
+    }
+
+    pub fn save_watermark(&self, schema: &str, table_name: &str, watermark: Watermark) -> Result<(), io::Error> {
+        if let Some(path) = &self.path {
+            let mut watermarks = self.load();
+            watermarks.tables.insert(format!("{}.{}", schema, table_name), watermark);
+            fs::write(path, serde_json::to_string_pretty(&watermarks)?)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the watermark of a table after its last batch is sent, never before: a run that fails
+    /// midway keeps the previous watermark and reads the same rows again.
+    pub fn finish_table(&self, schema: &str, table_name: &str, watermark: Option<Watermark>, metadata: &mut Metadata) -> Result<(), SinkError> {
+        if let Some(watermark) = watermark {
+            metadata.print_step(format!("Saving watermark {} = {}", watermark.column, watermark.value).as_str());
+            self.save_watermark(schema, table_name, watermark)
+                .map_err(|e| SinkError::new(format!("Could not write the watermarks: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the predicate of the rows to read, the table's filter and the rows after its
+    /// watermark.
+    pub fn predicate(&self, schema: &str, table_name: &str, filter: Option<&Predicate>) -> Option<Predicate> {
+        let after_watermark = self.get_watermark(schema, table_name).and_then(|watermark| {
+            let value: CValue = serde_json::from_value(watermark.value).ok()?;
+            Some(Predicate::Gte(watermark.column, value))
+        });
+
+        match (filter, after_watermark) {
+            (Some(filter), Some(after_watermark)) => Some(Predicate::And(vec![filter.clone(), after_watermark])),
+            (filter, after_watermark) => after_watermark.or(filter.cloned())
+        }
+    }
+}
+
+/// Tracks the largest value of the watermark column over the rows read.
+pub struct WatermarkTracker {
+    pub column: String,
+    pub max: Option<CValue>,
+}
+
+impl WatermarkTracker {
+    pub fn new(column: &str) -> Self {
+        Self { column: column.to_string(), max: None }
+    }
+
+    pub fn observe(&mut self, columns: &[String], row: &[CValue]) {
+        let Some(value) = lookup(columns, row, &self.column) else { return };
+        if matches!(value, CValue::None | CValue::Unknown) {
+            return;
+        }
+        let is_greater = match &self.max {
+            Some(max) => compare(value, max) == Some(Ordering::Greater),
+            None => true
+        };
+        if is_greater {
+            self.max = Some(value.clone());
+        }
+    }
+
+    /// Returns the watermark to save, None if no row had a value.
+    pub fn finish(self) -> Option<Watermark> {
+        let value = serde_json::to_value(self.max?).ok()?;
+        Some(Watermark { column: self.column, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::experiment::data::CValue;
+    use crate::experiment::predicate::Predicate;
+    use crate::source::filter::Dialect;
+    use crate::source::watermark::{Incremental, WatermarkTracker};
+
+    #[test]
+    fn test_incremental_watermarks() {
+        let path = std::env::temp_dir().join("cdctest_watermarks.json");
+        let _ = fs::remove_file(&path);
+        let incremental = Incremental::new(path.to_str().unwrap()).with_table("users", "updated_at");
+        let tenant = Predicate::Eq("tenant".to_string(), CValue::I32(1));
+
+        // The first run reads everything the filter matches.
+        assert!(incremental.predicate("public", "users", None).is_none());
+        assert!(matches!(incremental.predicate("public", "users", Some(&tenant)), Some(Predicate::Eq(..))));
+
+        let columns = vec!["id".to_string(), "updated_at".to_string()];
+        let mut tracker = WatermarkTracker::new("updated_at");
+        tracker.observe(&columns, &[CValue::I32(1), CValue::String("2024-01-02 10:00:00".to_string())]);
+        tracker.observe(&columns, &[CValue::I32(2), CValue::None]);
+        tracker.observe(&columns, &[CValue::I32(3), CValue::String("2024-01-01 10:00:00".to_string())]);
+        incremental.save_watermark("public", "users", tracker.finish().unwrap()).unwrap();
+
+        let predicate = incremental.predicate("public", "users", Some(&tenant)).unwrap();
+        assert_eq!(predicate.to_sql(Dialect::Postgres), r#"("tenant" = 1 AND "updated_at" >= '2024-01-02 10:00:00')"#);
+
+        // Watermarks of another column are not used.
+        let changed = Incremental::new(path.to_str().unwrap()).with_table("users", "id");
+        assert!(changed.get_watermark("public", "users").is_none());
+        assert!(WatermarkTracker::new("id").finish().is_none());
+    }
+}
@@ -0,0 +1,46 @@
+//! Helpers shared by the tests.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::experiment::data::CValue;
+use crate::source::source::{Sink, SinkError};
+
+pub(crate) fn object(fields: Vec<(&str, CValue)>) -> CValue {
+    CValue::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect::<HashMap<String, CValue>>())
+}
+
+/// Columns and rows of one `send_batch` call.
+pub(crate) type Batch = (Vec<String>, Vec<Vec<CValue>>);
+
+/// Sink that keeps the columns of its one table and the batches sent to it in memory, clones share
+/// them so a clone can be passed to a migration.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct MemorySink {
+    pub(crate) columns: Arc<Mutex<Vec<String>>>,
+    pub(crate) batches: Arc<Mutex<Vec<Batch>>>,
+}
+
+#[async_trait]
+impl Sink for MemorySink {
+    async fn create_table(&self, _schema: &str, _table_name: &str, columns: &[(String, String)]) -> Result<(), SinkError> {
+        *self.columns.lock().unwrap() = columns.iter().map(|(name, _)| name.clone()).collect();
+        Ok(())
+    }
+
+    async fn list_columns(&self, _schema: &str, _table_name: &str) -> Vec<String> {
+        self.columns.lock().unwrap().clone()
+    }
+
+    async fn add_column(&self, _schema: &str, _table_name: &str, column: &str, _dtype: &str) -> Result<(), SinkError> {
+        self.columns.lock().unwrap().push(column.to_string());
+        Ok(())
+    }
+
+    async fn send_batch(&self, _schema: &str, _table_name: &str, columns: &[String], buffer: Vec<Vec<CValue>>) -> Result<(), SinkError> {
+        self.batches.lock().unwrap().push((columns.to_vec(), buffer));
+        Ok(())
+    }
+}
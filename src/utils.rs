@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 pub fn get_fqn_table(schema: &str, table_name: &str) -> String {
     let mut query: String = String::from("");
     query.push_str(format!(r#"{schema}.{table_name}"#).as_str());
-    return query;
+    query
     // if !schema.is_none() {
     //     let _schema = schema;
     //     // let _schema = schema.unwrap();
@@ -10,4 +12,23 @@ pub fn get_fqn_table(schema: &str, table_name: &str) -> String {
     //     query.push_str(format!(r#"{table_name}"#).as_str());
     // }
     // return query;
-}
\ No newline at end of file
+}
+
+/// Looks up the entry of a table by `<schema>.<table>` and then by `<table>`, e.g. its filter or
+/// schema.
+pub fn lookup_table<'a, V>(entries: &'a HashMap<String, V>, schema: &str, table_name: &str) -> Option<&'a V> {
+    entries.get(&format!("{}.{}", schema, table_name)).or(entries.get(table_name))
+}
+
+/// Quotes an identifier for SQL, e.g. a column name, so its case is kept.
+pub fn quote_identifier(name: &str) -> String {
+    format!(r#""{}""#, name.replace('"', r#""""#))
+}
+
+/// Returns a connection string without its credentials, e.g. to print it.
+pub fn redact_uri(uri: &str) -> String {
+    match (uri.find("://"), uri.rfind('@')) {
+        (Some(scheme), Some(at)) if at > scheme => format!("{}://***{}", &uri[..scheme], &uri[at..]),
+        _ => uri.to_string()
+    }
+}